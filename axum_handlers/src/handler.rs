@@ -1,18 +1,77 @@
 use async_trait::async_trait;
-use axum::http::StatusCode;
+use axum::body::Bytes;
+use axum::http::{HeaderMap, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::collections::HashMap;
 
+use crate::response::HandlerResponse;
+
 /// Result type for handlers
-pub type HandlerResult = Result<String, (StatusCode, String)>;
+pub type HandlerResult = Result<HandlerResponse, (StatusCode, String)>;
 
 /// Context passed to handlers
+#[derive(Debug, Default)]
 pub struct HandlerContext {
+    /// HTTP method of the request
+    pub method: Method,
     /// Path of the request
     pub path: String,
+    /// Named path parameters captured by the route, e.g. `id` for `/api/user/{id}`
+    pub path_params: HashMap<String, String>,
+    /// Headers sent by the client
+    pub headers: HeaderMap,
+    /// Parsed query string parameters
+    pub query: HashMap<String, String>,
+    /// Raw request body
+    pub body: Bytes,
     /// Parameters from the YAML configuration
     pub params: HashMap<String, String>,
     /// Static response from the YAML configuration (if any)
     pub static_response: Option<String>,
+    /// Who made the request, if the endpoint requires authentication
+    pub principal: Option<Principal>,
+}
+
+/// An authenticated client
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Principal {
+    /// Name of the API key, the user name, or the `sub` claim of a token
+    pub name: String,
+    /// How the client authenticated: `api_key`, `basic` or `jwt`
+    pub scheme: String,
+    /// Claims of the token, empty for other schemes
+    pub claims: serde_json::Map<String, serde_json::Value>,
+}
+
+impl HandlerContext {
+    /// Get a named path parameter
+    pub fn path_param(&self, name: &str) -> Option<&str> {
+        self.path_params.get(name).map(String::as_str)
+    }
+
+    /// Get a header value as a string, if present and valid UTF-8
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// Get the request body as UTF-8 text
+    pub fn body_text(&self) -> Result<&str, (StatusCode, String)> {
+        std::str::from_utf8(&self.body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Request body is not valid UTF-8: {}", e)))
+    }
+
+    /// Decode the request body as JSON
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, (StatusCode, String)> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid JSON body: {}", e)))
+    }
+
+    /// Decode the request body as `application/x-www-form-urlencoded`
+    pub fn form<T: DeserializeOwned>(&self) -> Result<T, (StatusCode, String)> {
+        serde_urlencoded::from_bytes(&self.body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid form body: {}", e)))
+    }
 }
 
 /// Trait for implementing custom handlers
@@ -29,7 +88,7 @@ pub struct StaticResponseHandler;
 impl Handler for StaticResponseHandler {
     async fn handle(&self, ctx: HandlerContext) -> HandlerResult {
         if let Some(response) = ctx.static_response {
-            Ok(response)
+            Ok(response.into())
         } else {
             Err((StatusCode::INTERNAL_SERVER_ERROR, "No static response configured".to_string()))
         }
@@ -42,7 +101,7 @@ pub struct HealthCheckHandler;
 #[async_trait]
 impl Handler for HealthCheckHandler {
     async fn handle(&self, _ctx: HandlerContext) -> HandlerResult {
-        Ok("OK".to_string())
+        Ok("OK".into())
     }
 }
 
@@ -52,6 +111,6 @@ pub struct StatusHandler;
 #[async_trait]
 impl Handler for StatusHandler {
     async fn handle(&self, _ctx: HandlerContext) -> HandlerResult {
-        Ok("{ \"status\": \"running\", \"version\": \"1.0.0\" }".to_string())
+        HandlerResponse::json(&serde_json::json!({ "status": "running", "version": "1.0.0" }))
     }
 } 
\ No newline at end of file
@@ -1,5 +1,11 @@
 pub mod handler;
 pub mod registry;
+pub mod response;
+pub mod static_files;
+pub mod template;
 
-pub use handler::{Handler, HandlerContext, HandlerResult};
-pub use registry::{HandlerRegistry, register_default_handlers}; 
\ No newline at end of file
+pub use handler::{Handler, HandlerContext, HandlerResult, Principal};
+pub use registry::{HandlerRegistry, register_default_handlers};
+pub use response::HandlerResponse;
+pub use static_files::StaticFilesHandler;
+pub use template::TemplateHandler; 
\ No newline at end of file
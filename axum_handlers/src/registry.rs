@@ -2,6 +2,8 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::handler::{Handler, HealthCheckHandler, StaticResponseHandler, StatusHandler};
+use crate::static_files::StaticFilesHandler;
+use crate::template::TemplateHandler;
 
 /// Registry for storing and retrieving handlers
 pub struct HandlerRegistry {
@@ -48,4 +50,6 @@ pub fn register_default_handlers(registry: &mut HandlerRegistry) {
     // Register specialized handlers
     registry.register("health", HealthCheckHandler);
     registry.register("status", StatusHandler);
+    registry.register("template", TemplateHandler);
+    registry.register("static_files", StaticFilesHandler);
 } 
\ No newline at end of file
@@ -0,0 +1,98 @@
+use axum::body::{Body, Bytes};
+use axum::http::header::{self, HeaderMap, HeaderName, HeaderValue};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::Serialize;
+
+use crate::handler::HandlerResult;
+
+/// Response returned by handlers
+#[derive(Debug, Clone)]
+pub struct HandlerResponse {
+    /// Status code of the response
+    pub status: StatusCode,
+    /// Headers of the response
+    pub headers: HeaderMap,
+    /// Body of the response
+    pub body: Bytes,
+}
+
+impl HandlerResponse {
+    /// Create a 200 response with the given body and no content type
+    pub fn new(body: impl Into<Bytes>) -> Self {
+        Self {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Create a 200 `text/plain` response
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::new(body.into()).with_content_type("text/plain; charset=utf-8")
+    }
+
+    /// Create a 200 `application/json` response by serializing a value
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> HandlerResult {
+        let body = serde_json::to_vec(value)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to serialize JSON: {}", e)))?;
+        Ok(Self::new(body).with_content_type("application/json"))
+    }
+
+    /// Create a 200 response with a binary body and the given content type
+    pub fn bytes(body: impl Into<Bytes>, content_type: &str) -> Self {
+        Self::new(body).with_content_type(content_type)
+    }
+
+    /// Create an empty 204 No Content response
+    pub fn no_content() -> Self {
+        Self::new(Bytes::new()).with_status(StatusCode::NO_CONTENT)
+    }
+
+    /// Create a 302 Found redirect to the given location
+    pub fn redirect(location: &str) -> Self {
+        Self::new(Bytes::new())
+            .with_status(StatusCode::FOUND)
+            .with_header(header::LOCATION, location)
+    }
+
+    /// Set the status code
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Set a header, ignoring values that are not valid header values
+    pub fn with_header(mut self, name: HeaderName, value: &str) -> Self {
+        if let Ok(value) = HeaderValue::from_str(value) {
+            self.headers.insert(name, value);
+        }
+        self
+    }
+
+    /// Set the `Content-Type` header
+    pub fn with_content_type(self, content_type: &str) -> Self {
+        self.with_header(header::CONTENT_TYPE, content_type)
+    }
+}
+
+impl From<String> for HandlerResponse {
+    fn from(body: String) -> Self {
+        Self::text(body)
+    }
+}
+
+impl From<&str> for HandlerResponse {
+    fn from(body: &str) -> Self {
+        Self::text(body)
+    }
+}
+
+impl IntoResponse for HandlerResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::SeekFrom;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, StatusCode};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::handler::{Handler, HandlerContext, HandlerResult};
+use crate::response::HandlerResponse;
+
+/// Characters escaped in links of directory listings, the trailing `/` of directories is kept
+const PATH_SEGMENT: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>').add(b'?').add(b'`').add(b'{').add(b'}');
+
+/// Handler that serves files from a directory.
+///
+/// The file is taken from the path parameter named by the `param` param (default `path`),
+/// e.g. `/assets/{*path}`, and looked up below the `root` param. The `index`, `spa`,
+/// `listing` and `precompressed` params control index files, single page app fallback,
+/// directory listings and `.br`/`.gz` variants.
+pub struct StaticFilesHandler;
+
+#[async_trait]
+impl Handler for StaticFilesHandler {
+    async fn handle(&self, ctx: HandlerContext) -> HandlerResult {
+        let options = Options::from_params(&ctx).await?;
+        let relative = ctx.path_param(&options.param).unwrap_or_default();
+
+        let Some(path) = resolve(&options.root, relative).await else {
+            return Err(not_found());
+        };
+
+        let metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            // Unknown routes of a single page app are served its index file
+            Err(_) if options.spa && !has_extension(relative) => {
+                return serve_file(&ctx, &options, &options.root.join(&options.index)).await;
+            }
+            Err(_) => return Err(not_found()),
+        };
+
+        if !metadata.is_dir() {
+            return serve_file(&ctx, &options, &path).await;
+        }
+
+        // Relative links in index files and listings need the trailing slash
+        if !ctx.path.ends_with('/') {
+            return Ok(HandlerResponse::redirect(&format!("{}/", ctx.path))
+                .with_status(StatusCode::MOVED_PERMANENTLY));
+        }
+
+        let index = path.join(&options.index);
+        if tokio::fs::metadata(&index).await.is_ok_and(|m| m.is_file()) {
+            serve_file(&ctx, &options, &index).await
+        } else if options.listing {
+            list_directory(&ctx.path, &path).await
+        } else {
+            Err(not_found())
+        }
+    }
+}
+
+struct Options {
+    root: PathBuf,
+    param: String,
+    index: String,
+    spa: bool,
+    listing: bool,
+    precompressed: bool,
+}
+
+impl Options {
+    async fn from_params(ctx: &HandlerContext) -> Result<Self, (StatusCode, String)> {
+        let root = ctx.params.get("root")
+            .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "static_files: the `root` param is not set".to_string()))?;
+        // Canonicalize so that symlinks can't be used to leave the root
+        let root = tokio::fs::canonicalize(root).await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("static_files: invalid root `{}`: {}", root, e)))?;
+        let flag = |name: &str| ctx.params.get(name).is_some_and(|value| value == "true");
+
+        Ok(Self {
+            root,
+            param: ctx.params.get("param").cloned().unwrap_or_else(|| "path".to_string()),
+            index: ctx.params.get("index").cloned().unwrap_or_else(|| "index.html".to_string()),
+            spa: flag("spa"),
+            listing: flag("listing"),
+            precompressed: flag("precompressed"),
+        })
+    }
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "Not Found".to_string())
+}
+
+// Join a request path to the root, refusing anything that would end up outside of it
+async fn resolve(root: &Path, relative: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for component in Path::new(relative).components() {
+        match component {
+            Component::Normal(name) if !name.to_string_lossy().contains('\\') => path.push(name),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    // Symlinks inside the root may point elsewhere, missing files are checked by the caller
+    match tokio::fs::canonicalize(&path).await {
+        Ok(canonical) if canonical.starts_with(root) => Some(canonical),
+        Ok(_) => None,
+        Err(_) => Some(path),
+    }
+}
+
+fn has_extension(relative: &str) -> bool {
+    relative.rsplit('/').next().is_some_and(|name| name.contains('.'))
+}
+
+async fn serve_file(ctx: &HandlerContext, options: &Options, path: &Path) -> HandlerResult {
+    let metadata = tokio::fs::metadata(path).await.map_err(|_| not_found())?;
+    if !metadata.is_file() {
+        return Err(not_found());
+    }
+
+    let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+    let etag = entity_tag(metadata.len(), modified);
+    let last_modified = httpdate::fmt_http_date(modified);
+
+    let mut response = HandlerResponse::new(Bytes::new())
+        .with_content_type(&content_type(path))
+        .with_header(header::ETAG, &etag)
+        .with_header(header::LAST_MODIFIED, &last_modified)
+        .with_header(header::ACCEPT_RANGES, "bytes");
+
+    if not_modified(&ctx.headers, &etag, modified) {
+        return Ok(response.with_status(StatusCode::NOT_MODIFIED));
+    }
+
+    // Serve a precompressed variant when the client accepts it
+    if options.precompressed {
+        response = response.with_header(header::VARY, "accept-encoding");
+        let accepted = ctx.header("accept-encoding").unwrap_or_default();
+        for (encoding, extension) in [("br", "br"), ("gzip", "gz")] {
+            if !accepts_encoding(accepted, encoding) {
+                continue;
+            }
+            let mut variant = path.as_os_str().to_owned();
+            variant.push(".");
+            variant.push(extension);
+            // Variants may be symlinks out of the root like any other file
+            let variant = match tokio::fs::canonicalize(&variant).await {
+                Ok(variant) if variant.starts_with(&options.root) => variant,
+                _ => continue,
+            };
+            if let Ok(body) = tokio::fs::read(&variant).await {
+                response.body = body.into();
+                return Ok(response.with_header(header::CONTENT_ENCODING, encoding));
+            }
+        }
+    }
+
+    let size = metadata.len();
+    // Multiple ranges are not supported, those requests get the whole file
+    let range = ctx.header("range")
+        .filter(|range| !range.contains(','))
+        .filter(|_| if_range_matches(&ctx.headers, &etag, &last_modified))
+        .map(|range| parse_range(range, size));
+
+    match range {
+        Some(Some((start, end))) => {
+            let mut file = tokio::fs::File::open(path).await.map_err(read_error)?;
+            file.seek(SeekFrom::Start(start)).await.map_err(read_error)?;
+            let mut body = vec![0; (end - start + 1) as usize];
+            file.read_exact(&mut body).await.map_err(read_error)?;
+
+            response.body = body.into();
+            Ok(response
+                .with_status(StatusCode::PARTIAL_CONTENT)
+                .with_header(header::CONTENT_RANGE, &format!("bytes {}-{}/{}", start, end, size)))
+        }
+        Some(None) => Ok(HandlerResponse::new(Bytes::new())
+            .with_status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .with_header(header::CONTENT_RANGE, &format!("bytes */{}", size))),
+        None => {
+            response.body = tokio::fs::read(path).await.map_err(read_error)?.into();
+            Ok(response)
+        }
+    }
+}
+
+fn read_error(e: std::io::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to read file: {}", e))
+}
+
+fn content_type(path: &Path) -> String {
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    if mime.type_() == mime_guess::mime::TEXT || mime.subtype() == mime_guess::mime::JAVASCRIPT {
+        format!("{}; charset=utf-8", mime.essence_str())
+    } else {
+        mime.essence_str().to_string()
+    }
+}
+
+fn entity_tag(size: u64, modified: SystemTime) -> String {
+    let modified = modified.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!("\"{:x}-{:x}\"", size, modified.as_nanos())
+}
+
+// Evaluate If-None-Match, or If-Modified-Since when it is absent
+fn not_modified(headers: &HeaderMap, etag: &str, modified: SystemTime) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return if_none_match.split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag);
+    }
+
+    headers.get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|date| httpdate::parse_http_date(date).ok())
+        .is_some_and(|since| {
+            // HTTP dates have a resolution of one second
+            let modified = modified.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            let since = since.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            modified <= since
+        })
+}
+
+// A Range request is only honoured if If-Range, when present, matches the current file
+fn if_range_matches(headers: &HeaderMap, etag: &str, last_modified: &str) -> bool {
+    match headers.get(header::IF_RANGE).and_then(|v| v.to_str().ok()) {
+        Some(if_range) => if_range == etag || if_range == last_modified,
+        None => true,
+    }
+}
+
+// Parse a single `bytes=` range into inclusive offsets, None if it can't be satisfied
+fn parse_range(range: &str, size: u64) -> Option<(u64, u64)> {
+    let spec = range.strip_prefix("bytes=")?.trim();
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            (size.saturating_sub(suffix), size.checked_sub(1)?)
+        }
+        (start, "") => (start.parse().ok()?, size.checked_sub(1)?),
+        (start, end) => (start.parse().ok()?, end.parse::<u64>().ok()?.min(size.checked_sub(1)?)),
+    };
+
+    (start <= end && start < size).then_some((start, end))
+}
+
+fn accepts_encoding(accept_encoding: &str, encoding: &str) -> bool {
+    accept_encoding.split(',').any(|item| {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let rejected = parts.any(|param| matches!(param.trim(), "q=0" | "q=0.0" | "q=0.00" | "q=0.000"));
+        name.eq_ignore_ascii_case(encoding) && !rejected
+    })
+}
+
+async fn list_directory(request_path: &str, dir: &Path) -> HandlerResult {
+    let mut reader = tokio::fs::read_dir(dir).await.map_err(read_error)?;
+    let mut entries = Vec::new();
+    while let Some(entry) = reader.next_entry().await.map_err(read_error)? {
+        let is_dir = entry.file_type().await.is_ok_and(|t| t.is_dir());
+        entries.push((!is_dir, entry.file_name().to_string_lossy().into_owned()));
+    }
+    // Directories first, then files, both by name
+    entries.sort();
+
+    let title = escape_html(request_path);
+    let mut html = format!("<!DOCTYPE html>\n<html>\n<head><meta charset=\"utf-8\"><title>Index of {0}</title></head>\n<body>\n<h1>Index of {0}</h1>\n<ul>\n", title);
+    if request_path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>\n");
+    }
+    for (is_file, name) in entries {
+        let name = if is_file { name } else { format!("{}/", name) };
+        let href = utf8_percent_encode(&name, PATH_SEGMENT).to_string();
+        html.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", href, escape_html(&name)));
+    }
+    html.push_str("</ul>\n</body>\n</html>\n");
+
+    Ok(HandlerResponse::new(html).with_content_type("text/html; charset=utf-8"))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
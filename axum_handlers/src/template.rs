@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use minijinja::{Environment, Error, ErrorKind, Value};
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::handler::{Handler, HandlerContext, HandlerResult};
+use crate::response::HandlerResponse;
+
+/// Handler that renders the configured response as a template.
+///
+/// Templates use Jinja syntax and can read `path`, `query`, `headers`, `body`, `params`,
+/// `principal`, `method` and `request_path`, and call `now()`, `timestamp()`, `uuid()`,
+/// `random_int(min, max)`, `random_float(min, max)`, `random_bool()` and `fake(kind)`.
+/// The `status` and `content_type` params set the status code and content type.
+pub struct TemplateHandler;
+
+#[async_trait]
+impl Handler for TemplateHandler {
+    async fn handle(&self, ctx: HandlerContext) -> HandlerResult {
+        let source = ctx.static_response.as_deref().unwrap_or_default();
+        let rendered = render(source, &ctx)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to render template: {}", e)))?;
+
+        let status = match ctx.params.get("status") {
+            Some(status) => status.parse::<u16>().ok()
+                .and_then(|status| StatusCode::from_u16(status).ok())
+                .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, format!("Invalid status param: {}", status)))?,
+            None => StatusCode::OK,
+        };
+
+        // Without a configured content type, JSON output is served as JSON
+        let content_type = match ctx.params.get("content_type") {
+            Some(content_type) => content_type.as_str(),
+            None if serde_json::from_str::<serde_json::Value>(&rendered).is_ok_and(|v| v.is_object() || v.is_array()) => "application/json",
+            None => "text/plain; charset=utf-8",
+        };
+
+        Ok(HandlerResponse::new(rendered)
+            .with_status(status)
+            .with_content_type(content_type))
+    }
+}
+
+/// Check that a template compiles, returning the error message if it doesn't
+pub fn check_template(source: &str) -> Result<(), String> {
+    environment()
+        .template_from_str(source)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Render a template with the request data of a handler context
+pub fn render(source: &str, ctx: &HandlerContext) -> Result<String, Error> {
+    let headers: HashMap<String, String> = ctx.headers.iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    // The body is exposed as JSON when it parses, otherwise as form fields or text
+    let body = serde_json::from_slice::<serde_json::Value>(&ctx.body)
+        .map(Value::from_serialize)
+        .or_else(|_| serde_urlencoded::from_bytes::<HashMap<String, String>>(&ctx.body).map(Value::from_serialize))
+        .unwrap_or(Value::UNDEFINED);
+
+    let context = minijinja::context! {
+        method => ctx.method.as_str(),
+        request_path => &ctx.path,
+        path => &ctx.path_params,
+        query => &ctx.query,
+        headers => headers,
+        body => body,
+        body_text => String::from_utf8_lossy(&ctx.body),
+        params => &ctx.params,
+        principal => &ctx.principal,
+    };
+
+    environment().render_str(source, context)
+}
+
+fn environment() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.add_function("now", now);
+    env.add_function("timestamp", timestamp);
+    env.add_function("uuid", || uuid::Uuid::new_v4().to_string());
+    env.add_function("random_int", random_int);
+    env.add_function("random_float", random_float);
+    env.add_function("random_bool", || rand::rng().random_bool(0.5));
+    env.add_function("fake", fake);
+    env
+}
+
+fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Current time in RFC 3339 format, in UTC
+fn now() -> String {
+    OffsetDateTime::from_unix_timestamp(timestamp() as i64)
+        .ok()
+        .and_then(|time| time.format(&Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+fn random_int(min: Option<i64>, max: Option<i64>) -> Result<i64, Error> {
+    let (min, max) = (min.unwrap_or(0), max.unwrap_or(100));
+    if min > max {
+        return Err(Error::new(ErrorKind::InvalidOperation, "random_int: min is greater than max"));
+    }
+    Ok(rand::rng().random_range(min..=max))
+}
+
+fn random_float(min: Option<f64>, max: Option<f64>) -> Result<f64, Error> {
+    let (min, max) = (min.unwrap_or(0.0), max.unwrap_or(1.0));
+    if min >= max {
+        return Err(Error::new(ErrorKind::InvalidOperation, "random_float: min must be less than max"));
+    }
+    Ok(rand::rng().random_range(min..max))
+}
+
+const FIRST_NAMES: &[&str] = &["Alice", "Bob", "Carol", "David", "Emma", "Farid", "Grace", "Hiro", "Ines", "Jonas", "Kemal", "Lena", "Mateo", "Nora", "Olu", "Priya"];
+const LAST_NAMES: &[&str] = &["Andersson", "Brown", "Chen", "Dubois", "Evans", "Fischer", "Garcia", "Hansen", "Ivanova", "Johnson", "Kim", "Lopez", "Meyer", "Nakamura", "Okafor", "Patel"];
+const CITIES: &[&str] = &["Amsterdam", "Berlin", "Cairo", "Denver", "Edinburgh", "Florence", "Gothenburg", "Helsinki", "Istanbul", "Jakarta", "Kyoto", "Lisbon", "Montreal", "Nairobi", "Oslo", "Porto"];
+const COMPANIES: &[&str] = &["Acme", "Globex", "Initech", "Umbrella", "Hooli", "Stark Industries", "Wayne Enterprises", "Cyberdyne", "Soylent", "Tyrell"];
+const WORDS: &[&str] = &["lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do", "eiusmod", "tempor", "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua"];
+const DOMAINS: &[&str] = &["example.com", "example.org", "example.net"];
+
+// Random fake data of the given kind
+fn fake(kind: &str) -> Result<String, Error> {
+    let mut rng = rand::rng();
+    let mut pick = |list: &[&str]| list.choose(&mut rng).copied().unwrap_or_default().to_string();
+
+    let value = match kind {
+        "first_name" => pick(FIRST_NAMES),
+        "last_name" => pick(LAST_NAMES),
+        "name" => format!("{} {}", pick(FIRST_NAMES), pick(LAST_NAMES)),
+        "email" => format!("{}.{}@{}", pick(FIRST_NAMES), pick(LAST_NAMES), pick(DOMAINS)).to_lowercase(),
+        "city" => pick(CITIES),
+        "company" => pick(COMPANIES),
+        "word" => pick(WORDS),
+        "sentence" => {
+            let mut sentence = (0..8).map(|_| pick(WORDS)).collect::<Vec<_>>().join(" ");
+            sentence[..1].make_ascii_uppercase();
+            sentence + "."
+        },
+        "phone" => format!("+1-555-{:03}-{:04}", rand::rng().random_range(0..1000), rand::rng().random_range(0..10000)),
+        "ipv4" => format!("192.0.2.{}", rand::rng().random_range(1..255)),
+        _ => return Err(Error::new(ErrorKind::InvalidOperation, format!(
+            "fake: unknown kind `{}`, expected one of name, first_name, last_name, email, city, company, word, sentence, phone, ipv4",
+            kind,
+        ))),
+    };
+    Ok(value)
+}
@@ -1,6 +1,6 @@
-use axum_handlers::{Handler, HandlerContext, HandlerResult, HandlerRegistry};
+use axum_handlers::{Handler, HandlerContext, HandlerResponse, HandlerResult, HandlerRegistry};
 use async_trait::async_trait;
-use axum::http::StatusCode;
+use axum::http::{Method, StatusCode};
 
 // Example of a custom handler for user data
 pub struct UserHandler;
@@ -8,8 +8,8 @@ pub struct UserHandler;
 #[async_trait]
 impl Handler for UserHandler {
     async fn handle(&self, ctx: HandlerContext) -> HandlerResult {
-        // Extract user ID from the path (assuming path is like /api/user/:id)
-        let user_id = ctx.path.split('/').last().unwrap_or("unknown");
+        // Get the user ID captured by the `{id}` placeholder
+        let user_id = ctx.path_param("id").unwrap_or("unknown");
 
         // Get database and format from params
         let default_db = "default".to_string();
@@ -20,9 +20,9 @@ impl Handler for UserHandler {
         // In a real application, you would query a database here
         // For this example, we'll just return a formatted response
         match format.as_str() {
-            "json" => Ok(format!("{{ \"id\": \"{}\", \"name\": \"User {}\", \"database\": \"{}\" }}", 
-                user_id, user_id, database)),
-            "text" => Ok(format!("User ID: {}, Database: {}", user_id, database)),
+            "json" => Ok(HandlerResponse::text(format!("{{ \"id\": \"{}\", \"name\": \"User {}\", \"database\": \"{}\" }}", 
+                user_id, user_id, database)).with_content_type("application/json")),
+            "text" => Ok(format!("User ID: {}, Database: {}", user_id, database).into()),
             _ => Err((StatusCode::BAD_REQUEST, format!("Unsupported format: {}", format))),
         }
     }
@@ -52,24 +52,26 @@ impl Handler for ResourceHandler {
         // In a real application, you would perform the operation here
         // For this example, we'll just return a formatted response
         match self.operation.as_str() {
-            "get" => Ok("{ \"id\": 1, \"name\": \"Resource\", \"status\": \"active\" }".to_string()),
+            "get" => Ok("{ \"id\": 1, \"name\": \"Resource\", \"status\": \"active\" }".into()),
             "create" => {
                 if validate {
                     // Perform validation logic here
-                    Ok("{ \"status\": \"created\", \"id\": 2, \"validated\": true }".to_string())
+                    Ok(HandlerResponse::from("{ \"status\": \"created\", \"id\": 2, \"validated\": true }")
+                        .with_status(StatusCode::CREATED))
                 } else {
-                    Ok("{ \"status\": \"created\", \"id\": 2 }".to_string())
+                    Ok(HandlerResponse::from("{ \"status\": \"created\", \"id\": 2 }")
+                        .with_status(StatusCode::CREATED))
                 }
             },
             "update" => {
                 if validate {
                     // Perform validation logic here
-                    Ok("{ \"status\": \"updated\", \"validated\": true }".to_string())
+                    Ok("{ \"status\": \"updated\", \"validated\": true }".into())
                 } else {
-                    Ok("{ \"status\": \"updated\" }".to_string())
+                    Ok("{ \"status\": \"updated\" }".into())
                 }
             },
-            "delete" => Ok("{ \"status\": \"deleted\" }".to_string()),
+            "delete" => Ok(HandlerResponse::no_content()),
             _ => Err((StatusCode::BAD_REQUEST, format!("Unsupported operation: {}", self.operation))),
         }
     }
@@ -87,6 +89,14 @@ pub fn register_custom_handlers(registry: &mut HandlerRegistry) {
     registry.register("resource_delete", ResourceHandler::new("delete"));
 }
 
+// Print the status and body of a handler result
+fn print_result(name: &str, result: HandlerResult) {
+    match result {
+        Ok(response) => println!("{} result: {} {}", name, response.status, String::from_utf8_lossy(&response.body)),
+        Err((status, message)) => println!("{} error: {} {}", name, status, message),
+    }
+}
+
 // Example of how to use the custom handlers
 #[tokio::main]
 async fn main() {
@@ -103,26 +113,30 @@ async fn main() {
     let user_handler = registry.get("user").unwrap();
     let user_ctx = HandlerContext {
         path: "/api/user/123".to_string(),
+        path_params: [("id".to_string(), "123".to_string())].into_iter().collect(),
         params: [
             ("database".to_string(), "users".to_string()),
             ("format".to_string(), "json".to_string()),
         ].into_iter().collect(),
         static_response: None,
+        ..Default::default()
     };
     
     let user_result = user_handler.handle(user_ctx).await;
-    println!("User handler result: {:?}", user_result);
+    print_result("User handler", user_result);
     
     // Example of using the resource create handler
     let resource_create_handler = registry.get("resource_create").unwrap();
     let resource_ctx = HandlerContext {
+        method: Method::POST,
         path: "/api/resource".to_string(),
         params: [
             ("validate".to_string(), "true".to_string()),
         ].into_iter().collect(),
         static_response: None,
+        ..Default::default()
     };
     
     let resource_result = resource_create_handler.handle(resource_ctx).await;
-    println!("Resource create handler result: {:?}", resource_result);
+    print_result("Resource create handler", resource_result);
 } 
\ No newline at end of file
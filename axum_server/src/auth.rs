@@ -0,0 +1,361 @@
+use std::collections::{BTreeMap, HashMap};
+
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::extract::Query;
+use axum_handlers::Principal;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config::{ApiKeyConfig, AuthConfig, BasicAuthConfig, JwtConfig};
+
+/// Header carrying API keys when the configuration names neither a header nor a query parameter
+pub(crate) const DEFAULT_API_KEY_HEADER: &str = "x-api-key";
+
+/// Realm of the Basic challenge when none is configured
+const DEFAULT_REALM: &str = "axum_server";
+
+/// Salt and hash of a bcrypt hash that passwords of unknown users are checked against, so that
+/// they take as long to reject as wrong passwords. The cost is taken from the configured users.
+const DUMMY_BCRYPT: &str = "fsO6iqmYbf5/BQfSC2wfI.IkBUYwZoBghi3DgvW2otmm9SAYY2r0K";
+
+/// Checks the credentials of requests against the schemes of an `auth` section
+///
+/// Configured by [`AuthConfig`]: static API keys, HTTP Basic with hashed passwords, and
+/// JWTs signed with HS256 or RS256. Any scheme that accepts a request authenticates it.
+pub struct Auth {
+    api_key: Option<ApiKey>,
+    basic: Option<Basic>,
+    jwt: Option<Jwt>,
+}
+
+/// Why a request was not authenticated, answered with 401
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    /// Only logged, the response doesn't tell clients e.g. which user names exist
+    pub reason: String,
+    /// `WWW-Authenticate` challenges of the schemes that use them
+    challenges: Vec<HeaderValue>,
+}
+
+struct ApiKey {
+    header: Option<HeaderName>,
+    query: Option<String>,
+    /// Client names by key
+    keys: Vec<(String, String)>,
+}
+
+struct Basic {
+    challenge: HeaderValue,
+    users: BTreeMap<String, PasswordHash>,
+    /// Checked in place of the hash of an unknown user
+    dummy: PasswordHash,
+}
+
+#[derive(Clone)]
+enum PasswordHash {
+    Bcrypt(String),
+    Sha256([u8; 32]),
+}
+
+struct Jwt {
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl Auth {
+    /// Parse an `auth` section, reading the key files it names
+    pub fn new(config: &AuthConfig) -> Result<Self, String> {
+        Ok(Self {
+            api_key: config.api_key.as_ref().map(ApiKey::new).transpose().map_err(|e| format!("api_key: {}", e))?,
+            basic: config.basic.as_ref().map(Basic::new).transpose().map_err(|e| format!("basic: {}", e))?,
+            jwt: config.jwt.as_ref().map(Jwt::new).transpose().map_err(|e| format!("jwt: {}", e))?,
+        })
+    }
+
+    /// Whether every request is accepted, as with an empty `auth` section
+    pub fn is_public(&self) -> bool {
+        self.api_key.is_none() && self.basic.is_none() && self.jwt.is_none()
+    }
+
+    /// The client that sent a request, or why it is rejected
+    pub async fn authenticate(&self, headers: &HeaderMap, uri: &Uri) -> Result<Option<Principal>, Rejection> {
+        if self.is_public() {
+            return Ok(None);
+        }
+
+        // The reasons of schemes whose credentials were sent, the first one is reported
+        let mut reasons = Vec::new();
+        if let Some(api_key) = &self.api_key {
+            match api_key.authenticate(headers, uri) {
+                Some(Ok(principal)) => return Ok(Some(principal)),
+                Some(Err(reason)) => reasons.push(reason),
+                None => {},
+            }
+        }
+
+        let authorization = headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+        if let Some((scheme, credentials)) = authorization.and_then(|value| value.trim().split_once(' ')) {
+            if scheme.eq_ignore_ascii_case("basic") {
+                if let Some(basic) = &self.basic {
+                    match basic.authenticate(credentials.trim()).await {
+                        Ok(principal) => return Ok(Some(principal)),
+                        Err(reason) => reasons.push(reason),
+                    }
+                }
+            } else if scheme.eq_ignore_ascii_case("bearer") {
+                if let Some(jwt) = &self.jwt {
+                    match jwt.authenticate(credentials.trim()) {
+                        Ok(principal) => return Ok(Some(principal)),
+                        Err(reason) => reasons.push(reason),
+                    }
+                }
+            }
+        }
+
+        let reason = reasons.into_iter().next().unwrap_or_else(|| format!("missing credentials, expected {}", self.expected()));
+        Err(Rejection { reason, challenges: self.challenges() })
+    }
+
+    // The credentials requests can send, for rejections without any
+    fn expected(&self) -> String {
+        let mut expected = Vec::new();
+        if let Some(api_key) = &self.api_key {
+            if let Some(header) = &api_key.header {
+                expected.push(format!("an `{}` header", header));
+            }
+            if let Some(query) = &api_key.query {
+                expected.push(format!("a `{}` query parameter", query));
+            }
+        }
+        if self.basic.is_some() {
+            expected.push("Basic authorization".to_string());
+        }
+        if self.jwt.is_some() {
+            expected.push("a Bearer token".to_string());
+        }
+        expected.join(" or ")
+    }
+
+    fn challenges(&self) -> Vec<HeaderValue> {
+        let mut challenges = Vec::new();
+        if let Some(basic) = &self.basic {
+            challenges.push(basic.challenge.clone());
+        }
+        if self.jwt.is_some() {
+            challenges.push(HeaderValue::from_static("Bearer"));
+        }
+        challenges
+    }
+}
+
+impl Rejection {
+    /// Body of the response, the reason is left out
+    pub fn message(&self) -> String {
+        "Unauthorized".to_string()
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let mut response = (StatusCode::UNAUTHORIZED, self.message()).into_response();
+        for challenge in self.challenges {
+            response.headers_mut().append(header::WWW_AUTHENTICATE, challenge);
+        }
+        response
+    }
+}
+
+impl ApiKey {
+    fn new(config: &ApiKeyConfig) -> Result<Self, String> {
+        let header = match (&config.header, &config.query) {
+            (Some(name), _) => Some(HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| format!("`header`: invalid header name `{}`", name))?),
+            (None, Some(_)) => None,
+            (None, None) => Some(HeaderName::from_static(DEFAULT_API_KEY_HEADER)),
+        };
+        if config.keys.is_empty() {
+            return Err("`keys` must not be empty".to_string());
+        }
+        if let Some((name, _)) = config.keys.iter().find(|(_, key)| key.is_empty()) {
+            return Err(format!("`keys`: the key of `{}` is empty", name));
+        }
+
+        Ok(Self {
+            header,
+            query: config.query.clone(),
+            keys: config.keys.iter().map(|(name, key)| (key.clone(), name.clone())).collect(),
+        })
+    }
+
+    // None if the request sent no key
+    fn authenticate(&self, headers: &HeaderMap, uri: &Uri) -> Option<Result<Principal, String>> {
+        let from_header = self.header.as_ref()
+            .and_then(|name| headers.get(name))
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        let from_query = self.query.as_ref().and_then(|name| {
+            let Query(query) = Query::<HashMap<String, String>>::try_from_uri(uri).ok()?;
+            query.get(name).cloned()
+        });
+        let sent = from_header.or(from_query)?;
+
+        // Every key is compared, so the time taken doesn't tell which keys exist
+        let name = self.keys.iter()
+            .fold(None, |found, (key, name)| if constant_time_eq(key.as_bytes(), sent.as_bytes()) { Some(name) } else { found });
+        Some(match name {
+            Some(name) => Ok(principal(name, "api_key", Map::new())),
+            None => Err("invalid API key".to_string()),
+        })
+    }
+}
+
+impl Basic {
+    fn new(config: &BasicAuthConfig) -> Result<Self, String> {
+        let realm = config.realm.as_deref().unwrap_or(DEFAULT_REALM);
+        let challenge = HeaderValue::from_str(&format!("Basic realm=\"{}\", charset=\"UTF-8\"", realm.replace('"', "")))
+            .map_err(|_| format!("`realm`: invalid realm {:?}", realm))?;
+
+        let mut users = BTreeMap::new();
+        for (user, hash) in &config.users {
+            let hash = PasswordHash::parse(hash).map_err(|e| format!("`users.{}`: {}", user, e))?;
+            users.insert(user.clone(), hash);
+        }
+        let cost = users.values()
+            .filter_map(|hash| match hash {
+                PasswordHash::Bcrypt(hash) => hash.parse::<bcrypt::HashParts>().ok().map(|parts| parts.get_cost()),
+                PasswordHash::Sha256(_) => None,
+            })
+            .max();
+        let dummy = match cost {
+            Some(cost) => PasswordHash::Bcrypt(format!("$2b${:02}${}", cost, DUMMY_BCRYPT)),
+            None => PasswordHash::Sha256([0; 32]),
+        };
+        Ok(Self { challenge, users, dummy })
+    }
+
+    async fn authenticate(&self, credentials: &str) -> Result<Principal, String> {
+        let decoded = STANDARD.decode(credentials).map_err(|_| "malformed Basic credentials".to_string())?;
+        let decoded = String::from_utf8(decoded).map_err(|_| "malformed Basic credentials".to_string())?;
+        let (user, password) = decoded.split_once(':').ok_or("malformed Basic credentials")?;
+
+        // Unknown users are checked against the dummy hash, so they take as long as wrong passwords
+        let verified = match self.users.get(user) {
+            Some(hash) => hash.verify(password.to_string()).await,
+            None => {
+                self.dummy.verify(password.to_string()).await;
+                return Err(format!("unknown user `{}`", user));
+            },
+        };
+        if verified {
+            Ok(principal(user, "basic", Map::new()))
+        } else {
+            Err(format!("wrong password for user `{}`", user))
+        }
+    }
+}
+
+impl PasswordHash {
+    fn parse(hash: &str) -> Result<Self, String> {
+        if let Some(hex) = hash.strip_prefix("sha256:") {
+            let bytes = decode_hex(hex.trim()).filter(|bytes| bytes.len() == 32)
+                .ok_or("a sha256 hash must be 64 hex digits")?;
+            let mut digest = [0; 32];
+            digest.copy_from_slice(&bytes);
+            return Ok(PasswordHash::Sha256(digest));
+        }
+        hash.parse::<bcrypt::HashParts>()
+            .map_err(|_| "not a bcrypt hash (`$2b$...`) or `sha256:<hex>`, plain passwords are not accepted".to_string())?;
+        Ok(PasswordHash::Bcrypt(hash.to_string()))
+    }
+
+    async fn verify(&self, password: String) -> bool {
+        match self {
+            // bcrypt is slow on purpose, so it runs off the async workers
+            PasswordHash::Bcrypt(hash) => {
+                let hash = hash.clone();
+                tokio::task::spawn_blocking(move || bcrypt::verify(password, &hash).unwrap_or(false))
+                    .await
+                    .unwrap_or(false)
+            },
+            PasswordHash::Sha256(digest) => constant_time_eq(&Sha256::digest(password.as_bytes()), digest),
+        }
+    }
+}
+
+impl Jwt {
+    fn new(config: &JwtConfig) -> Result<Self, String> {
+        let (algorithm, key) = match config.algorithm.trim().to_uppercase().as_str() {
+            "HS256" => {
+                let secret = config.secret.as_ref().ok_or("`secret` is required for HS256")?;
+                (Algorithm::HS256, DecodingKey::from_secret(secret.as_bytes()))
+            },
+            "RS256" => {
+                let file = config.public_key_file.as_ref().ok_or("`public_key_file` is required for RS256")?;
+                let pem = std::fs::read(file).map_err(|e| format!("`public_key_file`: can't read {}: {}", file, e))?;
+                let key = DecodingKey::from_rsa_pem(&pem)
+                    .map_err(|e| format!("`public_key_file`: {} is not an RSA public key in PEM format: {}", file, e))?;
+                (Algorithm::RS256, key)
+            },
+            other => return Err(format!("`algorithm`: unsupported algorithm `{}`, use HS256 or RS256", other)),
+        };
+
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = config.leeway.unwrap_or(0);
+        validation.validate_nbf = true;
+        match &config.audience {
+            Some(audience) => validation.set_audience(&[audience]),
+            None => validation.validate_aud = false,
+        }
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
+        Ok(Self { key, validation })
+    }
+
+    fn authenticate(&self, token: &str) -> Result<Principal, String> {
+        let data = jsonwebtoken::decode::<Map<String, Value>>(token, &self.key, &self.validation)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => "token expired".to_string(),
+                ErrorKind::ImmatureSignature => "token not valid yet".to_string(),
+                ErrorKind::InvalidSignature => "invalid token signature".to_string(),
+                ErrorKind::InvalidIssuer => "token has the wrong issuer".to_string(),
+                ErrorKind::InvalidAudience => "token has the wrong audience".to_string(),
+                ErrorKind::InvalidAlgorithm => "token is signed with another algorithm".to_string(),
+                ErrorKind::MissingRequiredClaim(claim) => format!("token has no `{}` claim", claim),
+                _ => format!("malformed token: {}", e),
+            })?;
+
+        let name = match data.claims.get("sub") {
+            Some(Value::String(subject)) => subject.clone(),
+            _ => String::new(),
+        };
+        Ok(principal(&name, "jwt", data.claims))
+    }
+}
+
+fn principal(name: &str, scheme: &str, claims: Map<String, Value>) -> Principal {
+    Principal {
+        name: name.to_string(),
+        scheme: scheme.to_string(),
+        claims,
+    }
+}
+
+// Compare secrets in a time that only depends on their lengths
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
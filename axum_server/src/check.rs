@@ -0,0 +1,320 @@
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::{header, HeaderName, HeaderValue, Method, Request, Uri};
+use clap::ValueEnum;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde_json::Value;
+
+use crate::config::{Check, Config, EndpointConfig, EndpointMethod};
+use crate::proxy::error_chain;
+
+/// Longest part of a body quoted in a failure
+const MAX_QUOTED_BODY: usize = 120;
+
+/// Format of the `check` report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// A table with one line per check
+    Table,
+    /// JUnit XML, for CI systems
+    Junit,
+}
+
+/// Outcome of one check
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// The check's name, or its method and path
+    pub name: String,
+    pub method: Method,
+    pub path: String,
+    /// Status of the response, None if there was no response
+    pub status: Option<u16>,
+    pub duration: Duration,
+    /// Why the check failed, empty if it passed
+    pub failures: Vec<String>,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Base URL of the configured server, unspecified addresses are reached through loopback
+pub fn default_base_url(config: &Config) -> String {
+    match config.server.host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) if ip.is_unspecified() => format!("http://127.0.0.1:{}", config.server.port),
+        Ok(IpAddr::V6(ip)) if ip.is_unspecified() => format!("http://[::1]:{}", config.server.port),
+        Ok(IpAddr::V6(ip)) => format!("http://[{}]:{}", ip, config.server.port),
+        _ => format!("http://{}:{}", config.server.host, config.server.port),
+    }
+}
+
+/// Send the checks of every endpoint to a server, one at a time
+///
+/// Endpoints without checks get one that requests the endpoint path, with placeholders
+/// filled in, and expects the endpoint's `status` param or any 2xx status.
+pub async fn run(config: &Config, base_url: &str, timeout: Duration) -> Result<Vec<CheckResult>, String> {
+    let base: Uri = base_url.parse().map_err(|e| format!("invalid base URL `{}`: {}", base_url, e))?;
+    if base.scheme_str() != Some("http") || base.authority().is_none() {
+        return Err(format!("`{}` is not an http:// URL", base_url));
+    }
+    let client: Client<HttpConnector, Body> = Client::builder(TokioExecutor::new()).build_http();
+
+    let mut results = Vec::new();
+    for endpoint in &config.endpoints {
+        for check in endpoint_checks(endpoint) {
+            results.push(run_check(&client, &base, endpoint, &check, timeout).await);
+        }
+    }
+    Ok(results)
+}
+
+fn endpoint_checks(endpoint: &EndpointConfig) -> Vec<Check> {
+    if !endpoint.checks.is_empty() {
+        return endpoint.checks.clone();
+    }
+    let mut check = Check::default();
+    check.expect.status = endpoint.params.get("status").and_then(|status| status.parse().ok());
+    vec![check]
+}
+
+async fn run_check(
+    client: &Client<HttpConnector, Body>,
+    base: &Uri,
+    endpoint: &EndpointConfig,
+    check: &Check,
+    timeout: Duration,
+) -> CheckResult {
+    let method = match (&check.method, &endpoint.method) {
+        (Some(method), _) => Method::from_bytes(method.trim().to_uppercase().as_bytes()).unwrap_or(Method::GET),
+        (None, EndpointMethod::Methods(methods)) => methods.first().cloned().unwrap_or(Method::GET),
+        (None, EndpointMethod::Any) => Method::GET,
+    };
+    let path = check.path.clone().unwrap_or_else(|| example_path(&endpoint.path));
+    let mut result = CheckResult {
+        name: check.name.clone().unwrap_or_else(|| format!("{} {}", method, path)),
+        method: method.clone(),
+        path: path.clone(),
+        status: None,
+        duration: Duration::ZERO,
+        failures: Vec::new(),
+    };
+
+    let request = match build_request(base, &method, &path, endpoint.host.as_deref(), check) {
+        Ok(request) => request,
+        Err(e) => {
+            result.failures.push(e);
+            return result;
+        }
+    };
+
+    let start = Instant::now();
+    let response = match tokio::time::timeout(timeout, client.request(request)).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => {
+            result.failures.push(format!("request failed: {}", error_chain(&e)));
+            return result;
+        },
+        Err(_) => {
+            result.failures.push(format!("no response within {:?}", timeout));
+            return result;
+        },
+    };
+    let (parts, body) = response.into_parts();
+    let body = match tokio::time::timeout(timeout, axum::body::to_bytes(Body::new(body), usize::MAX)).await {
+        Ok(Ok(body)) => body,
+        Ok(Err(e)) => {
+            result.failures.push(format!("failed to read the body: {}", e));
+            return result;
+        },
+        Err(_) => {
+            result.failures.push(format!("body not received within {:?}", timeout));
+            return result;
+        },
+    };
+    result.duration = start.elapsed();
+    result.status = Some(parts.status.as_u16());
+
+    let expect = &check.expect;
+    match expect.status {
+        Some(status) if status != parts.status.as_u16() => {
+            result.failures.push(format!("status: expected {}, got {}", status, parts.status.as_u16()));
+        },
+        None if !parts.status.is_success() => {
+            result.failures.push(format!("status: expected 2xx, got {}", parts.status.as_u16()));
+        },
+        _ => {},
+    }
+
+    for (name, expected) in &expect.headers {
+        match parts.headers.get(name.as_str()).map(|value| String::from_utf8_lossy(value.as_bytes())) {
+            Some(actual) if actual == expected.as_str() => {},
+            Some(actual) => result.failures.push(format!("header {}: expected {:?}, got {:?}", name, expected, actual)),
+            None => result.failures.push(format!("header {}: missing, expected {:?}", name, expected)),
+        }
+    }
+
+    let text = String::from_utf8_lossy(&body);
+    if let Some(expected) = &expect.body {
+        if !same_body(expected, &text) {
+            result.failures.push(format!("body: expected {}, got {}", quote(expected), quote(&text)));
+        }
+    }
+    if let Some(expected) = &expect.body_contains {
+        if !text.contains(expected.as_str()) {
+            result.failures.push(format!("body: does not contain {:?}, got {}", expected, quote(&text)));
+        }
+    }
+    if let Some(expected) = &expect.json {
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(actual) if json_contains(&actual, expected) => {},
+            Ok(_) => result.failures.push(format!("json: does not contain {}, got {}", expected, quote(&text))),
+            Err(_) => result.failures.push(format!("json: body is not JSON, got {}", quote(&text))),
+        }
+    }
+
+    result
+}
+
+// The request of a check, for the endpoint's virtual host unless the check sets a `Host` header
+fn build_request(base: &Uri, method: &Method, path: &str, host: Option<&str>, check: &Check) -> Result<Request<Body>, String> {
+    let uri = format!("{}{}", base.to_string().trim_end_matches('/'), path);
+    let mut request = Request::builder()
+        .method(method.clone())
+        .uri(&uri)
+        .body(Body::from(check.body.clone().unwrap_or_default()))
+        .map_err(|e| format!("invalid request {}: {}", uri, e))?;
+
+    for (name, value) in &check.headers {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid header name `{}`", name))?;
+        let value = HeaderValue::from_str(value).map_err(|_| format!("invalid value for header `{}`", name))?;
+        request.headers_mut().append(name, value);
+    }
+    if let Some(host) = host {
+        if !request.headers().contains_key(header::HOST) {
+            let value = HeaderValue::from_str(host).map_err(|_| format!("invalid host `{}`", host))?;
+            request.headers_mut().insert(header::HOST, value);
+        }
+    }
+    Ok(request)
+}
+
+// A path the route matches, e.g. `/users/1` for `/users/{id}`
+fn example_path(route: &str) -> String {
+    let mut path = String::new();
+    let mut rest = route;
+    while let Some(start) = rest.find('{') {
+        // `{{` and `}}` are escaped braces
+        if rest[start..].starts_with("{{") {
+            path.push_str(&rest[..start + 1]);
+            rest = &rest[start + 2..];
+            continue;
+        }
+        path.push_str(&rest[..start]);
+        path.push('1');
+        rest = rest[start..].find('}').map_or("", |end| &rest[start + end + 1..]);
+    }
+    path.push_str(rest);
+    path.replace("}}", "}")
+}
+
+// JSON bodies are compared by value, so formatting differences don't matter
+fn same_body(expected: &str, actual: &str) -> bool {
+    if expected == actual {
+        return true;
+    }
+    match (serde_json::from_str::<Value>(expected), serde_json::from_str::<Value>(actual)) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        _ => false,
+    }
+}
+
+// Objects match when every expected field matches, arrays and values must match exactly
+fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter()
+            .all(|(key, expected)| actual.get(key).is_some_and(|actual| json_contains(actual, expected))),
+        (Value::Array(actual), Value::Array(expected)) => actual.len() == expected.len()
+            && actual.iter().zip(expected).all(|(actual, expected)| json_contains(actual, expected)),
+        _ => actual == expected,
+    }
+}
+
+fn quote(body: &str) -> String {
+    if body.chars().count() <= MAX_QUOTED_BODY {
+        format!("{:?}", body)
+    } else {
+        let start: String = body.chars().take(MAX_QUOTED_BODY).collect();
+        format!("{:?}...", start)
+    }
+}
+
+/// Report as a table, one line per check followed by the failures and a summary
+pub fn table(results: &[CheckResult]) -> String {
+    let width = results.iter().map(|result| result.name.len()).max().unwrap_or(0).max("CHECK".len());
+    let mut report = format!("{:<6} {:<width$} {:>6} {:>10}\n", "RESULT", "CHECK", "STATUS", "TIME");
+
+    for result in results {
+        let status = result.status.map_or("-".to_string(), |status| status.to_string());
+        let time = format!("{:.1}ms", result.duration.as_secs_f64() * 1000.0);
+        let outcome = if result.passed() { "pass" } else { "FAIL" };
+        report.push_str(&format!("{:<6} {:<width$} {:>6} {:>10}\n", outcome, result.name, status, time));
+        for failure in &result.failures {
+            report.push_str(&format!("       {}\n", failure));
+        }
+    }
+
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    report.push_str(&format!("\n{} passed, {} failed\n", results.len() - failed, failed));
+    report
+}
+
+/// Report as JUnit XML, with the checks in one test suite
+pub fn junit(suite: &str, results: &[CheckResult]) -> String {
+    let failed = results.iter().filter(|result| !result.passed()).count();
+    let total: f64 = results.iter().map(|result| result.duration.as_secs_f64()).sum();
+
+    let mut report = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    report.push_str(&format!("<testsuites tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n", results.len(), failed, total));
+    report.push_str(&format!("  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.3}\">\n",
+        xml_escape(suite), results.len(), failed, total));
+
+    for result in results {
+        let testcase = format!("    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            xml_escape(&result.name), xml_escape(suite), result.duration.as_secs_f64());
+        if result.passed() {
+            report.push_str(&testcase);
+            report.push_str("/>\n");
+        } else {
+            report.push_str(&format!("{}>\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                testcase,
+                xml_escape(&result.failures[0]),
+                xml_escape(&result.failures.join("\n"))));
+        }
+    }
+
+    report.push_str("  </testsuite>\n</testsuites>\n");
+    report
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\t' | '\r') => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
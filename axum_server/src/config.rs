@@ -1,45 +1,686 @@
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
+use axum::http::Method;
+use axum_handlers::HandlerRegistry;
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
 use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use crate::validate::{self, Diagnostic};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Reload the configuration when the file changes
+    #[serde(default = "default_true")]
+    pub watch_config: bool,
+    /// Path of an admin endpoint that reloads the configuration on POST
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reload_path: Option<String>,
+    /// Path of the Prometheus metrics endpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metrics_path: Option<String>,
+    /// Path the OpenAPI document of the endpoints is served at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub openapi_path: Option<String>,
+    /// Path of a page showing the OpenAPI document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs_path: Option<String>,
+    /// Proxy params for requests that match no endpoint, see the proxy handler
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_proxy: Option<HashMap<String, String>>,
+    /// CORS policy of every endpoint, endpoints can override single fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    /// Authentication required by every endpoint, endpoints can replace it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    /// Limits shared by all endpoints, on top of the endpoints' own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Serve HTTPS instead of HTTP
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    /// Addresses the server listens on in addition to `host` and `port`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listeners: Vec<ListenerConfig>,
+}
+
+impl Default for ServerConfig {
+    /// Listen on 127.0.0.1:3000 without any admin endpoints
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 3000,
+            watch_config: true,
+            reload_path: None,
+            metrics_path: None,
+            openapi_path: None,
+            docs_path: None,
+            fallback_proxy: None,
+            cors: None,
+            auth: None,
+            rate_limit: None,
+            tls: None,
+            listeners: Vec::new(),
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Name of the listener on the server's own `host` and `port`
+pub const DEFAULT_LISTENER: &str = "default";
+
+/// An additional address the server listens on, on a TCP port or a Unix domain socket
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListenerConfig {
+    /// Name endpoints are scoped to with their `listener` field
+    pub name: String,
+    /// Host address of a TCP listener, the server's host when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Path of a Unix domain socket, in place of host and port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unix: Option<String>,
+}
+
+/// Where a listener accepts connections
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListenAddress {
+    Tcp(SocketAddr),
+    Unix(String),
+}
+
+impl fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenAddress::Tcp(address) => write!(f, "{}", address),
+            ListenAddress::Unix(path) => write!(f, "unix:{}", path),
+        }
+    }
+}
+
+impl ListenerConfig {
+    /// The listener's address, with the server's host filled in
+    pub fn address(&self, server: &ServerConfig) -> Result<ListenAddress, String> {
+        match (&self.unix, self.port) {
+            (Some(path), None) if self.host.is_none() => Ok(ListenAddress::Unix(path.clone())),
+            (Some(_), _) => Err("`unix` can't be combined with `host` or `port`".to_string()),
+            (None, Some(port)) => {
+                let host = self.host.as_deref().unwrap_or(&server.host);
+                let ip = IpAddr::from_str(host)
+                    .map_err(|_| format!("`{}` is not an IP address, use e.g. 127.0.0.1 or 0.0.0.0", host))?;
+                Ok(ListenAddress::Tcp(SocketAddr::new(ip, port)))
+            },
+            (None, None) => Err("either `port` or `unix` is required".to_string()),
+        }
+    }
+}
+
+/// HTTP methods an endpoint is routed for
+///
+/// In YAML this is either a single method (`GET`), `ANY`, or a list of methods
+/// (`[GET, POST]`). Method names are case-insensitive and may be non-standard
+/// methods such as `PROPFIND`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndpointMethod {
+    /// Every method not claimed by another endpoint on the same path
+    Any,
+    /// The listed methods
+    Methods(Vec<Method>),
+}
+
+impl EndpointMethod {
+    /// Parse a single method name, or `ANY`
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let name = name.trim().to_uppercase();
+        if name == "ANY" {
+            return Ok(EndpointMethod::Any);
+        }
+        parse_method(&name).map(|method| EndpointMethod::Methods(vec![method]))
+    }
+
+    /// Parse a list of method names
+    pub fn parse_list(names: &[String]) -> Result<Self, String> {
+        if names.is_empty() {
+            return Err("method list must not be empty".to_string());
+        }
+
+        let mut methods = Vec::with_capacity(names.len());
+        for name in names {
+            let name = name.trim().to_uppercase();
+            if name == "ANY" {
+                return Err("ANY cannot be combined with other methods".to_string());
+            }
+            let method = parse_method(&name)?;
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        Ok(EndpointMethod::Methods(methods))
+    }
+}
+
+fn parse_method(name: &str) -> Result<Method, String> {
+    Method::from_bytes(name.as_bytes())
+        .map_err(|_| format!("invalid HTTP method: {:?}", name))
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl fmt::Display for EndpointMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EndpointMethod::Any => write!(f, "ANY"),
+            EndpointMethod::Methods(methods) => {
+                let names: Vec<&str> = methods.iter().map(Method::as_str).collect();
+                write!(f, "{}", names.join(", "))
+            }
+        }
+    }
+}
+
+impl Serialize for EndpointMethod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            EndpointMethod::Any => serializer.serialize_str("ANY"),
+            EndpointMethod::Methods(methods) if methods.len() == 1 => serializer.serialize_str(methods[0].as_str()),
+            EndpointMethod::Methods(methods) => serializer.collect_seq(methods.iter().map(Method::as_str)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for EndpointMethod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EndpointMethodVisitor;
+
+        impl<'de> Visitor<'de> for EndpointMethodVisitor {
+            type Value = EndpointMethod;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an HTTP method, ANY, or a list of HTTP methods")
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                EndpointMethod::parse(name).map_err(E::custom)
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut names = Vec::new();
+                while let Some(name) = seq.next_element::<String>()? {
+                    names.push(name);
+                }
+                EndpointMethod::parse_list(&names).map_err(de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_any(EndpointMethodVisitor)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct EndpointConfig {
     pub path: String,
-    pub method: String,
+    pub method: EndpointMethod,
     pub handler: String,
     pub response: String,
     pub description: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub params: HashMap<String, String>,
+    /// JSON schema of the request body, for the OpenAPI document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_schema: Option<serde_json::Value>,
+    /// JSON schema of the response body, for the OpenAPI document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_schema: Option<serde_json::Value>,
+    /// Recorded responses served by the replay handler
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recordings: Vec<Recording>,
+    /// Requests the `check` command sends, with the responses they must get
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<Check>,
+    /// Fields that override the server's CORS policy, enables CORS for this endpoint only if the server has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cors: Option<CorsConfig>,
+    /// Authentication replacing the server's, `{}` makes the endpoint public
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+    /// Limits of this endpoint, requests must also pass the server's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Delays, errors and broken responses injected into this endpoint's requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faults: Option<FaultConfig>,
+    /// Name of the only listener serving this endpoint, every listener serves it when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub listener: Option<String>,
+    /// Virtual host this endpoint is served for, matched against the `Host` header without its port
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+impl EndpointConfig {
+    /// An endpoint served by the default handler, with an empty response
+    pub fn new(method: EndpointMethod, path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            method,
+            handler: "default".to_string(),
+            response: String::new(),
+            description: String::new(),
+            params: HashMap::new(),
+            request_schema: None,
+            response_schema: None,
+            recordings: Vec::new(),
+            checks: Vec::new(),
+            cors: None,
+            auth: None,
+            rate_limit: None,
+            faults: None,
+            listener: None,
+            host: None,
+        }
+    }
+
+    /// Resolve the fixture files of the endpoint's recordings against a directory, absolute paths are kept
+    pub fn resolve_fixtures(&mut self, dir: &Path) {
+        for recording in &mut self.recordings {
+            for file in recording.request_body_file.iter_mut().chain(&mut recording.body_file) {
+                *file = dir.join(&*file).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    /// Method and route identifying the endpoint, e.g. `GET /users/{id}` or `GET /users/{id} on admin for api.local`
+    pub fn key(&self) -> String {
+        format!("{} {}", self.method, self.route())
+    }
+
+    /// The path with the listener and virtual host the endpoint is scoped to, its route label in metrics
+    pub fn route(&self) -> String {
+        let mut route = self.path.clone();
+        if let Some(listener) = &self.listener {
+            route.push_str(&format!(" on {}", listener));
+        }
+        if let Some(host) = &self.host {
+            route.push_str(&format!(" for {}", host));
+        }
+        route
+    }
+
+    /// Whether a listener serves this endpoint
+    pub fn served_on(&self, listener: &str) -> bool {
+        self.listener.as_deref().is_none_or(|name| name == listener)
+    }
+
+    // The virtual host in the case requests are matched in
+    fn virtual_host(&self) -> Option<String> {
+        self.host.as_deref().map(str::to_ascii_lowercase)
+    }
+}
+
+/// A listener and virtual host whose requests are routed to the same endpoints
+///
+/// None stands for every listener, or every host, that no endpoint is scoped to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Site {
+    pub listener: Option<String>,
+    pub host: Option<String>,
+}
+
+impl Site {
+    /// The sites a set of endpoints is split into, a single one when no endpoint is scoped
+    pub fn all(endpoints: &[&EndpointConfig]) -> Vec<Site> {
+        let mut listeners = vec![None];
+        let mut hosts = vec![None];
+        for endpoint in endpoints {
+            if endpoint.listener.is_some() && !listeners.contains(&endpoint.listener) {
+                listeners.push(endpoint.listener.clone());
+            }
+            let host = endpoint.virtual_host();
+            if host.is_some() && !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+        listeners.iter()
+            .flat_map(|listener| hosts.iter().map(|host| Site { listener: listener.clone(), host: host.clone() }))
+            .collect()
+    }
+
+    /// The site of a request that arrived on `listener` for `host`
+    pub fn of_request(sites: &[Site], listener: &str, host: Option<&str>) -> Site {
+        let listener = sites.iter()
+            .find(|site| site.listener.as_deref() == Some(listener))
+            .and_then(|site| site.listener.clone());
+        let host = host.map(str::to_ascii_lowercase)
+            .filter(|host| sites.iter().any(|site| site.host.as_ref() == Some(host)));
+        Site { listener, host }
+    }
+
+    /// Positions of the endpoints served on this site
+    ///
+    /// Of the endpoints sharing a path, only the most specific are served: those of the site's
+    /// virtual host before those of its listener, before unscoped ones.
+    pub fn endpoints(&self, endpoints: &[&EndpointConfig]) -> Vec<usize> {
+        let served = |endpoint: &EndpointConfig| (endpoint.listener.is_none() || endpoint.listener == self.listener)
+            && (endpoint.host.is_none() || endpoint.virtual_host() == self.host);
+        let specificity = |endpoint: &EndpointConfig| 2 * endpoint.host.is_some() as u8 + endpoint.listener.is_some() as u8;
+        (0..endpoints.len())
+            .filter(|&index| {
+                let endpoint = endpoints[index];
+                served(endpoint) && !endpoints.iter().any(|other| {
+                    served(other) && other.path == endpoint.path && specificity(other) > specificity(endpoint)
+                })
+            })
+            .collect()
+    }
+}
+
+/// HTTPS settings of the server, see the `tls` module
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct TlsConfig {
+    /// PEM file with the certificate chain, leaf first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_file: Option<String>,
+    /// PEM file with the private key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<String>,
+    /// Reload the certificate when its files change
+    #[serde(default = "default_true")]
+    pub watch: bool,
+    /// Port of a plain HTTP listener that redirects every request to HTTPS
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_redirect_port: Option<u16>,
+}
+
+/// Cross-origin resource sharing policy, see the `cors` module
+///
+/// Unset fields of an endpoint's policy are taken from the server's.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct CorsConfig {
+    /// Origins allowed to call the endpoints, e.g. `http://localhost:5173`, any origin when unset or `*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_origins: Option<Vec<String>>,
+    /// Methods allowed in preflight requests, the requested method when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_methods: Option<Vec<String>>,
+    /// Request headers allowed in preflight requests, the requested headers when unset or `*`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_headers: Option<Vec<String>>,
+    /// Response headers scripts may read besides the CORS-safelisted ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expose_headers: Option<Vec<String>>,
+    /// Allow cookies and authorization headers, only with a list of `allowed_origins`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_credentials: Option<bool>,
+    /// Seconds browsers may cache a preflight response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+}
+
+impl CorsConfig {
+    /// This policy with the fields an endpoint sets replaced
+    pub fn overridden_by(&self, endpoint: &CorsConfig) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: endpoint.allowed_origins.clone().or_else(|| self.allowed_origins.clone()),
+            allowed_methods: endpoint.allowed_methods.clone().or_else(|| self.allowed_methods.clone()),
+            allowed_headers: endpoint.allowed_headers.clone().or_else(|| self.allowed_headers.clone()),
+            expose_headers: endpoint.expose_headers.clone().or_else(|| self.expose_headers.clone()),
+            allow_credentials: endpoint.allow_credentials.or(self.allow_credentials),
+            max_age: endpoint.max_age.or(self.max_age),
+        }
+    }
+}
+
+/// Authentication schemes, see the `auth` module
+///
+/// A request is accepted when any of the configured schemes accepts it, and an empty
+/// section accepts every request.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct AuthConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKeyConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic: Option<BasicAuthConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jwt: Option<JwtConfig>,
+}
+
+/// Static API keys sent in a header or a query parameter
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct ApiKeyConfig {
+    /// Header carrying the key, `x-api-key` when neither this nor `query` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+    /// Query parameter carrying the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Keys by the name of the client using them
+    pub keys: BTreeMap<String, String>,
+}
+
+/// HTTP Basic authentication
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct BasicAuthConfig {
+    /// Realm of the `WWW-Authenticate` challenge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub realm: Option<String>,
+    /// Password hashes by user name, bcrypt (`$2b$...`) or `sha256:<hex>`
+    pub users: BTreeMap<String, String>,
+}
+
+/// Bearer tokens signed with a local key
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct JwtConfig {
+    /// `HS256` or `RS256`
+    pub algorithm: String,
+    /// Shared secret of HS256 tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// PEM file with the public key of RS256 tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_file: Option<String>,
+    /// Required `iss` claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issuer: Option<String>,
+    /// Required `aud` claim
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    /// Seconds of clock skew allowed when checking `exp` and `nbf`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leeway: Option<u64>,
+}
+
+/// Token bucket and in-flight limits per client, see the `rate_limit` module
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct RateLimitConfig {
+    /// Requests allowed per `per`, no rate limit when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests: Option<u32>,
+    /// Period of `requests`, e.g. `1s` or `1m`, one second when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per: Option<String>,
+    /// Requests allowed at once after a quiet period, `requests` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    /// Requests handled at the same time, no limit when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_in_flight: Option<u32>,
+    /// What identifies a client: `ip` (default), `api_key`, `header:<name>` or `global`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+/// Faults injected into an endpoint's requests, see the `faults` module
+///
+/// Each request is delayed, then at most one of closing the connection, answering with
+/// an error or truncating the body is picked by its rate, tried in that order.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct FaultConfig {
+    /// Whether the faults are injected at startup, they can be toggled from the terminal UI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// Delay before the request is handled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<DelayConfig>,
+    /// Share of requests answered with `error_status`, from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_rate: Option<f64>,
+    /// Status of injected errors, 503 when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_status: Option<u16>,
+    /// Body of injected errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_body: Option<String>,
+    /// Share of responses cut off halfway through the body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncate_rate: Option<f64>,
+    /// Share of requests whose connection is closed without a response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub close_rate: Option<f64>,
+}
+
+/// How long injected delays are, durations are written like `250ms` or `2s`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum DelayConfig {
+    /// Always the same delay
+    Fixed(String),
+    /// Uniformly distributed between `min` and `max`
+    Uniform { min: String, max: String },
+    /// Normally distributed around `mean`, never negative
+    Normal { mean: String, stddev: String },
+}
+
+/// A request of the contract test run by the `check` command
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Check {
+    /// Shown in the report instead of the method and path
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Path to request, e.g. `/users/1`, placeholders of the endpoint path are filled in when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Method to use, the endpoint's first method when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub expect: Expectation,
+}
+
+/// What a checked response must look like, unset fields are not checked
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Expectation {
+    /// Status code, any 2xx status when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Exact body, JSON bodies are compared by value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Text the body must contain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_contains: Option<String>,
+    /// JSON the body must contain, objects may have further fields
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<serde_json::Value>,
+}
+
+/// A recorded response, see the replay handler
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Recording {
+    /// Query string the request must have, requests with any query match when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// File with the body the request must have, bodies are not compared when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body_file: Option<String>,
+    #[serde(default = "default_status")]
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, HeaderValues>,
+    /// File with the response body, the body is empty when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_file: Option<String>,
+}
+
+/// Values of a recorded header, a list when the response repeated it, e.g. `set-cookie`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum HeaderValues {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl HeaderValues {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let values = match self {
+            HeaderValues::One(value) => std::slice::from_ref(value),
+            HeaderValues::Many(values) => values.as_slice(),
+        };
+        values.iter().map(String::as_str)
+    }
+
+    /// Add a value, turning a single value into a list
+    pub fn push(&mut self, value: String) {
+        match self {
+            HeaderValues::One(first) => *self = HeaderValues::Many(vec![std::mem::take(first), value]),
+            HeaderValues::Many(values) => values.push(value),
+        }
+    }
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
 pub struct Config {
     pub server: ServerConfig,
     pub endpoints: Vec<EndpointConfig>,
 }
 
 impl Config {
-    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        
-        let config: Config = serde_yaml::from_str(&contents)?;
-        Ok(config)
+    /// Parse a configuration from YAML, without validating it
+    pub fn from_yaml(source: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(source)
+    }
+
+    /// Resolve the fixture files of replayed recordings against a directory, e.g. the configuration file's
+    pub fn resolve_fixtures(&mut self, dir: &Path) {
+        for endpoint in &mut self.endpoints {
+            endpoint.resolve_fixtures(dir);
+        }
+    }
+
+    /// Check the configuration for problems that would break or surprise at runtime
+    pub fn validate(&self, registry: &HandlerRegistry) -> Vec<Diagnostic> {
+        validate::check_config(self, registry)
     }
     
     pub fn get_socket_addr(&self) -> Result<SocketAddr, Box<dyn std::error::Error>> {
         let ip = IpAddr::from_str(&self.server.host)?;
         Ok(SocketAddr::new(ip, self.server.port))
     }
+
+    /// Name and address of every listener, the one on the server's host and port first
+    pub fn listen_addresses(&self) -> Result<Vec<(String, ListenAddress)>, Box<dyn std::error::Error>> {
+        let mut addresses = vec![(DEFAULT_LISTENER.to_string(), ListenAddress::Tcp(self.get_socket_addr()?))];
+        for listener in &self.server.listeners {
+            let address = listener.address(&self.server).map_err(|e| format!("listener {}: {}", listener.name, e))?;
+            addresses.push((listener.name.clone(), address));
+        }
+        Ok(addresses)
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,180 @@
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, Uri};
+
+use crate::config::CorsConfig;
+
+const VARY_PREFLIGHT: &str = "Origin, Access-Control-Request-Method, Access-Control-Request-Headers";
+
+/// Adds CORS headers to responses and answers preflight requests
+///
+/// Built from a server's `cors` section merged with an endpoint's, see [`CorsConfig`].
+#[derive(Debug, Clone)]
+pub struct Cors {
+    /// None allows any origin
+    origins: Option<Vec<HeaderValue>>,
+    /// None allows the requested method
+    methods: Option<Vec<Method>>,
+    /// None allows the requested headers
+    headers: Option<Vec<HeaderName>>,
+    expose_headers: Option<HeaderValue>,
+    credentials: bool,
+    max_age: Option<HeaderValue>,
+}
+
+impl Cors {
+    pub fn new(config: &CorsConfig) -> Result<Self, String> {
+        let origins = match &config.allowed_origins {
+            Some(origins) if !origins.iter().any(|origin| origin == "*") => Some(
+                origins.iter().map(|origin| parse_origin(origin)).collect::<Result<_, _>>()?,
+            ),
+            _ => None,
+        };
+
+        let methods = match &config.allowed_methods {
+            Some(methods) if !methods.iter().any(|method| method == "*") => Some(
+                methods.iter()
+                    .map(|method| Method::from_bytes(method.trim().to_uppercase().as_bytes())
+                        .map_err(|_| format!("`allowed_methods`: invalid HTTP method {:?}", method)))
+                    .collect::<Result<_, _>>()?,
+            ),
+            _ => None,
+        };
+
+        // Reflecting any origin with credentials would let every site make requests as the user
+        let credentials = config.allow_credentials.unwrap_or(false);
+        if credentials && origins.is_none() {
+            return Err("`allow_credentials` needs an `allowed_origins` list without `*`".to_string());
+        }
+
+        let headers = match &config.allowed_headers {
+            Some(headers) if !headers.iter().any(|name| name == "*") => Some(header_names(headers, "allowed_headers")?),
+            _ => None,
+        };
+
+        let expose_headers = match &config.expose_headers {
+            Some(headers) if !headers.is_empty() => {
+                let names: Vec<String> = header_names(headers, "expose_headers")?.iter().map(HeaderName::to_string).collect();
+                // Joined valid header names are a valid header value
+                HeaderValue::from_str(&names.join(", ")).ok()
+            },
+            _ => None,
+        };
+
+        Ok(Self {
+            origins,
+            methods,
+            headers,
+            expose_headers,
+            credentials,
+            max_age: config.max_age.map(HeaderValue::from),
+        })
+    }
+
+    /// Add the CORS headers for a request's origin to its response
+    pub fn apply(&self, origin: Option<&HeaderValue>, headers: &mut HeaderMap) {
+        if let Some(allowed) = origin.and_then(|origin| self.allow_origin(origin)) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allowed);
+            if self.credentials {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+            }
+            if let Some(expose) = &self.expose_headers {
+                headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, expose.clone());
+            }
+        }
+        // Caches must not serve the response to other origins when it depends on the origin
+        if self.echoes_origin() {
+            headers.append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+    }
+
+    /// Headers of the answer to a preflight request, or why it is rejected
+    pub fn preflight(&self, request: &HeaderMap) -> Result<HeaderMap, String> {
+        let origin = request.get(header::ORIGIN).ok_or("no Origin header")?;
+        let allowed_origin = self.allow_origin(origin)
+            .ok_or_else(|| format!("origin {} is not allowed", String::from_utf8_lossy(origin.as_bytes())))?;
+
+        let method = requested_method(request).ok_or("invalid Access-Control-Request-Method header")?;
+        let allowed_methods = match &self.methods {
+            Some(methods) if !methods.contains(&method) => return Err(format!("method {} is not allowed", method)),
+            Some(methods) => methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", "),
+            None => method.to_string(),
+        };
+
+        let requested: Vec<&str> = request.get_all(header::ACCESS_CONTROL_REQUEST_HEADERS).iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect();
+        if let Some(allowed) = &self.headers {
+            if let Some(name) = requested.iter().find(|name| !allowed.iter().any(|allowed| allowed.as_str().eq_ignore_ascii_case(name))) {
+                return Err(format!("header {} is not allowed", name));
+            }
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+        // Both lists were parsed from valid names, so they are valid values
+        if let Ok(methods) = HeaderValue::from_str(&allowed_methods) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if !requested.is_empty() {
+            if let Ok(names) = HeaderValue::from_str(&requested.join(", ")) {
+                headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, names);
+            }
+        }
+        if self.credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+        if let Some(max_age) = &self.max_age {
+            headers.insert(header::ACCESS_CONTROL_MAX_AGE, max_age.clone());
+        }
+        headers.insert(header::VARY, HeaderValue::from_static(VARY_PREFLIGHT));
+        Ok(headers)
+    }
+
+    // The Access-Control-Allow-Origin value for an origin, None if it is not allowed
+    fn allow_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        match &self.origins {
+            Some(origins) if !origins.contains(origin) => None,
+            Some(_) => Some(origin.clone()),
+            None => Some(HeaderValue::from_static("*")),
+        }
+    }
+
+    fn echoes_origin(&self) -> bool {
+        self.origins.is_some()
+    }
+}
+
+/// Whether a request is a CORS preflight rather than a plain OPTIONS request
+pub fn is_preflight(request: &Request) -> bool {
+    request.method() == Method::OPTIONS
+        && request.headers().contains_key(header::ORIGIN)
+        && request.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// The method a preflight request asks for
+pub fn requested_method(headers: &HeaderMap) -> Option<Method> {
+    headers.get(header::ACCESS_CONTROL_REQUEST_METHOD)
+        .and_then(|method| Method::from_bytes(method.as_bytes()).ok())
+}
+
+// An origin as browsers send it, `scheme://host[:port]` without a trailing slash
+fn parse_origin(origin: &str) -> Result<HeaderValue, String> {
+    let invalid = || format!("`allowed_origins`: `{}` is not an origin, use e.g. http://localhost:5173", origin);
+    // Scheme and host are case-insensitive, browsers send them in lowercase
+    let origin = origin.trim().trim_end_matches('/').to_ascii_lowercase();
+    let uri: Uri = origin.parse().map_err(|_| invalid())?;
+    if uri.scheme().is_none() || uri.authority().is_none() || uri.path_and_query().is_some_and(|path| path != "/") {
+        return Err(invalid());
+    }
+    HeaderValue::from_str(&origin).map_err(|_| invalid())
+}
+
+fn header_names(names: &[String], field: &str) -> Result<Vec<HeaderName>, String> {
+    names.iter()
+        .map(|name| HeaderName::from_bytes(name.trim().as_bytes())
+            .map_err(|_| format!("`{}`: invalid header name `{}`", field, name)))
+        .collect()
+}
@@ -1,6 +1,7 @@
-use axum_handlers::{Handler, HandlerContext, HandlerResult, HandlerRegistry};
+use axum_handlers::{Handler, HandlerContext, HandlerResponse, HandlerResult, HandlerRegistry};
 use async_trait::async_trait;
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
+use serde_json::{json, Value};
 
 // Example of a custom handler for user data
 pub struct UserHandler;
@@ -8,8 +9,8 @@ pub struct UserHandler;
 #[async_trait]
 impl Handler for UserHandler {
     async fn handle(&self, ctx: HandlerContext) -> HandlerResult {
-        // Extract user ID from the path (assuming path is like /api/user/{id})
-        let user_id = ctx.path.split('/').last().unwrap_or("unknown");
+        // Get the user ID captured by the `{id}` placeholder
+        let user_id = ctx.path_param("id").unwrap_or("unknown");
 
         // Get database and format from params
         let default_db = "default".to_string();
@@ -20,9 +21,12 @@ impl Handler for UserHandler {
         // In a real application, you would query a database here
         // For this example, we'll just return a formatted response
         match format.as_str() {
-            "json" => Ok(format!("{{ \"id\": \"{}\", \"name\": \"User {}\", \"database\": \"{}\" }}", 
-                user_id, user_id, database)),
-            "text" => Ok(format!("User ID: {}, Database: {}", user_id, database)),
+            "json" => HandlerResponse::json(&json!({
+                "id": user_id,
+                "name": format!("User {}", user_id),
+                "database": database,
+            })),
+            "text" => Ok(format!("User ID: {}, Database: {}", user_id, database).into()),
             _ => Err((StatusCode::BAD_REQUEST, format!("Unsupported format: {}", format))),
         }
     }
@@ -49,38 +53,62 @@ impl Handler for ResourceHandler {
             .map(|v| v == "true")
             .unwrap_or(false);
         
-        // Extract resource ID from the path if available
-        let resource_id = ctx.path.split('/').last()
-            .and_then(|id| if id.is_empty() || id == "resource" { None } else { Some(id) })
-            .unwrap_or("1"); // Default to ID 1 if not provided
+        // Get the resource ID from the `{id}` placeholder if the route has one
+        let resource_id = ctx.path_param("id").unwrap_or("1"); // Default to ID 1 if not provided
         
         // In a real application, you would perform the operation here
         // For this example, we'll just return a formatted response
         match self.operation.as_str() {
-            "get" => Ok(format!("{{ \"id\": {}, \"name\": \"Resource {}\", \"status\": \"active\" }}", 
-                resource_id, resource_id)),
+            "get" => HandlerResponse::json(&json!({
+                // Keep numeric IDs as JSON numbers
+                "id": resource_id.parse::<u64>().map(Value::from).unwrap_or_else(|_| json!(resource_id)),
+                "name": format!("Resource {}", resource_id),
+                "status": "active",
+            })),
             "create" => {
+                let mut body = json!({ "status": "created", "id": 2 });
                 if validate {
                     // Perform validation logic here
-                    Ok("{ \"status\": \"created\", \"id\": 2, \"validated\": true }".to_string())
-                } else {
-                    Ok("{ \"status\": \"created\", \"id\": 2 }".to_string())
+                    body["validated"] = json!(true);
                 }
+                if let Some(data) = request_data(&ctx, validate)? {
+                    body["data"] = data;
+                }
+                Ok(HandlerResponse::json(&body)?
+                    .with_status(StatusCode::CREATED)
+                    .with_header(header::LOCATION, "/api/resource/2"))
             },
             "update" => {
+                let mut body = json!({ "status": "updated" });
                 if validate {
                     // Perform validation logic here
-                    Ok("{ \"status\": \"updated\", \"validated\": true }".to_string())
-                } else {
-                    Ok("{ \"status\": \"updated\" }".to_string())
+                    body["validated"] = json!(true);
+                }
+                if let Some(data) = request_data(&ctx, validate)? {
+                    body["data"] = data;
                 }
+                HandlerResponse::json(&body)
             },
-            "delete" => Ok("{ \"status\": \"deleted\" }".to_string()),
+            "delete" => Ok(HandlerResponse::no_content()),
             _ => Err((StatusCode::BAD_REQUEST, format!("Unsupported operation: {}", self.operation))),
         }
     }
 }
 
+// Decode the JSON payload sent by the client, if there is one
+fn request_data(ctx: &HandlerContext, validate: bool) -> Result<Option<Value>, (StatusCode, String)> {
+    if ctx.body.is_empty() {
+        return Ok(None);
+    }
+
+    let data: Value = ctx.json()?;
+    if validate && !data.is_object() {
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, "Resource must be a JSON object".to_string()));
+    }
+
+    Ok(Some(data))
+}
+
 // Function to register custom handlers
 pub fn register_custom_handlers(registry: &mut HandlerRegistry) {
     // Register user handler
@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_tui::FaultInfo;
+use futures_util::{stream, StreamExt};
+use rand::Rng;
+
+use crate::config::{DelayConfig, FaultConfig};
+use crate::proxy::parse_duration;
+
+/// Status of injected errors when none is configured
+const DEFAULT_ERROR_STATUS: StatusCode = StatusCode::SERVICE_UNAVAILABLE;
+
+/// Fault injectors by endpoint key, kept across reloads with their switch unless their configuration changes
+#[derive(Default)]
+pub struct Faults {
+    injectors: Mutex<HashMap<String, Arc<FaultInjector>>>,
+}
+
+/// Picks the faults of an endpoint's requests while it is switched on
+pub struct FaultInjector {
+    config: FaultConfig,
+    delay: Option<Delay>,
+    error_rate: f64,
+    error_status: StatusCode,
+    truncate_rate: f64,
+    close_rate: f64,
+    enabled: AtomicBool,
+}
+
+enum Delay {
+    Fixed(Duration),
+    Uniform(Duration, Duration),
+    /// Mean and standard deviation in seconds
+    Normal(f64, f64),
+}
+
+/// What is done to a single request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    /// Drop the connection without a response
+    Close,
+    /// Answer with the configured error instead of handling the request
+    Error,
+    /// Send only the first half of the response body
+    Truncate,
+}
+
+/// The faults picked for a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Injection {
+    pub delay: Option<Duration>,
+    pub fault: Option<Fault>,
+}
+
+impl Faults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The injector of an endpoint, a new one if the endpoint's configuration changed
+    pub fn injector(&self, key: &str, config: &FaultConfig) -> Result<Arc<FaultInjector>, String> {
+        let mut injectors = self.injectors.lock().unwrap();
+        if let Some(injector) = injectors.get(key).filter(|injector| injector.config == *config) {
+            return Ok(injector.clone());
+        }
+        let injector = Arc::new(FaultInjector::new(config)?);
+        injectors.insert(key.to_string(), injector.clone());
+        Ok(injector)
+    }
+
+    /// A copy for building a new configuration's injectors, so that a failed build leaves these as they are
+    pub fn stage(&self) -> Self {
+        Self { injectors: Mutex::new(self.injectors.lock().unwrap().clone()) }
+    }
+
+    /// Replace the injectors with those of a staged copy
+    pub fn install(&self, staged: &Faults) {
+        *self.injectors.lock().unwrap() = staged.injectors.lock().unwrap().clone();
+    }
+
+    /// Switch an endpoint's faults on or off, returning whether they are now on, None if it has none
+    pub fn toggle(&self, key: &str) -> Option<bool> {
+        self.injectors.lock().unwrap().get(key).map(|injector| injector.toggle())
+    }
+
+    /// State of an endpoint's faults, None if it has none
+    pub fn info(&self, key: &str) -> Option<FaultInfo> {
+        self.injectors.lock().unwrap().get(key).map(|injector| FaultInfo {
+            enabled: injector.is_enabled(),
+            faults: injector.describe(),
+        })
+    }
+}
+
+impl FaultInjector {
+    pub fn new(config: &FaultConfig) -> Result<Self, String> {
+        let duration = |field: &str, value: &str| parse_duration(value)
+            .ok_or_else(|| format!("`{}`: invalid duration `{}`, use e.g. 250ms or 2s", field, value));
+        let delay = match &config.delay {
+            None => None,
+            Some(DelayConfig::Fixed(delay)) => Some(Delay::Fixed(duration("delay", delay)?)),
+            Some(DelayConfig::Uniform { min, max }) => {
+                let (min, max) = (duration("delay.min", min)?, duration("delay.max", max)?);
+                if min > max {
+                    return Err("`delay.min` must not be greater than `delay.max`".to_string());
+                }
+                Some(Delay::Uniform(min, max))
+            },
+            Some(DelayConfig::Normal { mean, stddev }) => Some(Delay::Normal(
+                duration("delay.mean", mean)?.as_secs_f64(),
+                duration("delay.stddev", stddev)?.as_secs_f64(),
+            )),
+        };
+
+        let rate = |field: &str, rate: Option<f64>| match rate {
+            Some(rate) if !(0.0..=1.0).contains(&rate) => Err(format!("`{}` must be between 0 and 1, got {}", field, rate)),
+            rate => Ok(rate.unwrap_or(0.0)),
+        };
+        let error_status = match config.error_status {
+            Some(status) => StatusCode::from_u16(status).ok().filter(|status| status.as_u16() >= 200)
+                .ok_or_else(|| format!("`error_status`: invalid status {}", status))?,
+            None => DEFAULT_ERROR_STATUS,
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            delay,
+            error_rate: rate("error_rate", config.error_rate)?,
+            error_status,
+            truncate_rate: rate("truncate_rate", config.truncate_rate)?,
+            close_rate: rate("close_rate", config.close_rate)?,
+            enabled: AtomicBool::new(config.enabled.unwrap_or(true)),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Switch the faults on or off, returning whether they are now on
+    pub fn toggle(&self) -> bool {
+        !self.enabled.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    /// Pick the faults of a request, None while the injector is switched off
+    pub fn inject(&self) -> Option<Injection> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut rng = rand::rng();
+        let delay = self.delay.as_ref().map(|delay| match *delay {
+            Delay::Fixed(delay) => delay,
+            Delay::Uniform(min, max) => rng.random_range(min..=max),
+            Delay::Normal(mean, stddev) => {
+                // Box-Muller transform of two uniform samples
+                let (u, v): (f64, f64) = (1.0 - rng.random::<f64>(), rng.random());
+                let sample = mean + stddev * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos();
+                Duration::try_from_secs_f64(sample).unwrap_or_default()
+            },
+        });
+        let fault = [(Fault::Close, self.close_rate), (Fault::Error, self.error_rate), (Fault::Truncate, self.truncate_rate)]
+            .into_iter()
+            .find(|(_, rate)| rng.random_bool(*rate))
+            .map(|(fault, _)| fault);
+        Some(Injection { delay, fault })
+    }
+
+    /// The body of injected errors
+    pub fn error_body(&self) -> String {
+        self.config.error_body.clone()
+            .unwrap_or_else(|| format!("Injected fault: {}", self.error_status))
+    }
+
+    /// The response of an injected error
+    pub fn error_response(&self) -> Response {
+        (self.error_status, self.error_body()).into_response()
+    }
+
+    /// Log description of the faults picked for a request, e.g. `delay 120ms, error 503`
+    pub fn describe_injection(&self, injection: &Injection) -> Option<String> {
+        let mut faults = Vec::new();
+        if let Some(delay) = injection.delay {
+            faults.push(format!("delay {}ms", delay.as_millis()));
+        }
+        match injection.fault {
+            Some(Fault::Close) => faults.push("closed".to_string()),
+            Some(Fault::Error) => faults.push(format!("error {}", self.error_status.as_u16())),
+            Some(Fault::Truncate) => faults.push("truncated".to_string()),
+            None => {},
+        }
+        (!faults.is_empty()).then(|| faults.join(", "))
+    }
+
+    // The configured faults, e.g. `delay 100ms-500ms, 10% 503, 5% truncated, 1% closed`
+    fn describe(&self) -> String {
+        let mut faults = Vec::new();
+        match &self.config.delay {
+            Some(DelayConfig::Fixed(delay)) => faults.push(format!("delay {}", delay)),
+            Some(DelayConfig::Uniform { min, max }) => faults.push(format!("delay {}-{}", min, max)),
+            Some(DelayConfig::Normal { mean, stddev }) => faults.push(format!("delay {} ± {}", mean, stddev)),
+            None => {},
+        }
+        let percent = |rate: f64| format!("{}%", (rate * 1000.0).round() / 10.0);
+        if self.error_rate > 0.0 {
+            faults.push(format!("{} {}", percent(self.error_rate), self.error_status.as_u16()));
+        }
+        if self.truncate_rate > 0.0 {
+            faults.push(format!("{} truncated", percent(self.truncate_rate)));
+        }
+        if self.close_rate > 0.0 {
+            faults.push(format!("{} closed", percent(self.close_rate)));
+        }
+        if faults.is_empty() {
+            faults.push("none".to_string());
+        }
+        faults.join(", ")
+    }
+}
+
+/// A response whose body fails at once, so the server drops the connection without answering
+///
+/// Nothing is sent, the status is only what the request is logged and counted with.
+pub fn closed_connection() -> Response {
+    let body = Body::from_stream(stream::iter([Err::<Bytes, _>(fault_error())]));
+    (StatusCode::SERVICE_UNAVAILABLE, body).into_response()
+}
+
+/// Cut a response off halfway through its body, its headers still announce the whole body
+///
+/// The body is streamed through up to half its length, or half its first chunk when its length
+/// isn't known, then the connection is dropped.
+pub fn truncate(response: Response) -> Response {
+    let (mut parts, body) = response.into_parts();
+    let length = parts.headers.get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<u64>().ok())
+        .or_else(|| body.size_hint().exact());
+    if let Some(length) = length {
+        parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+    }
+    let remaining = length.map(|length| length / 2);
+    let half = stream::unfold((body.into_data_stream(), remaining), |(mut data, remaining)| async move {
+        if remaining == Some(0) {
+            return None;
+        }
+        let chunk = match data.next().await? {
+            Ok(chunk) => chunk,
+            Err(e) => return Some((Err(io::Error::other(e)), (data, Some(0)))),
+        };
+        let limit = remaining.unwrap_or(chunk.len() as u64 / 2);
+        let keep = limit.min(chunk.len() as u64);
+        Some((Ok(chunk.slice(..keep as usize)), (data, Some(limit - keep))))
+    });
+    // The error is delayed until the first half has been written, the connection is dropped
+    // without flushing buffered data otherwise
+    let error = stream::once(async {
+        tokio::task::yield_now().await;
+        Err(fault_error())
+    });
+    Response::from_parts(parts, Body::from_stream(half.chain(error)))
+}
+
+fn fault_error() -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionAborted, "connection closed by fault injection")
+}
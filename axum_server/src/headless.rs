@@ -0,0 +1,96 @@
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use axum_tui::{LogEvent, LogLevel};
+use clap::ValueEnum;
+
+/// Output format for logs when running without the terminal UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines, warnings and errors go to stderr
+    Text,
+    /// One JSON object per line on stdout
+    Json,
+}
+
+/// Writes log events to stdout and stderr in place of the terminal UI
+pub struct HeadlessLogger {
+    thread: JoinHandle<()>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl HeadlessLogger {
+    /// Start printing the events received on `log_rx`
+    pub fn spawn(log_rx: mpsc::Receiver<LogEvent>, format: LogFormat) -> Self {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stop = stopped.clone();
+
+        let thread = thread::spawn(move || loop {
+            match log_rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(event) => print_event(&event, format),
+                // The queue is empty here, so nothing is lost by stopping
+                Err(mpsc::RecvTimeoutError::Timeout) if stop.load(Ordering::Relaxed) => break,
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        });
+
+        Self { thread, stopped }
+    }
+
+    /// Print the remaining events and wait for the logger to stop
+    pub fn finish(self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.thread.join().ok();
+    }
+}
+
+fn print_event(event: &LogEvent, format: LogFormat) {
+    // Write errors are ignored, e.g. when stdout is a closed pipe
+    match format {
+        LogFormat::Json => {
+            writeln!(io::stdout().lock(), "{}", event.to_json()).ok();
+        },
+        LogFormat::Text => {
+            let line = format!("{} {:<5} [{}] {}",
+                event.timestamp_rfc3339(),
+                event.level,
+                event.source,
+                event.text());
+
+            if event.level >= LogLevel::Warn {
+                writeln!(io::stderr().lock(), "{}", line).ok();
+            } else {
+                writeln!(io::stdout().lock(), "{}", line).ok();
+            }
+        },
+    }
+}
+
+/// Wait for SIGINT, or SIGTERM on Unix
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c().await.ok();
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            },
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
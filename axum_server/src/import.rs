@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use axum::http::{Method, Uri};
+use serde_json::{json, Map, Value};
+
+use crate::config::{Config, EndpointConfig, EndpointMethod, ServerConfig};
+
+/// Operations of a path item, in the order they are imported
+const OPERATIONS: [&str; 8] = ["get", "put", "post", "delete", "options", "head", "patch", "trace"];
+
+/// Port used when the document's server URL doesn't name one
+const DEFAULT_PORT: u16 = 3000;
+
+/// Endpoints imported from an OpenAPI document
+pub struct Import {
+    pub config: Config,
+    /// Parts of the document that could not be imported
+    pub warnings: Vec<String>,
+}
+
+/// Build a configuration with one template endpoint per operation of an OpenAPI 3 document
+///
+/// The document may be JSON or YAML. Responses come from the examples of the first
+/// successful response, or are generated from its schema.
+pub fn import(source: &str) -> Result<Import, String> {
+    // JSON is valid YAML, so one parser reads both
+    let document: Value = serde_yaml::from_str(source).map_err(|e| format!("invalid document: {}", e))?;
+    match document.get("openapi").and_then(Value::as_str) {
+        Some(version) if version.starts_with("3.") => {},
+        Some(version) => return Err(format!("OpenAPI {} is not supported, only 3.x documents can be imported", version)),
+        None => return Err("not an OpenAPI 3 document, the `openapi` field is missing".to_string()),
+    }
+
+    let mut importer = Importer { document: &document, warnings: Vec::new(), following: Vec::new() };
+    let (port, base_path) = importer.server();
+    let mut paths = PathNames::default();
+    let mut endpoints = Vec::new();
+
+    let empty = Map::new();
+    let items = document.get("paths").and_then(Value::as_object).unwrap_or(&empty);
+    for (path, item) in items {
+        let item = importer.resolve(item);
+        let route = match path.as_str() {
+            "/" if !base_path.is_empty() => paths.normalize(&base_path),
+            path => paths.normalize(&format!("{}{}", base_path, path)),
+        };
+        for name in OPERATIONS {
+            if let Some(operation) = item.get(name) {
+                let operation = importer.resolve(operation);
+                endpoints.push(importer.endpoint(&route, name, &operation));
+            }
+        }
+    }
+
+    Ok(Import {
+        config: Config {
+            server: ServerConfig { port, ..ServerConfig::default() },
+            endpoints,
+        },
+        warnings: importer.warnings,
+    })
+}
+
+struct Importer<'a> {
+    document: &'a Value,
+    warnings: Vec<String>,
+    /// References being resolved, to stop at cycles
+    following: Vec<String>,
+}
+
+impl Importer<'_> {
+    // Port and base path of the first server, relative URLs only have a path
+    fn server(&mut self) -> (u16, String) {
+        let Some(url) = self.document.pointer("/servers/0/url").and_then(Value::as_str) else {
+            return (DEFAULT_PORT, String::new());
+        };
+        match url.parse::<Uri>() {
+            Ok(uri) => (
+                uri.port_u16().unwrap_or(DEFAULT_PORT),
+                uri.path().trim_end_matches('/').to_string(),
+            ),
+            Err(_) => {
+                self.warnings.push(format!("servers[0]: ignoring URL `{}`", url));
+                (DEFAULT_PORT, String::new())
+            }
+        }
+    }
+
+    fn endpoint(&mut self, path: &str, name: &str, operation: &Value) -> EndpointConfig {
+        let method = name.to_uppercase();
+        let mut params = HashMap::new();
+
+        let (status, media_type, media) = self.success_response(operation);
+        if status != "200" {
+            params.insert("status".to_string(), status);
+        }
+
+        let example = media.as_ref().map(|media| self.example(media)).unwrap_or(Value::Null);
+        let response = match &example {
+            Value::Null => String::new(),
+            // Text examples are served as they are
+            Value::String(text) if !is_json(&media_type) => text.clone(),
+            example => serde_json::to_string_pretty(example).unwrap_or_default(),
+        };
+        // The template handler only detects JSON objects and arrays on its own
+        if !media_type.is_empty() && (media_type != "application/json" || !(example.is_object() || example.is_array())) {
+            params.insert("content_type".to_string(), media_type);
+        }
+
+        // Operations are always one of the standard methods
+        let endpoint_method = EndpointMethod::Methods(vec![Method::from_bytes(method.as_bytes()).unwrap_or(Method::GET)]);
+        EndpointConfig {
+            handler: "template".to_string(),
+            response: escape_template(&response),
+            description: description(operation, &method, path),
+            params,
+            request_schema: operation.pointer("/requestBody/content")
+                .and_then(|content| media_entry(content).map(|(_, media)| media))
+                .and_then(|media| media.get("schema"))
+                .map(|schema| self.resolve(schema)),
+            response_schema: media.as_ref().and_then(|media| media.get("schema")).cloned(),
+            ..EndpointConfig::new(endpoint_method, path)
+        }
+    }
+
+    // Status, media type and media object of the response that is served
+    fn success_response(&mut self, operation: &Value) -> (String, String, Option<Value>) {
+        let Some(responses) = operation.get("responses").and_then(Value::as_object) else {
+            return ("200".to_string(), String::new(), None);
+        };
+
+        // The first successful response, `default` when the operation only lists that
+        let (status, response) = responses.iter()
+            .find(|(status, _)| status.starts_with('2'))
+            .or_else(|| responses.get_key_value("default"))
+            .map(|(status, response)| match status.as_str() {
+                // Ranges like `2XX` and `default` are served as 200
+                "default" => ("200".to_string(), response),
+                status if status.parse::<u16>().is_err() => ("200".to_string(), response),
+                status => (status.to_string(), response),
+            })
+            .unwrap_or(("200".to_string(), &Value::Null));
+
+        let response = self.resolve(response);
+        match response.get("content").and_then(media_entry) {
+            Some((media_type, media)) => (status, media_type.clone(), Some(self.resolve(media))),
+            None => (status, String::new(), None),
+        }
+    }
+
+    // The example of a media object, generated from the schema if it has none
+    fn example(&mut self, media: &Value) -> Value {
+        if let Some(example) = media.get("example") {
+            return example.clone();
+        }
+        let named = media.get("examples")
+            .and_then(Value::as_object)
+            .and_then(|examples| examples.values().next());
+        if let Some(example) = named {
+            if let Some(value) = self.resolve(example).get("value") {
+                return value.clone();
+            }
+        }
+        match media.get("schema") {
+            Some(schema) => generate(&self.resolve(schema)),
+            None => Value::Null,
+        }
+    }
+
+    // Replace local `$ref`s with what they point to
+    fn resolve(&mut self, value: &Value) -> Value {
+        match value {
+            Value::Object(object) => {
+                if let Some(reference) = object.get("$ref").and_then(Value::as_str) {
+                    return self.follow(reference);
+                }
+                Value::Object(object.iter().map(|(key, value)| (key.clone(), self.resolve(value))).collect())
+            },
+            Value::Array(items) => Value::Array(items.iter().map(|item| self.resolve(item)).collect()),
+            value => value.clone(),
+        }
+    }
+
+    fn follow(&mut self, reference: &str) -> Value {
+        // A recursive schema is only expanded once
+        if self.following.iter().any(|followed| followed == reference) {
+            return json!({});
+        }
+        let Some(pointer) = reference.strip_prefix('#') else {
+            self.warning(format!("`{}`: only references within the document are supported", reference));
+            return json!({});
+        };
+        match self.document.pointer(pointer) {
+            Some(target) => {
+                self.following.push(reference.to_string());
+                let resolved = self.resolve(target);
+                self.following.pop();
+                resolved
+            },
+            None => {
+                self.warning(format!("`{}` does not point to anything", reference));
+                json!({})
+            }
+        }
+    }
+
+    fn warning(&mut self, message: String) {
+        if !self.warnings.contains(&message) {
+            self.warnings.push(message);
+        }
+    }
+}
+
+/// Renames path parameters so that routes sharing a prefix agree on the names
+///
+/// OpenAPI allows `/users/{id}` next to `/users/{userId}/posts`, the router does not.
+#[derive(Default)]
+struct PathNames {
+    /// Parameter name of each prefix, e.g. `/users/` -> `id`
+    names: HashMap<String, String>,
+}
+
+impl PathNames {
+    fn normalize(&mut self, path: &str) -> String {
+        let mut normalized = String::new();
+        for (index, segment) in path.split('/').enumerate() {
+            if index > 0 {
+                normalized.push('/');
+            }
+            match segment.strip_prefix('{').and_then(|rest| rest.strip_suffix('}')) {
+                Some(name) if !name.starts_with('*') && !name.contains(['{', '}']) => {
+                    let name = self.names.entry(normalized.clone()).or_insert_with(|| name.to_string());
+                    normalized.push_str(&format!("{{{}}}", name));
+                },
+                _ => normalized.push_str(segment),
+            }
+        }
+        normalized
+    }
+}
+
+// The JSON media of a content map if there is one, otherwise the first
+fn media_entry(content: &Value) -> Option<(&String, &Value)> {
+    let content = content.as_object()?;
+    content.get_key_value("application/json")
+        .or_else(|| content.iter().find(|(media_type, _)| is_json(media_type)))
+        .or_else(|| content.iter().next())
+}
+
+fn is_json(media_type: &str) -> bool {
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    essence == "application/json" || essence.ends_with("+json")
+}
+
+// Summary, first line of the description, operation id, or the method and path
+fn description(operation: &Value, method: &str, path: &str) -> String {
+    let text = |key: &str| operation.get(key)
+        .and_then(Value::as_str)
+        .map(|text| text.lines().next().unwrap_or_default().trim().to_string())
+        .filter(|text| !text.is_empty());
+    text("summary")
+        .or_else(|| text("description"))
+        .or_else(|| text("operationId"))
+        .unwrap_or_else(|| format!("{} {}", method, path))
+}
+
+// Template syntax in an example is served as it is
+fn escape_template(response: &str) -> String {
+    if response.contains("{{") || response.contains("{%") || response.contains("{#") {
+        format!("{{% raw %}}{}{{% endraw %}}", response)
+    } else {
+        response.to_string()
+    }
+}
+
+// An example value matching a resolved schema
+fn generate(schema: &Value) -> Value {
+    if let Some(example) = schema.get("example")
+        .or_else(|| schema.get("examples").and_then(|examples| examples.get(0)))
+        .or_else(|| schema.get("default"))
+        .or_else(|| schema.get("const"))
+        .or_else(|| schema.get("enum").and_then(|values| values.get(0)))
+    {
+        return example.clone();
+    }
+
+    if let Some(schemas) = schema.get("allOf").and_then(Value::as_array) {
+        let mut merged = Map::new();
+        for example in schemas.iter().map(generate) {
+            match example {
+                Value::Object(object) => merged.extend(object),
+                example if merged.is_empty() => return example,
+                _ => {},
+            }
+        }
+        return Value::Object(merged);
+    }
+    if let Some(first) = schema.get("oneOf").or_else(|| schema.get("anyOf")).and_then(|schemas| schemas.get(0)) {
+        return generate(first);
+    }
+
+    // 3.1 allows a list of types, e.g. `[string, "null"]`
+    let kind = match schema.get("type") {
+        Some(Value::String(kind)) => kind.as_str(),
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).find(|kind| *kind != "null").unwrap_or("null"),
+        _ if schema.get("properties").is_some() => "object",
+        _ if schema.get("items").is_some() => "array",
+        _ => "",
+    };
+    match kind {
+        "object" => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            Value::Object(properties.iter()
+                .flat_map(|properties| properties.iter())
+                .map(|(name, property)| (name.clone(), generate(property)))
+                .collect())
+        },
+        "array" => match schema.get("items") {
+            Some(items) => json!([generate(items)]),
+            None => json!([]),
+        },
+        "string" => json!(match schema.get("format").and_then(Value::as_str) {
+            Some("date-time") => "2024-01-01T00:00:00Z",
+            Some("date") => "2024-01-01",
+            Some("time") => "00:00:00",
+            Some("uuid") => "3fa85f64-5717-4562-b3fc-2c963f66afa6",
+            Some("email") => "user@example.com",
+            Some("uri") | Some("url") => "https://example.com",
+            Some("hostname") => "example.com",
+            Some("ipv4") => "192.0.2.1",
+            Some("ipv6") => "2001:db8::1",
+            Some("byte") => "ZXhhbXBsZQ==",
+            _ => "string",
+        }),
+        "integer" => json!(0),
+        "number" => json!(0.0),
+        "boolean" => json!(true),
+        "null" => Value::Null,
+        _ => json!({}),
+    }
+}
@@ -0,0 +1,26 @@
+pub mod config;
+pub mod routes;
+pub mod validate;
+pub mod reload;
+pub mod headless;
+pub mod metrics;
+pub mod proxy;
+pub mod rate_limit;
+pub mod faults;
+pub mod cors;
+pub mod auth;
+pub mod record;
+pub mod replay;
+pub mod openapi;
+pub mod import;
+pub mod check;
+pub mod tls;
+pub mod server;
+#[cfg(feature = "testing")]
+pub mod testing;
+
+pub use config::{Config, EndpointConfig, EndpointMethod, ServerConfig};
+pub use routes::{AppState, create_router};
+pub use server::{LogOutput, ServerBuilder, ServerHandle};
+#[cfg(feature = "testing")]
+pub use testing::TestServer;
@@ -1,23 +1,125 @@
-mod config;
-mod routes;
 mod custom_handlers;
 
-use tokio::sync::watch;
-use std::thread;
-use std::sync::{mpsc, Arc};
-use clap::Parser;
-use crate::config::Config;
-use crate::routes::{AppState, create_router};
-use axum_tui::{run_ui, ServerInfo, EndpointInfo};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use axum::middleware;
+use clap::{Parser, Subcommand, ValueEnum};
 use axum_handlers::{HandlerRegistry, register_default_handlers};
-use crate::custom_handlers::register_custom_handlers;
+use custom_handlers::register_custom_handlers;
+use axum_server::validate::{validate_file, validate_str, Validation};
+use axum_server::headless::{shutdown_signal, LogFormat};
+use axum_server::record::{self, Recorder};
+use axum_server::check::{self, ReportFormat};
+use axum_server::proxy::parse_duration;
+use axum_server::config::{EndpointConfig, Site, DEFAULT_LISTENER};
+use axum_server::{import, openapi, LogOutput, ServerBuilder};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to the configuration file
-    #[arg(short, long, default_value = "config.yaml")]
+    #[arg(short, long, default_value = "config.yaml", global = true)]
     config: String,
+
+    /// Run without the terminal UI and write logs to stdout/stderr.
+    /// This is the default when stdout is not a terminal.
+    #[arg(long)]
+    headless: bool,
+
+    /// Log format in headless mode
+    #[arg(long, value_enum, default_value = "text")]
+    log_format: LogFormat,
+
+    /// Serve HTTPS with a certificate for localhost generated at startup,
+    /// in place of the certificate configured in `server.tls`
+    #[arg(long)]
+    self_signed: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Validate the configuration file and exit
+    Validate {
+        /// Treat warnings as errors
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Forward every request to an upstream and record the exchanges as replay endpoints
+    Record {
+        /// Base URL of the server to record, e.g. http://127.0.0.1:8080
+        #[arg(long)]
+        upstream: String,
+        /// Configuration file the recorded endpoints are written to
+        #[arg(short, long, default_value = "recorded.yaml")]
+        output: PathBuf,
+        /// Directory the recorded bodies are written to
+        #[arg(long, default_value = "fixtures")]
+        fixtures: PathBuf,
+        /// Record request bodies so that replay also matches requests by body
+        #[arg(long)]
+        match_body: bool,
+    },
+    /// Print the OpenAPI document of the configured endpoints and exit
+    Openapi {
+        /// Write the document to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Format of the document
+        #[arg(long, value_enum, default_value = "json")]
+        format: DocumentFormat,
+        /// Document the endpoints served on this listener
+        #[arg(long, default_value = DEFAULT_LISTENER)]
+        listener: String,
+        /// Document the endpoints served for this virtual host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// Send the checks of each endpoint to a running server and report which pass
+    Check {
+        /// Server to check, the configured address by default
+        #[arg(long)]
+        base_url: Option<String>,
+        /// Format of the report
+        #[arg(long, value_enum, default_value = "table")]
+        format: ReportFormat,
+        /// Write the report to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Time to wait for each response, e.g. 500ms or 10s
+        #[arg(long, default_value = "10s", value_parser = parse_timeout)]
+        timeout: Duration,
+    },
+    /// Create a configuration with an endpoint for each operation of an OpenAPI 3 document
+    ImportOpenapi {
+        /// OpenAPI document in JSON or YAML
+        file: PathBuf,
+        /// Write the configuration to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum DocumentFormat {
+    Json,
+    Yaml,
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    parse_duration(value).ok_or_else(|| format!("invalid duration `{}`, use e.g. 500ms or 10s", value))
+}
+
+// Print every diagnostic followed by a summary
+fn print_validation(validation: &Validation) {
+    for diagnostic in &validation.diagnostics {
+        eprintln!("{}", validation.format(diagnostic));
+    }
+    eprintln!("{}", validation.summary());
 }
 
 #[tokio::main]
@@ -25,76 +127,122 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command-line arguments
     let args = Args::parse();
     
-    // Load configuration
-    let config = Config::load(&args.config)?;
-    
-    // Create a channel for sending logs from the server to the UI
-    let (log_tx, log_rx) = mpsc::channel::<String>();
-    
-    // Create a channel for graceful shutdown
-    let (shutdown_tx, mut shutdown_rx) = watch::channel::<()>(());
-
-    // Log server startup
-    log_tx.send(format!("Server starting with configuration from {}", args.config))?;
-    log_tx.send(format!("Server will listen on {}:{}", config.server.host, config.server.port))?;
-    
     // Create and initialize the handler registry
     let mut handler_registry = HandlerRegistry::new();
     register_default_handlers(&mut handler_registry);
     register_custom_handlers(&mut handler_registry);
-    
-    // Log configured endpoints
-    for endpoint in &config.endpoints {
-        log_tx.send(format!("Configured endpoint: {} {} - {}", 
-            endpoint.method, 
-            endpoint.path, 
-            endpoint.description))?;
-        
-        // Log whether the endpoint uses a custom handler or the default
-        if handler_registry.contains(&endpoint.handler) {
-            log_tx.send(format!("  Using custom handler: {}", endpoint.handler))?;
-        } else {
-            log_tx.send(format!("  Using default handler with static response"))?;
+
+    // Importing creates a configuration, so there is none to load yet
+    if let Some(Command::ImportOpenapi { file, output }) = &args.command {
+        let source = std::fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+        let imported = import::import(&source).map_err(|e| format!("{}: {}", file.display(), e))?;
+        for warning in &imported.warnings {
+            eprintln!("{}: warning: {}", file.display(), warning);
         }
+
+        let yaml = serde_yaml::to_string(&imported.config)?;
+        // Report what the generated configuration still needs, e.g. conflicting routes
+        let name = output.as_ref().map_or("<stdout>".to_string(), |path| path.display().to_string());
+        let validation = validate_str(&name, &yaml, &handler_registry);
+        for diagnostic in &validation.diagnostics {
+            eprintln!("{}", validation.format(diagnostic));
+        }
+
+        match output {
+            Some(path) => {
+                std::fs::write(path, yaml)?;
+                eprintln!("Imported {} endpoint(s) into {}", imported.config.endpoints.len(), path.display());
+            },
+            None => {
+                write!(std::io::stdout(), "{}", yaml).ok();
+            },
+        }
+        return Ok(());
+    }
+
+    // Load and validate the configuration
+    let validation = validate_file(&args.config, &handler_registry)
+        .map_err(|e| format!("Failed to read {}: {}", args.config, e))?;
+
+    if let Some(Command::Validate { strict }) = args.command {
+        print_validation(&validation);
+        let failed = validation.has_errors() || (strict && validation.warning_count() > 0);
+        std::process::exit(if failed { 1 } else { 0 });
     }
 
-    // Create server info for the UI
-    let server_info = ServerInfo {
-        host: config.server.host.clone(),
-        port: config.server.port,
-        endpoints: config.endpoints.iter().map(|e| EndpointInfo {
-            path: e.path.clone(),
-            method: e.method.clone(),
-            description: e.description.clone(),
-        }).collect(),
+    let config = match validation.config.clone() {
+        Some(config) if !validation.has_errors() => config,
+        _ => {
+            print_validation(&validation);
+            std::process::exit(1);
+        }
     };
+    
+    if let Some(Command::Openapi { output, format, listener, host }) = &args.command {
+        let endpoints: Vec<&EndpointConfig> = config.endpoints.iter().collect();
+        let site = Site::of_request(&Site::all(&endpoints), listener, host.as_deref());
+        let document = openapi::document(&config, &handler_registry, &site);
+        let text = match format {
+            DocumentFormat::Json => serde_json::to_string_pretty(&document)?,
+            DocumentFormat::Yaml => serde_yaml::to_string(&document)?,
+        };
+        match output {
+            Some(path) => std::fs::write(path, text)?,
+            // A closed pipe, e.g. from `| head`, is not an error
+            None => {
+                writeln!(std::io::stdout(), "{}", text).ok();
+            },
+        }
+        return Ok(());
+    }
 
-    // Share the log sender, config, and handler registry across routes using Arc
-    let state = Arc::new(AppState {
-        log_sender: log_tx,
-        config: config.clone(),
-        handler_registry,
-    });
+    if let Some(Command::Check { base_url, format, output, timeout }) = &args.command {
+        let base_url = base_url.clone().unwrap_or_else(|| check::default_base_url(&config));
+        let results = check::run(&config, &base_url, *timeout).await?;
+        let report = match format {
+            ReportFormat::Table => check::table(&results),
+            ReportFormat::Junit => check::junit(&args.config, &results),
+        };
+        match output {
+            Some(path) => std::fs::write(path, report)?,
+            None => {
+                write!(std::io::stdout(), "{}", report).ok();
+            },
+        }
+        let failed = results.iter().any(|result| !result.passed());
+        std::process::exit(if failed { 1 } else { 0 });
+    }
 
-    // Create the router with dynamic routes
-    let app = create_router(state);
+    let mut builder = ServerBuilder::new(config.clone())
+        .handlers(handler_registry)
+        .tls_self_signed(args.self_signed);
+    let log_tx = builder.log_sender();
+    log_tx.send(format!("Server starting with configuration from {}", args.config).into())?;
 
-    // Set up the server to listen on the configured address
-    let addr = config.get_socket_addr()?;
-    let listener = tokio::net::TcpListener::bind(&addr).await?;
-    
-    // Spawn the UI thread with server info
-    let ui_thread = thread::spawn(move || {
-        run_ui(log_rx, shutdown_tx, Some(server_info));
-    });
-
-    // Run the server in the main thread with graceful shutdown
-    axum::serve(listener, app.into_make_service()).with_graceful_shutdown(async move {
-        shutdown_rx.changed().await.ok();
-    }).await?;
-
-    // Wait for the UI thread to finish
-    ui_thread.join().unwrap();
-    
-    Ok(())
-} 
\ No newline at end of file
+    match &args.command {
+        // While recording every request goes to the upstream and the configured endpoints are not served.
+        // The file isn't passed on, reloading it would replace the recording proxy with its endpoints.
+        Some(Command::Record { upstream, output, fixtures, match_body }) => {
+            let recorder = Recorder::new(upstream, output, fixtures, *match_body, &config.server, log_tx.clone())?;
+            log_tx.send(format!("Recording requests to {} into {}", upstream, output.display()).into())?;
+            builder = builder
+                .config(recorder.recording_config(&config))
+                .layer(middleware::from_fn_with_state(Arc::new(recorder), record::record));
+        },
+        _ => {
+            builder = builder.config_file(&args.config).reload_on_hangup(true);
+        },
+    }
+
+    if args.headless || !std::io::stdout().is_terminal() {
+        // Print logs instead of drawing the UI and stop on SIGINT or SIGTERM
+        builder = builder
+            .log_output(LogOutput::Print(args.log_format))
+            .shutdown_signal(shutdown_signal());
+    } else {
+        builder = builder.log_output(LogOutput::Ui);
+    }
+
+    builder.start().await?.wait().await
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::http::Method;
+use axum_tui::EndpointStats;
+
+use crate::config::EndpointMethod;
+
+/// Upper bounds of the latency histogram buckets, in seconds
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Methods that are labelled as they are
+const STANDARD_METHODS: [Method; 9] = [
+    Method::GET,
+    Method::HEAD,
+    Method::POST,
+    Method::PUT,
+    Method::DELETE,
+    Method::CONNECT,
+    Method::OPTIONS,
+    Method::TRACE,
+    Method::PATCH,
+];
+
+/// Method label of non-standard methods the endpoint doesn't declare, e.g. those served by ANY endpoints
+const OTHER_METHOD: &str = "OTHER";
+
+/// Labels of a metrics series
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    method: String,
+    route: String,
+    handler: String,
+}
+
+#[derive(Debug, Default)]
+struct Series {
+    /// Request count by status code
+    statuses: BTreeMap<u16, u64>,
+    errors: u64,
+    /// Request count per latency bucket, not cumulative
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    /// Sum of latencies in seconds
+    sum: f64,
+}
+
+impl Series {
+    fn record(&mut self, status: u16, latency: Duration) {
+        let seconds = latency.as_secs_f64();
+        *self.statuses.entry(status).or_default() += 1;
+        if status >= 400 {
+            self.errors += 1;
+        }
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&bound| seconds <= bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn add(&mut self, other: &Series) {
+        for (status, count) in &other.statuses {
+            *self.statuses.entry(*status).or_default() += count;
+        }
+        self.errors += other.errors;
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+
+    // Upper bound of the bucket holding the given quantile, None if it is above the last bucket
+    fn quantile(&self, quantile: f64) -> Option<Duration> {
+        let target = (self.count as f64 * quantile).ceil() as u64;
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.buckets) {
+            cumulative += count;
+            if cumulative >= target {
+                return Some(Duration::from_secs_f64(*bound));
+            }
+        }
+        None
+    }
+
+    fn stats(&self) -> EndpointStats {
+        EndpointStats {
+            requests: self.count,
+            errors: self.errors,
+            average_latency: (self.count > 0).then(|| Duration::from_secs_f64(self.sum / self.count as f64)),
+            p95_latency: (self.count > 0).then(|| self.quantile(0.95)).flatten(),
+        }
+    }
+}
+
+/// Request counters and latency histograms for the configured endpoints
+#[derive(Debug, Default)]
+pub struct Metrics {
+    series: Mutex<BTreeMap<SeriesKey, Series>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a completed request. `route` is the path template of the endpoint.
+    pub fn record(&self, method: &str, route: &str, handler: &str, status: u16, latency: Duration) {
+        let key = SeriesKey {
+            method: method.to_string(),
+            route: route.to_string(),
+            handler: handler.to_string(),
+        };
+        self.series.lock().unwrap().entry(key).or_default().record(status, latency);
+    }
+
+    /// Statistics for requests to a route with a method accepted by `include`
+    pub fn endpoint_stats(&self, route: &str, include: impl Fn(&str) -> bool) -> EndpointStats {
+        let series = self.series.lock().unwrap();
+        let mut total = Series::default();
+        for (key, series) in series.iter() {
+            if key.route == route && include(&key.method) {
+                total.add(series);
+            }
+        }
+        total.stats()
+    }
+
+    /// All metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap();
+        let mut out = String::new();
+
+        out.push_str("# HELP http_requests_total Total number of HTTP requests.\n");
+        out.push_str("# TYPE http_requests_total counter\n");
+        for (key, series) in series.iter() {
+            for (status, count) in &series.statuses {
+                writeln!(out, "http_requests_total{{{},status=\"{}\"}} {}", labels(key), status, count).ok();
+            }
+        }
+
+        out.push_str("# HELP http_request_errors_total Total number of HTTP requests answered with a 4xx or 5xx status.\n");
+        out.push_str("# TYPE http_request_errors_total counter\n");
+        for (key, series) in series.iter() {
+            writeln!(out, "http_request_errors_total{{{}}} {}", labels(key), series.errors).ok();
+        }
+
+        out.push_str("# HELP http_request_duration_seconds Time taken to handle HTTP requests.\n");
+        out.push_str("# TYPE http_request_duration_seconds histogram\n");
+        for (key, series) in series.iter() {
+            let labels = labels(key);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(series.buckets) {
+                cumulative += count;
+                writeln!(out, "http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound, cumulative).ok();
+            }
+            writeln!(out, "http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, series.count).ok();
+            writeln!(out, "http_request_duration_seconds_sum{{{}}} {}", labels, series.sum).ok();
+            writeln!(out, "http_request_duration_seconds_count{{{}}} {}", labels, series.count).ok();
+        }
+
+        out
+    }
+}
+
+/// Method label of a request to an endpoint, non-standard methods are only labelled as they are
+/// when the endpoint declares them, so that clients can't create new series at will
+pub fn method_label<'a>(method: &'a Method, endpoint: &EndpointMethod) -> &'a str {
+    let declared = matches!(endpoint, EndpointMethod::Methods(methods) if methods.contains(method));
+    if declared || STANDARD_METHODS.contains(method) {
+        method.as_str()
+    } else {
+        OTHER_METHOD
+    }
+}
+
+fn labels(key: &SeriesKey) -> String {
+    format!("method=\"{}\",route=\"{}\",handler=\"{}\"",
+        escape_label(&key.method),
+        escape_label(&key.route),
+        escape_label(&key.handler))
+}
+
+// Escape a label value as required by the text format
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
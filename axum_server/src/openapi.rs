@@ -0,0 +1,266 @@
+use std::net::IpAddr;
+
+use axum::http::Method;
+use axum_handlers::HandlerRegistry;
+use serde_json::{json, Map, Value};
+
+use crate::auth::DEFAULT_API_KEY_HEADER;
+use crate::config::{AuthConfig, Config, EndpointConfig, EndpointMethod, Site};
+use crate::proxy::PROXY_HANDLER;
+use crate::replay::REPLAY_HANDLER;
+use crate::routes::SERVER_HANDLERS;
+
+/// Methods that can be described as OpenAPI operations, in document order
+const OPERATIONS: [Method; 8] = [
+    Method::GET,
+    Method::PUT,
+    Method::POST,
+    Method::DELETE,
+    Method::OPTIONS,
+    Method::HEAD,
+    Method::PATCH,
+    Method::TRACE,
+];
+
+/// Methods an ANY endpoint is documented with
+const ANY_OPERATIONS: [Method; 5] = [Method::GET, Method::PUT, Method::POST, Method::DELETE, Method::PATCH];
+
+/// Page rendering the document, `__SPEC__` is replaced with the document
+const DOCS_PAGE: &str = include_str!("docs.html");
+
+/// OpenAPI 3.1 document describing the endpoints served on a site
+///
+/// Endpoints scoped to different listeners or virtual hosts may share a path and method, which
+/// one document can't describe, so each site gets a document of its own.
+pub fn document(config: &Config, registry: &HandlerRegistry, site: &Site) -> Value {
+    let mut paths = Map::new();
+    let mut security_schemes = Map::new();
+
+    let endpoints: Vec<&EndpointConfig> = config.endpoints.iter().collect();
+    let served: Vec<&EndpointConfig> = site.endpoints(&endpoints).into_iter().map(|index| endpoints[index]).collect();
+    for endpoint in &served {
+        let path = paths.entry(openapi_path(&endpoint.path)).or_insert_with(|| json!({}));
+        let Value::Object(path) = path else {
+            continue;
+        };
+
+        let methods = match &endpoint.method {
+            EndpointMethod::Methods(methods) => methods.clone(),
+            // ANY covers the methods no other endpoint on the path declares
+            EndpointMethod::Any => ANY_OPERATIONS.iter()
+                .filter(|method| !served.iter().any(|other| match &other.method {
+                    EndpointMethod::Methods(methods) => other.path == endpoint.path && methods.contains(method),
+                    EndpointMethod::Any => false,
+                }))
+                .cloned()
+                .collect(),
+        };
+
+        // An endpoint's auth section replaces the server's, an empty one makes it public
+        let security = endpoint.auth.as_ref().or(config.server.auth.as_ref())
+            .map(|auth| security(auth, &mut security_schemes));
+
+        // Non-standard methods can't be described in OpenAPI 3.1
+        for method in methods.iter().filter(|method| OPERATIONS.contains(method)) {
+            let mut operation = operation(endpoint, method, registry);
+            if let (Some(security), Value::Object(operation)) = (&security, &mut operation) {
+                operation.insert("security".to_string(), security.clone());
+            }
+            path.insert(method.as_str().to_lowercase(), operation);
+        }
+    }
+
+    // Keep the operations of each path in the usual order
+    for path in paths.values_mut() {
+        if let Value::Object(operations) = path {
+            let mut sorted: Vec<(String, Value)> = std::mem::take(operations).into_iter().collect();
+            sorted.sort_by_key(|(method, _)| OPERATIONS.iter().position(|m| m.as_str().eq_ignore_ascii_case(method)));
+            operations.extend(sorted);
+        }
+    }
+
+    let mut document = json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": "Axum Server",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "servers": [{ "url": server_url(config, site) }],
+        "paths": paths,
+    });
+    if !security_schemes.is_empty() {
+        document["components"] = json!({ "securitySchemes": security_schemes });
+    }
+    document
+}
+
+// Security requirements of an auth section, any one of them is enough. The schemes
+// they refer to are added to `schemes`.
+fn security(auth: &AuthConfig, schemes: &mut Map<String, Value>) -> Value {
+    let mut requirements = Vec::new();
+    let mut require = |name: String, scheme: Value| {
+        schemes.entry(name.clone()).or_insert(scheme);
+        requirements.push(json!({ name: [] }));
+    };
+
+    if let Some(api_key) = &auth.api_key {
+        let header = match (&api_key.header, &api_key.query) {
+            (None, None) => Some(DEFAULT_API_KEY_HEADER),
+            (header, _) => header.as_deref(),
+        };
+        if let Some(header) = header {
+            require(format!("apiKeyHeader_{}", header), json!({ "type": "apiKey", "in": "header", "name": header }));
+        }
+        if let Some(query) = &api_key.query {
+            require(format!("apiKeyQuery_{}", query), json!({ "type": "apiKey", "in": "query", "name": query }));
+        }
+    }
+    if auth.basic.is_some() {
+        require("basicAuth".to_string(), json!({ "type": "http", "scheme": "basic" }));
+    }
+    if auth.jwt.is_some() {
+        require("bearerAuth".to_string(), json!({ "type": "http", "scheme": "bearer", "bearerFormat": "JWT" }));
+    }
+    Value::Array(requirements)
+}
+
+/// HTML page showing the document
+pub fn docs_page(document: &Value) -> String {
+    // `</script>` in a string would end the script element the document is embedded in
+    let spec = document.to_string().replace("</", "<\\/");
+    DOCS_PAGE.replace("__SPEC__", &spec)
+}
+
+fn operation(endpoint: &EndpointConfig, method: &Method, registry: &HandlerRegistry) -> Value {
+    let mut operation = Map::new();
+    if !endpoint.description.is_empty() {
+        operation.insert("summary".to_string(), json!(endpoint.description));
+    }
+    operation.insert("operationId".to_string(), json!(operation_id(method, &endpoint.path)));
+
+    let parameters: Vec<Value> = path_parameters(&endpoint.path).into_iter()
+        .map(|(name, wildcard)| {
+            let mut parameter = json!({
+                "name": name,
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            });
+            if wildcard {
+                parameter["description"] = json!("The rest of the path, may contain `/`");
+            }
+            parameter
+        })
+        .collect();
+    if !parameters.is_empty() {
+        operation.insert("parameters".to_string(), Value::Array(parameters));
+    }
+
+    if let Some(schema) = &endpoint.request_schema {
+        operation.insert("requestBody".to_string(), json!({
+            "content": { "application/json": { "schema": schema } },
+        }));
+    }
+
+    operation.insert("responses".to_string(), responses(endpoint, registry));
+    Value::Object(operation)
+}
+
+fn responses(endpoint: &EndpointConfig, registry: &HandlerRegistry) -> Value {
+    if endpoint.handler == PROXY_HANDLER {
+        let upstream = endpoint.params.get("upstream").map(String::as_str).unwrap_or_default();
+        return json!({ "default": { "description": format!("Response of {}", upstream) } });
+    }
+
+    if endpoint.handler == REPLAY_HANDLER {
+        let mut responses = Map::new();
+        for recording in &endpoint.recordings {
+            let content_type = recording.headers.get("content-type")
+                .and_then(|values| values.iter().next())
+                .unwrap_or("application/octet-stream");
+            let response = responses.entry(recording.status.to_string())
+                .or_insert_with(|| json!({ "description": "Recorded response", "content": {} }));
+            response["content"][content_type] = json!({});
+        }
+        return Value::Object(responses);
+    }
+
+    let status = endpoint.params.get("status")
+        .filter(|status| status.parse::<u16>().is_ok())
+        .cloned()
+        .unwrap_or_else(|| "200".to_string());
+    let mut media = Map::new();
+    if let Some(schema) = &endpoint.response_schema {
+        media.insert("schema".to_string(), schema.clone());
+    }
+
+    // The response is only an example when it is served as it is
+    let static_response = endpoint.handler == "default"
+        || (!registry.contains(&endpoint.handler) && !SERVER_HANDLERS.contains(&endpoint.handler.as_str()));
+    let content_type = match serde_json::from_str::<Value>(&endpoint.response) {
+        Ok(example) if static_response => {
+            media.insert("example".to_string(), example);
+            "application/json"
+        },
+        Err(_) if static_response && !endpoint.response.is_empty() => {
+            media.insert("example".to_string(), json!(endpoint.response));
+            "text/plain"
+        },
+        _ => "application/json",
+    };
+
+    let mut response = json!({ "description": "Successful response" });
+    if !media.is_empty() {
+        response["content"] = json!({ content_type: media });
+    }
+    json!({ status: response })
+}
+
+// The address of the site's TCP listener, with its virtual host in place of the IP address.
+// Clients can't connect to an unspecified address, so those are shown as localhost.
+fn server_url(config: &Config, site: &Site) -> String {
+    let listener = site.listener.as_ref()
+        .and_then(|name| config.server.listeners.iter().find(|listener| &listener.name == name))
+        .filter(|listener| listener.unix.is_none());
+    let address = listener.and_then(|listener| listener.host.as_ref()).unwrap_or(&config.server.host);
+    let port = listener.and_then(|listener| listener.port).unwrap_or(config.server.port);
+
+    let host = match (&site.host, address.parse::<IpAddr>()) {
+        (Some(host), _) => host.clone(),
+        (None, Ok(ip)) if ip.is_unspecified() => "localhost".to_string(),
+        (None, Ok(IpAddr::V6(ip))) => format!("[{}]", ip),
+        (None, _) => address.clone(),
+    };
+    let scheme = if config.server.tls.is_some() { "https" } else { "http" };
+    format!("{}://{}:{}", scheme, host, port)
+}
+
+// axum and OpenAPI share the `{name}` syntax, wildcards `{*name}` become `{name}`
+fn openapi_path(path: &str) -> String {
+    path.replace("{*", "{")
+}
+
+// Names of the path parameters and whether they are wildcards
+fn path_parameters(path: &str) -> Vec<(String, bool)> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .filter(|name| !name.starts_with('{'))
+        .map(|name| match name.strip_prefix('*') {
+            Some(name) => (name.to_string(), true),
+            None => (name.to_string(), false),
+        })
+        .collect()
+}
+
+// E.g. `get_api_user_id` for GET /api/user/{id}
+fn operation_id(method: &Method, path: &str) -> String {
+    let mut id = method.as_str().to_lowercase();
+    for word in path.split(|c: char| !c.is_ascii_alphanumeric()).filter(|word| !word.is_empty()) {
+        id.push('_');
+        id.push_str(word);
+    }
+    if path == "/" {
+        id.push_str("_root");
+    }
+    id
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri, Version};
+use axum::response::{IntoResponse, Response};
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+
+use crate::routes::ListenerScheme;
+
+/// Name of the handler that forwards requests to an upstream server
+pub const PROXY_HANDLER: &str = "proxy";
+
+/// Time to wait for the upstream response headers when no `timeout` param is set
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Headers that only apply to a single connection and are never forwarded
+const HOP_BY_HOP: [HeaderName; 8] = [
+    header::CONNECTION,
+    HeaderName::from_static("keep-alive"),
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+const X_FORWARDED_HOST: HeaderName = HeaderName::from_static("x-forwarded-host");
+const X_FORWARDED_PROTO: HeaderName = HeaderName::from_static("x-forwarded-proto");
+
+/// Forwards requests to an upstream server, streaming bodies in both directions
+///
+/// Configured by endpoint params: `upstream` (required), `strip_prefix`, `timeout`,
+/// `request_header.<name>`, `response_header.<name>`, `remove_request_headers` and
+/// `remove_response_headers`.
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    upstream: Uri,
+    strip_prefix: Option<String>,
+    timeout: Duration,
+    request_headers: HeaderMap,
+    remove_request_headers: Vec<HeaderName>,
+    response_headers: HeaderMap,
+    remove_response_headers: Vec<HeaderName>,
+    client: Client<HttpConnector, Body>,
+}
+
+/// Marks responses the proxy made up because the upstream didn't answer
+#[derive(Debug, Clone, Copy)]
+pub struct UpstreamFailed;
+
+/// Where a request was forwarded to, for logging
+#[derive(Debug)]
+pub struct Forwarded {
+    pub url: String,
+    /// Time until the upstream response headers arrived, None if there was no response
+    pub latency: Option<Duration>,
+    /// Why there was no response
+    pub error: Option<String>,
+}
+
+impl Proxy {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        let upstream = params.get("upstream")
+            .ok_or("the `upstream` param is required by the proxy handler")?;
+        let upstream: Uri = upstream.parse()
+            .map_err(|e| format!("`upstream`: invalid URL `{}`: {}", upstream, e))?;
+        if upstream.scheme_str() != Some("http") || upstream.authority().is_none() {
+            return Err(format!("`upstream`: `{}` is not an http:// URL", upstream));
+        }
+
+        let timeout = match params.get("timeout") {
+            Some(timeout) => parse_duration(timeout)
+                .ok_or_else(|| format!("`timeout`: invalid duration `{}`, use e.g. 500ms or 10s", timeout))?,
+            None => DEFAULT_TIMEOUT,
+        };
+
+        Ok(Self {
+            upstream,
+            strip_prefix: params.get("strip_prefix").cloned(),
+            timeout,
+            request_headers: header_params(params, "request_header.")?,
+            remove_request_headers: header_list(params, "remove_request_headers")?,
+            response_headers: header_params(params, "response_header.")?,
+            remove_response_headers: header_list(params, "remove_response_headers")?,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+        })
+    }
+
+    /// Forward a request, answering with 502 or 504 if the upstream fails
+    pub async fn forward(&self, request: Request) -> (Response, Forwarded) {
+        let (mut parts, body) = request.into_parts();
+        let uri = self.upstream_uri(&parts.uri);
+        let forwarded = |latency, error| Forwarded { url: uri.to_string(), latency, error };
+
+        // The client sets Host from the upstream URL
+        if let Some(host) = parts.headers.remove(header::HOST) {
+            parts.headers.insert(X_FORWARDED_HOST, host);
+        }
+        let scheme = parts.extensions.get::<ListenerScheme>().map_or("http", |ListenerScheme(scheme)| scheme);
+        parts.headers.insert(X_FORWARDED_PROTO, HeaderValue::from_static(scheme));
+        remove_hop_by_hop(&mut parts.headers);
+        apply_headers(&mut parts.headers, &self.remove_request_headers, &self.request_headers);
+
+        parts.uri = uri.clone();
+        parts.version = Version::HTTP_11;
+
+        let start = Instant::now();
+        let response = match tokio::time::timeout(self.timeout, self.client.request(Request::from_parts(parts, body))).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                let message = format!("Upstream request to {} failed: {}", uri, error_chain(&e));
+                return (failure(StatusCode::BAD_GATEWAY, message.clone()), forwarded(None, Some(message)));
+            },
+            Err(_) => {
+                let message = format!("Upstream {} did not respond within {:?}", uri, self.timeout);
+                return (failure(StatusCode::GATEWAY_TIMEOUT, message.clone()), forwarded(None, Some(message)));
+            },
+        };
+        let latency = start.elapsed();
+
+        let (mut parts, body) = response.into_parts();
+        remove_hop_by_hop(&mut parts.headers);
+        apply_headers(&mut parts.headers, &self.remove_response_headers, &self.response_headers);
+
+        (Response::from_parts(parts, Body::new(body)), forwarded(Some(latency), None))
+    }
+
+    // Rewrite the request path onto the upstream URL
+    fn upstream_uri(&self, uri: &Uri) -> Uri {
+        let mut path = uri.path();
+        if let Some(prefix) = &self.strip_prefix {
+            // Only whole path segments are stripped, `/api` is not a prefix of `/apis`
+            if let Some(rest) = path.strip_prefix(prefix.trim_end_matches('/')) {
+                if rest.is_empty() || rest.starts_with('/') {
+                    path = rest;
+                }
+            }
+        }
+
+        let mut target = format!("{}/{}",
+            self.upstream.path().trim_end_matches('/'),
+            path.trim_start_matches('/'));
+        if let Some(query) = uri.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+
+        let mut parts = self.upstream.clone().into_parts();
+        // Both halves come from valid URIs, so the result is valid too
+        parts.path_and_query = target.parse().ok();
+        Uri::from_parts(parts).unwrap_or_else(|_| self.upstream.clone())
+    }
+}
+
+fn failure(status: StatusCode, message: String) -> Response {
+    let mut response = (status, message).into_response();
+    response.extensions_mut().insert(UpstreamFailed);
+    response
+}
+
+// Headers set by params named `<prefix><header name>`
+fn header_params(params: &HashMap<String, String>, prefix: &str) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    for (key, value) in params {
+        if let Some(name) = key.strip_prefix(prefix) {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("`{}`: invalid header name `{}`", key, name))?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| format!("`{}`: invalid header value", key))?;
+            headers.insert(name, value);
+        }
+    }
+    Ok(headers)
+}
+
+// Header names from a comma separated param
+fn header_list(params: &HashMap<String, String>, key: &str) -> Result<Vec<HeaderName>, String> {
+    let Some(list) = params.get(key) else {
+        return Ok(Vec::new());
+    };
+    list.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("`{}`: invalid header name `{}`", key, name)))
+        .collect()
+}
+
+fn apply_headers(headers: &mut HeaderMap, remove: &[HeaderName], add: &HeaderMap) {
+    for name in remove {
+        headers.remove(name);
+    }
+    for (name, value) in add {
+        headers.insert(name, value.clone());
+    }
+}
+
+fn remove_hop_by_hop(headers: &mut HeaderMap) {
+    // Connection lists further headers that are specific to the connection
+    let listed: Vec<HeaderName> = headers.get_all(header::CONNECTION).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+
+    for name in HOP_BY_HOP.iter().chain(&listed) {
+        headers.remove(name);
+    }
+}
+
+/// Parse durations like `500ms`, `10s` or `2m`, plain numbers are seconds
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|index| value.split_at(index))
+        .unwrap_or((value, "s"));
+    let number: f64 = number.parse().ok()?;
+
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+// An error with its sources, e.g. `client error (Connect): tcp connect error: Connection refused`
+pub(crate) fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        // Some errors already include their source in the message
+        let text = error.to_string();
+        if !message.ends_with(&text) {
+            message.push_str(": ");
+            message.push_str(&text);
+        }
+        source = error.source();
+    }
+    message
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_handlers::Principal;
+use http_body::{Frame, SizeHint};
+use axum_tui::LimiterStats;
+
+use crate::config::{Config, RateLimitConfig};
+use crate::proxy::parse_duration;
+
+/// Scope of the server's limits, endpoints are scoped by their key
+pub const SERVER_SCOPE: &str = "server";
+
+/// Period of `requests` when none is configured
+const DEFAULT_PERIOD: Duration = Duration::from_secs(1);
+
+/// Idle clients are forgotten once this many clients are tracked, new clients are limited
+/// while none of them is idle
+const MAX_CLIENTS: usize = 10_000;
+
+/// Limiters by scope, kept across reloads unless their configuration changes
+#[derive(Default)]
+pub struct RateLimits {
+    limiters: Mutex<HashMap<String, Arc<Limiter>>>,
+}
+
+/// Token buckets and in-flight counts of the clients of one scope
+pub struct Limiter {
+    config: RateLimitConfig,
+    /// Tokens added per second and the bucket size, None without a rate limit
+    rate: Option<(f64, f64)>,
+    max_in_flight: Option<u32>,
+    key: ClientKey,
+    clients: Mutex<HashMap<String, Client>>,
+    rejected: AtomicU64,
+}
+
+/// What identifies the client of a request
+enum ClientKey {
+    Ip,
+    /// The authenticated client, or the IP address on public endpoints as unverified keys could
+    /// be changed on every request
+    ApiKey,
+    Header(HeaderName),
+    /// Every request counts against the same client
+    Global,
+}
+
+struct Client {
+    tokens: f64,
+    updated: Instant,
+    in_flight: u32,
+}
+
+/// A request let through by a limiter, counted as in flight until it is dropped
+pub struct Permit {
+    limiter: Arc<Limiter>,
+    client: String,
+}
+
+/// Why a request was limited, answered with 429
+#[derive(Debug, Clone)]
+pub struct Limited {
+    pub reason: String,
+    pub retry_after: Duration,
+}
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The limiter of a scope, a new one if the scope's configuration changed
+    pub fn limiter(&self, scope: &str, config: &RateLimitConfig) -> Result<Arc<Limiter>, String> {
+        let mut limiters = self.limiters.lock().unwrap();
+        if let Some(limiter) = limiters.get(scope).filter(|limiter| limiter.config == *config) {
+            return Ok(limiter.clone());
+        }
+        let limiter = Arc::new(Limiter::new(config)?);
+        limiters.insert(scope.to_string(), limiter.clone());
+        Ok(limiter)
+    }
+
+    /// A copy for building a new configuration's limiters, so that a failed build leaves these as they are
+    pub fn stage(&self) -> Self {
+        Self { limiters: Mutex::new(self.limiters.lock().unwrap().clone()) }
+    }
+
+    /// Replace the limiters with those of a staged copy
+    pub fn install(&self, staged: &RateLimits) {
+        *self.limiters.lock().unwrap() = staged.limiters.lock().unwrap().clone();
+    }
+
+    /// Forget the limiters of scopes a configuration doesn't limit, e.g. of removed endpoints
+    pub fn retain(&self, config: &Config) {
+        let mut scopes: Vec<String> = config.endpoints.iter()
+            .filter(|endpoint| endpoint.rate_limit.is_some())
+            .map(|endpoint| endpoint.key())
+            .collect();
+        if config.server.rate_limit.is_some() {
+            scopes.push(SERVER_SCOPE.to_string());
+        }
+        self.limiters.lock().unwrap().retain(|scope, _| scopes.contains(scope));
+    }
+
+    /// State of a scope's limiter, None if it has none
+    pub fn stats(&self, scope: &str) -> Option<LimiterStats> {
+        self.limiters.lock().unwrap().get(scope).map(|limiter| limiter.stats())
+    }
+}
+
+/// Let a request through every limiter, or tell why it is limited
+///
+/// Every limiter is checked before any of them counts the request, so a request one of them
+/// rejects doesn't use up the others, e.g. the server's.
+pub fn acquire(
+    limiters: &[Arc<Limiter>],
+    headers: &HeaderMap,
+    ip: Option<IpAddr>,
+    principal: Option<&Principal>,
+) -> Result<Vec<Permit>, Limited> {
+    let now = Instant::now();
+    let clients: Vec<String> = limiters.iter().map(|limiter| limiter.client(headers, ip, principal)).collect();
+    // Limiters are always locked in the same order, the server's first
+    let mut states: Vec<_> = limiters.iter().map(|limiter| limiter.clients.lock().unwrap()).collect();
+    for ((limiter, states), client) in limiters.iter().zip(&mut states).zip(&clients) {
+        limiter.check(states, client, now)?;
+    }
+    Ok(limiters.iter().zip(&mut states).zip(clients)
+        .map(|((limiter, states), client)| limiter.take(states, client))
+        .collect())
+}
+
+impl Limiter {
+    pub fn new(config: &RateLimitConfig) -> Result<Self, String> {
+        if config.requests.is_none() && config.max_in_flight.is_none() {
+            return Err("set `requests`, `max_in_flight` or both".to_string());
+        }
+
+        let per = match &config.per {
+            Some(per) => parse_duration(per).filter(|per| !per.is_zero())
+                .ok_or_else(|| format!("`per`: invalid duration `{}`, use e.g. 1s or 1m", per))?,
+            None => DEFAULT_PERIOD,
+        };
+        let rate = match config.requests {
+            Some(0) => return Err("`requests` must be at least 1".to_string()),
+            Some(requests) => {
+                let burst = config.burst.unwrap_or(requests);
+                if burst == 0 {
+                    return Err("`burst` must be at least 1".to_string());
+                }
+                Some((requests as f64 / per.as_secs_f64(), burst as f64))
+            },
+            None if config.burst.is_some() || config.per.is_some() => {
+                return Err("`burst` and `per` only apply with `requests`".to_string());
+            },
+            None => None,
+        };
+        if config.max_in_flight == Some(0) {
+            return Err("`max_in_flight` must be at least 1".to_string());
+        }
+
+        let key = match config.key.as_deref().map(str::trim) {
+            None | Some("ip") => ClientKey::Ip,
+            Some("api_key") => ClientKey::ApiKey,
+            Some("global") => ClientKey::Global,
+            Some(key) => match key.strip_prefix("header:") {
+                Some(name) => ClientKey::Header(HeaderName::from_bytes(name.trim().as_bytes())
+                    .map_err(|_| format!("`key`: invalid header name `{}`", name))?),
+                None => return Err(format!("`key`: unknown key `{}`, use ip, api_key, header:<name> or global", key)),
+            },
+        };
+
+        Ok(Self {
+            config: config.clone(),
+            rate,
+            max_in_flight: config.max_in_flight,
+            key,
+            clients: Mutex::default(),
+            rejected: AtomicU64::new(0),
+        })
+    }
+
+    /// The client a request counts against, requests without the key share one client
+    pub fn client(&self, headers: &HeaderMap, ip: Option<IpAddr>, principal: Option<&Principal>) -> String {
+        let header = |name: &str| headers.get(name).map(|value| String::from_utf8_lossy(value.as_bytes()).into_owned());
+        match &self.key {
+            ClientKey::Ip => ip.map(|ip| ip.to_string()),
+            ClientKey::ApiKey => principal.map(|principal| principal.name.clone()).or_else(|| ip.map(|ip| ip.to_string())),
+            ClientKey::Header(name) => header(name.as_str()),
+            ClientKey::Global => None,
+        }
+        .unwrap_or_default()
+    }
+
+    pub fn acquire(self: &Arc<Self>, client: String) -> Result<Permit, Limited> {
+        let mut clients = self.clients.lock().unwrap();
+        self.check(&mut clients, &client, Instant::now())?;
+        Ok(self.take(&mut clients, client))
+    }
+
+    // Whether a client may send a request now, refilling its bucket without taking a token
+    fn check(&self, clients: &mut HashMap<String, Client>, client: &str, now: Instant) -> Result<(), Limited> {
+        if clients.len() >= MAX_CLIENTS && !clients.contains_key(client) {
+            clients.retain(|_, client| !self.is_idle(client, now));
+            if clients.len() >= MAX_CLIENTS {
+                return Err(self.reject(format!("{} clients already limited", MAX_CLIENTS), Duration::from_secs(1)));
+            }
+        }
+        let state = clients.entry(client.to_string()).or_insert_with(|| Client {
+            tokens: self.rate.map_or(0.0, |(_, burst)| burst),
+            updated: now,
+            in_flight: 0,
+        });
+
+        if let Some(max) = self.max_in_flight {
+            if state.in_flight >= max {
+                return Err(self.reject(format!("{} request(s) already in flight", max), Duration::from_secs(1)));
+            }
+        }
+        if let Some((rate, _)) = self.rate {
+            state.tokens = self.tokens(state, now);
+            state.updated = now;
+            if state.tokens < 1.0 {
+                let retry_after = Duration::from_secs_f64((1.0 - state.tokens) / rate);
+                return Err(self.reject(format!("rate limit of {} exceeded", self.describe_rate()), retry_after));
+            }
+        }
+        Ok(())
+    }
+
+    // Count a request of a client that passed the check
+    fn take(self: &Arc<Self>, clients: &mut HashMap<String, Client>, client: String) -> Permit {
+        if let Some(state) = clients.get_mut(&client) {
+            if self.rate.is_some() {
+                state.tokens -= 1.0;
+            }
+            state.in_flight += 1;
+        }
+        Permit { limiter: self.clone(), client }
+    }
+
+    pub fn stats(&self) -> LimiterStats {
+        let now = Instant::now();
+        let clients = self.clients.lock().unwrap();
+        LimiterStats {
+            limits: self.describe(),
+            active_clients: clients.values().filter(|client| !self.is_idle(client, now)).count(),
+            min_tokens: self.rate.map(|(_, burst)| clients.values().map(|client| self.tokens(client, now)).fold(burst, f64::min)),
+            in_flight: clients.values().map(|client| client.in_flight as u64).sum(),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    fn reject(&self, reason: String, retry_after: Duration) -> Limited {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+        Limited { reason, retry_after }
+    }
+
+    // Tokens in a client's bucket, refilled up to now
+    fn tokens(&self, client: &Client, now: Instant) -> f64 {
+        match self.rate {
+            Some((rate, burst)) => (client.tokens + now.duration_since(client.updated).as_secs_f64() * rate).min(burst),
+            None => 0.0,
+        }
+    }
+
+    // Idle clients are in the same state as new ones, so they can be forgotten
+    fn is_idle(&self, client: &Client, now: Instant) -> bool {
+        client.in_flight == 0 && self.rate.is_none_or(|(_, burst)| self.tokens(client, now) >= burst)
+    }
+
+    fn describe_rate(&self) -> String {
+        let requests = self.config.requests.unwrap_or_default();
+        format!("{} per {}", requests, self.config.per.as_deref().unwrap_or("1s"))
+    }
+
+    // The configured limits, e.g. `10 per 1s, burst 20, 5 in flight, per ip`
+    fn describe(&self) -> String {
+        let mut limits = Vec::new();
+        if self.rate.is_some() {
+            limits.push(self.describe_rate());
+            if let Some(burst) = self.config.burst {
+                limits.push(format!("burst {}", burst));
+            }
+        }
+        if let Some(max) = self.max_in_flight {
+            limits.push(format!("{} in flight", max));
+        }
+        limits.push(match &self.key {
+            ClientKey::Ip => "per ip".to_string(),
+            ClientKey::ApiKey => "per api key".to_string(),
+            ClientKey::Header(name) => format!("per {}", name),
+            ClientKey::Global => "global".to_string(),
+        });
+        limits.join(", ")
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut clients = self.limiter.clients.lock().unwrap();
+        if let Some(client) = clients.get_mut(&self.client) {
+            client.in_flight = client.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+/// Keep a request's permits until its response body has been sent, or dropped with the connection,
+/// so streamed and proxied responses count as in flight
+pub fn hold(response: Response, permits: Vec<Permit>) -> Response {
+    if permits.is_empty() {
+        return response;
+    }
+    response.map(|body| Body::new(PermitBody { body, _permits: permits }))
+}
+
+/// A response body holding its request's permits
+struct PermitBody {
+    body: Body,
+    _permits: Vec<Permit>,
+}
+
+impl HttpBody for PermitBody {
+    type Data = Bytes;
+    type Error = axum::Error;
+
+    fn poll_frame(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, axum::Error>>> {
+        Pin::new(&mut self.body).poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+impl Limited {
+    pub fn message(&self) -> String {
+        format!("Too Many Requests: {}", self.reason)
+    }
+}
+
+impl IntoResponse for Limited {
+    fn into_response(self) -> Response {
+        // Retry-After is in whole seconds, rounded up so the retry is not limited again
+        let seconds = self.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, self.message()).into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(yaml: &str) -> RateLimitConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn limits_new_clients_once_the_tracked_clients_are_full() {
+        let limiter = Arc::new(Limiter::new(&config("{ requests: 1, per: 1m }")).unwrap());
+        for client in 0..MAX_CLIENTS {
+            assert!(limiter.acquire(client.to_string()).is_ok());
+        }
+
+        let limited = limiter.acquire("new".to_string()).err().unwrap();
+        assert_eq!(limited.reason, "10000 clients already limited");
+        assert_eq!(limiter.clients.lock().unwrap().len(), MAX_CLIENTS);
+        assert_eq!(limiter.acquire("0".to_string()).err().unwrap().reason, "rate limit of 1 per 1m exceeded");
+    }
+
+    #[test]
+    fn forgets_idle_clients_once_the_tracked_clients_are_full() {
+        let limiter = Arc::new(Limiter::new(&config("{ max_in_flight: 1 }")).unwrap());
+        for client in 0..MAX_CLIENTS {
+            drop(limiter.acquire(client.to_string()).unwrap());
+        }
+
+        assert!(limiter.acquire("new".to_string()).is_ok());
+        assert_eq!(limiter.clients.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn holds_the_permits_until_the_response_body_has_been_sent() {
+        let limiter = Arc::new(Limiter::new(&config("{ max_in_flight: 1 }")).unwrap());
+        let permit = limiter.acquire("client".to_string()).unwrap();
+        let response = hold("streamed".into_response(), vec![permit]);
+        assert!(limiter.acquire("client".to_string()).is_err());
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "streamed");
+        assert!(limiter.acquire("client".to_string()).is_ok());
+    }
+
+    #[test]
+    fn keys_unauthenticated_api_key_clients_by_ip() {
+        let limiter = Limiter::new(&config("{ requests: 1, key: api_key }")).unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-api-key", HeaderValue::from_static("made-up"));
+        let ip = "192.0.2.1".parse().ok();
+
+        assert_eq!(limiter.client(&headers, ip, None), "192.0.2.1");
+        let principal = Principal { name: "ci".to_string(), scheme: "api_key".to_string(), claims: Default::default() };
+        assert_eq!(limiter.client(&headers, ip, Some(&principal)), "ci");
+    }
+
+    #[test]
+    fn keeps_the_limiters_until_a_staged_copy_is_installed() {
+        let limits = RateLimits::new();
+        let first = limits.limiter("GET /a", &config("{ requests: 1 }")).unwrap();
+        let staged = limits.stage();
+        staged.limiter("GET /a", &config("{ requests: 2 }")).unwrap();
+
+        assert!(Arc::ptr_eq(&limits.limiter("GET /a", &config("{ requests: 1 }")).unwrap(), &first));
+        limits.install(&staged);
+        assert_eq!(limits.stats("GET /a").unwrap().limits, "2 per 1s, per ip");
+    }
+
+    #[test]
+    fn forgets_the_limiters_of_removed_scopes() {
+        let limits = RateLimits::new();
+        let limited = config("{ requests: 1 }");
+        limits.limiter("GET /a", &limited).unwrap();
+        limits.limiter("GET /b", &limited).unwrap();
+
+        let remaining = Config::from_yaml(r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /a
+    method: GET
+    handler: default
+    response: "ok"
+    description: "Limited"
+    rate_limit:
+      requests: 1
+"#).unwrap();
+        limits.retain(&remaining);
+
+        assert!(limits.stats("GET /a").is_some());
+        assert!(limits.stats("GET /b").is_none());
+    }
+}
@@ -0,0 +1,312 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, Method, StatusCode, Uri};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum_tui::LogEvent;
+
+use crate::config::{Config, EndpointConfig, EndpointMethod, HeaderValues, Recording, ServerConfig};
+use crate::proxy::{Proxy, UpstreamFailed};
+use crate::replay::REPLAY_HANDLER;
+use crate::validate::check_path;
+
+/// Largest request or response body that is recorded
+const MAX_RECORDED_BODY: usize = 64 * 1024 * 1024;
+
+/// How long the output file's writes wait for further exchanges, so a burst is written once
+const WRITE_DELAY: Duration = Duration::from_millis(200);
+
+/// Response headers that are not recorded because they describe a single response
+const SKIPPED_HEADERS: [header::HeaderName; 2] = [header::CONTENT_LENGTH, header::DATE];
+
+/// Records exchanges with an upstream and writes them out as replay endpoints
+pub struct Recorder {
+    upstream: String,
+    output: PathBuf,
+    /// Directory the fixture paths in the output are relative to, the output's
+    output_dir: PathBuf,
+    fixtures: PathBuf,
+    match_body: bool,
+    /// Server section of the written configuration
+    server: ServerConfig,
+    /// Paths served by the server itself, e.g. the metrics endpoint and the OpenAPI document
+    ignored: Vec<String>,
+    log_sender: mpsc::Sender<LogEvent>,
+    state: Mutex<RecorderState>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    endpoints: Vec<EndpointConfig>,
+    /// Number of fixture files written, used to name them
+    fixture_count: usize,
+    /// Whether recordings are waiting to be written to the output
+    write_pending: bool,
+}
+
+/// A request and its response, with both bodies read
+struct Exchange {
+    method: Method,
+    uri: Uri,
+    request_content_type: Option<header::HeaderValue>,
+    request_body: Bytes,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Recorder {
+    pub fn new(
+        upstream: &str,
+        output: &Path,
+        fixtures: &Path,
+        match_body: bool,
+        server: &ServerConfig,
+        log_sender: mpsc::Sender<LogEvent>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        // Check the URL now rather than failing on the first request
+        Proxy::from_params(&Self::proxy_params(upstream)).map_err(|e| format!("--upstream: {}", e))?;
+
+        Ok(Self {
+            upstream: upstream.to_string(),
+            output: output.to_path_buf(),
+            output_dir: std::path::absolute(output)?.parent().map(Path::to_path_buf).unwrap_or_default(),
+            fixtures: fixtures.to_path_buf(),
+            match_body,
+            server: ServerConfig { fallback_proxy: None, ..server.clone() },
+            ignored: [&server.metrics_path, &server.openapi_path, &server.docs_path]
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect(),
+            log_sender,
+            state: Mutex::default(),
+        })
+    }
+
+    fn proxy_params(upstream: &str) -> HashMap<String, String> {
+        HashMap::from([("upstream".to_string(), upstream.to_string())])
+    }
+
+    /// The configuration to serve while recording: every request goes to the upstream
+    pub fn recording_config(&self, config: &Config) -> Config {
+        Config {
+            server: ServerConfig {
+                watch_config: false,
+                reload_path: None,
+                fallback_proxy: Some(Self::proxy_params(&self.upstream)),
+                ..config.server.clone()
+            },
+            endpoints: Vec::new(),
+        }
+    }
+
+    fn log(&self, event: LogEvent) {
+        self.log_sender.send(event.with_source("record")).ok();
+    }
+
+    // Add an exchange to the recordings, the output file is written once no more arrive for a moment
+    fn add(&self, exchange: Exchange) {
+        // Braces are route syntax, so they are escaped in recorded paths
+        let path = exchange.uri.path().replace('{', "{{").replace('}', "}}");
+        if let Err(message) = check_path(&path) {
+            self.log(LogEvent::warn(format!("Not recording {} {}: {}", exchange.method, path, message)));
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Err(e) = self.add_recording(&mut state, &path, &exchange) {
+            self.log(LogEvent::error(format!("Failed to record {} {}: {}", exchange.method, exchange.uri, e)));
+            return;
+        }
+        let write = !std::mem::replace(&mut state.write_pending, true);
+        drop(state);
+        self.log(LogEvent::info(format!("Recorded {} {} -> {}", exchange.method, exchange.uri, exchange.status.as_u16())));
+
+        // Exchanges recorded in the meantime are written along with this one
+        if write {
+            std::thread::sleep(WRITE_DELAY);
+            let mut state = self.state.lock().unwrap();
+            state.write_pending = false;
+            if let Err(e) = self.write_config(&state.endpoints) {
+                self.log(LogEvent::error(format!("Failed to write {}: {}", self.output.display(), e)));
+            }
+        }
+    }
+
+    fn add_recording(&self, state: &mut RecorderState, path: &str, exchange: &Exchange) -> std::io::Result<()> {
+        let stem = fixture_stem(state.fixture_count + 1, &exchange.method, path);
+        let request_body_file = if self.match_body && !exchange.request_body.is_empty() {
+            let name = format!("{}.request.{}", stem, extension(exchange.request_content_type.as_ref()));
+            Some(self.write_fixture(&name, &exchange.request_body)?)
+        } else {
+            None
+        };
+        let body_file = if exchange.body.is_empty() {
+            None
+        } else {
+            let name = format!("{}.{}", stem, extension(exchange.headers.get(header::CONTENT_TYPE)));
+            Some(self.write_fixture(&name, &exchange.body)?)
+        };
+        state.fixture_count += 1;
+
+        let recording = Recording {
+            query: exchange.uri.query().map(str::to_string),
+            request_body_file,
+            status: exchange.status.as_u16(),
+            headers: recorded_headers(&exchange.headers),
+            body_file,
+        };
+
+        let method = EndpointMethod::Methods(vec![exchange.method.clone()]);
+        let index = match state.endpoints.iter().position(|e| e.path == path && e.method == method) {
+            Some(index) => index,
+            None => {
+                state.endpoints.push(EndpointConfig {
+                    handler: REPLAY_HANDLER.to_string(),
+                    description: format!("Recorded from {}", self.upstream),
+                    ..EndpointConfig::new(method, path)
+                });
+                state.endpoints.len() - 1
+            }
+        };
+
+        // A repeated request replaces the earlier recording
+        let recordings = &mut state.endpoints[index].recordings;
+        match recordings.iter().position(|r| r.query == recording.query && self.same_request_body(r, &exchange.request_body)) {
+            Some(existing) => {
+                let replaced = std::mem::replace(&mut recordings[existing], recording);
+                for file in replaced.body_file.iter().chain(&replaced.request_body_file) {
+                    std::fs::remove_file(self.output_dir.join(file)).ok();
+                }
+            },
+            None => recordings.push(recording),
+        }
+        Ok(())
+    }
+
+    // Write a fixture file, returning its path relative to the output when it is below the output's directory
+    fn write_fixture(&self, name: &str, body: &[u8]) -> std::io::Result<String> {
+        std::fs::create_dir_all(&self.fixtures)?;
+        let path = std::path::absolute(self.fixtures.join(name))?;
+        std::fs::write(&path, body)?;
+        let path = path.strip_prefix(&self.output_dir).unwrap_or(&path);
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    fn same_request_body(&self, recording: &Recording, body: &[u8]) -> bool {
+        match &recording.request_body_file {
+            Some(file) => std::fs::read(self.output_dir.join(file)).is_ok_and(|recorded| recorded == body),
+            None => true,
+        }
+    }
+
+    // Write through a temporary file so the output is never left half written
+    fn write_config(&self, endpoints: &[EndpointConfig]) -> std::io::Result<()> {
+        let config = Config {
+            server: self.server.clone(),
+            endpoints: endpoints.to_vec(),
+        };
+        let yaml = serde_yaml::to_string(&config).map_err(std::io::Error::other)?;
+
+        if let Some(parent) = self.output.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temporary = self.output.with_extension("yaml.tmp");
+        std::fs::write(&temporary, yaml)?;
+        std::fs::rename(&temporary, &self.output)
+    }
+}
+
+/// Middleware that records every exchange passing through it
+pub async fn record(State(recorder): State<Arc<Recorder>>, request: Request, next: Next) -> Response {
+    if recorder.ignored.iter().any(|path| path == request.uri().path()) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let method = parts.method.clone();
+    let uri = parts.uri.clone();
+    let request_content_type = parts.headers.get(header::CONTENT_TYPE).cloned();
+    let request_body = match axum::body::to_bytes(body, MAX_RECORDED_BODY).await {
+        Ok(body) => body,
+        Err(e) => return (StatusCode::PAYLOAD_TOO_LARGE, format!("Failed to read request body: {}", e)).into_response(),
+    };
+
+    let response = next.run(Request::from_parts(parts, Body::from(request_body.clone()))).await;
+    // Nothing came back from the upstream, so there is nothing to record
+    if response.extensions().get::<UpstreamFailed>().is_some() {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_RECORDED_BODY).await {
+        Ok(body) => body,
+        Err(e) => {
+            recorder.log(LogEvent::error(format!("Not recording {} {}: failed to read the response: {}", method, uri, e)));
+            return (StatusCode::BAD_GATEWAY, format!("Failed to read upstream response: {}", e)).into_response();
+        }
+    };
+
+    let exchange = Exchange {
+        method,
+        uri,
+        request_content_type,
+        request_body,
+        status: parts.status,
+        headers: parts.headers.clone(),
+        body: body.clone(),
+    };
+    tokio::task::spawn_blocking(move || recorder.add(exchange));
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+// Headers worth replaying, repeated headers keep each of their values as joining them would
+// break headers such as `set-cookie`
+fn recorded_headers(headers: &HeaderMap) -> BTreeMap<String, HeaderValues> {
+    let mut recorded: BTreeMap<String, HeaderValues> = BTreeMap::new();
+    for (name, value) in headers {
+        if SKIPPED_HEADERS.contains(name) {
+            continue;
+        }
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        recorded.entry(name.to_string())
+            .and_modify(|existing| existing.push(value.to_string()))
+            .or_insert_with(|| HeaderValues::One(value.to_string()));
+    }
+    recorded
+}
+
+// File name without extension, e.g. `0003-GET-api_users`
+fn fixture_stem(number: usize, method: &Method, path: &str) -> String {
+    let slug: String = path.trim_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .take(60)
+        .collect();
+    let slug = if slug.is_empty() { "root".to_string() } else { slug };
+    format!("{:04}-{}-{}", number, method, slug)
+}
+
+fn extension(content_type: Option<&header::HeaderValue>) -> &'static str {
+    let content_type = content_type.and_then(|value| value.to_str().ok()).unwrap_or_default();
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    match essence {
+        "application/json" => "json",
+        "text/html" => "html",
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "application/xml" | "text/xml" => "xml",
+        _ if essence.ends_with("+json") => "json",
+        _ if essence.starts_with("text/") => "txt",
+        _ => "bin",
+    }
+}
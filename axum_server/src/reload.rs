@@ -0,0 +1,485 @@
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::{
+    Json, Router,
+    extract::Request,
+    http::{header, Method, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse},
+    routing::{get, post},
+};
+use axum_handlers::HandlerRegistry;
+use axum_tui::{EndpointInfo, EndpointStats, ListenerInfo, LogEvent, ServerInfo};
+use tokio::sync::watch;
+use tower::ServiceExt;
+
+use crate::config::{Config, EndpointConfig, EndpointMethod, ServerConfig, Site};
+use crate::faults::Faults;
+use crate::metrics::Metrics;
+use crate::openapi;
+use crate::rate_limit::{RateLimits, SERVER_SCOPE};
+use crate::routes::{AppState, ServerPolicies, create_router, request_site};
+use crate::validate::{validate_config, validate_file, Severity, Validation};
+
+/// How often the configuration file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How often the endpoint statistics shown in the UI are refreshed
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rebuilds the router when the configuration changes and swaps it in while serving
+pub struct Reloader {
+    /// File the configuration is reloaded from, None if it was built in code
+    config_path: Option<String>,
+    registry: Arc<HandlerRegistry>,
+    log_sender: mpsc::Sender<LogEvent>,
+    metrics: Arc<Metrics>,
+    /// Limiters outlive the routers so that reloads keep the clients' state
+    rate_limits: Arc<RateLimits>,
+    /// Fault injectors keep whether they are switched on across reloads
+    faults: Arc<Faults>,
+    /// The bound listeners, they don't change until a restart
+    listeners: Vec<ListenerInfo>,
+    config: Mutex<Config>,
+    /// Held from validating a configuration until it is served, so that overlapping reloads apply in order
+    reloading: Mutex<()>,
+    router: Arc<RwLock<Router>>,
+    /// The server-level policies of the current configuration, for the routes served outside the router
+    policies: Arc<RwLock<Arc<ServerPolicies>>>,
+    server_info: watch::Sender<Option<ServerInfo>>,
+}
+
+impl Reloader {
+    /// Build the initial router for a validated configuration served on `listeners`
+    pub fn new(
+        config_path: Option<&str>,
+        config: Config,
+        registry: Arc<HandlerRegistry>,
+        log_sender: mpsc::Sender<LogEvent>,
+        listeners: Vec<ListenerInfo>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let metrics = Arc::new(Metrics::new());
+        let rate_limits = Arc::new(RateLimits::new());
+        let faults = Arc::new(Faults::new());
+        let (router, policies) = build_router(&config, &registry, &log_sender, &metrics, &rate_limits, &faults)?;
+        let (server_info, _) = watch::channel(Some(server_info(&config, &listeners, &metrics, &rate_limits, &faults)));
+
+        Ok(Self {
+            config_path: config_path.map(str::to_string),
+            registry,
+            log_sender,
+            metrics,
+            rate_limits,
+            faults,
+            listeners,
+            config: Mutex::new(config),
+            reloading: Mutex::new(()),
+            router: Arc::new(RwLock::new(router)),
+            policies: Arc::new(RwLock::new(policies)),
+            server_info,
+        })
+    }
+
+    /// Receiver for the server info shown in the UI, updated on every reload
+    pub fn server_info(&self) -> watch::Receiver<Option<ServerInfo>> {
+        self.server_info.subscribe()
+    }
+
+    /// Router that forwards every request to the current configuration's router
+    pub fn router(self: &Arc<Self>) -> Router {
+        let current = self.router.clone();
+        let mut router = Router::new().fallback(move |request: Request| {
+            let router = current.read().unwrap().clone();
+            async move { router.oneshot(request).await.into_response() }
+        });
+
+        // The reload endpoint lives outside the swapped router so it survives reloads
+        let config = self.config.lock().unwrap();
+
+        // The routes go through the same server-level policies as the endpoints. The layer is
+        // added to each method router so that it also sees the OPTIONS requests they don't serve.
+        let policies = self.policies.clone();
+        let guard = middleware::from_fn(move |request: Request, next: Next| {
+            let policies = policies.read().unwrap().clone();
+            async move { policies.apply(request, next).await }
+        });
+        if let Some(path) = &config.server.metrics_path {
+            let metrics = self.metrics.clone();
+            router = router.route(path, get(move || async move {
+                ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], metrics.render())
+            }).layer(guard.clone()));
+        }
+        // The document is generated per request so that it follows reloads
+        if let Some(path) = &config.server.openapi_path {
+            let reloader = self.clone();
+            router = router.route(path, get(move |request: Request| async move {
+                Json(reloader.request_openapi(&request))
+            }).layer(guard.clone()));
+        }
+        if let Some(path) = &config.server.docs_path {
+            let reloader = self.clone();
+            router = router.route(path, get(move |request: Request| async move {
+                Html(openapi::docs_page(&reloader.request_openapi(&request)))
+            }).layer(guard.clone()));
+        }
+        if let Some(path) = &config.server.reload_path {
+            let reloader = self.clone();
+            router = router.route(path, post(move || async move {
+                if reloader.reload().await {
+                    (StatusCode::OK, "Configuration reloaded")
+                } else {
+                    (StatusCode::UNPROCESSABLE_ENTITY, "Configuration reload failed, see server logs")
+                }
+            }).layer(guard));
+        }
+
+        router
+    }
+
+    /// OpenAPI document of the endpoints a request arriving on `listener` for `host` can reach
+    pub fn openapi(&self, listener: &str, host: Option<&str>) -> serde_json::Value {
+        let config = self.config.lock().unwrap();
+        let site = Site::of_request(&sites(&config), listener, host);
+        openapi::document(&config, &self.registry, &site)
+    }
+
+    // The document of the site a request arrived at
+    fn request_openapi(&self, request: &Request) -> serde_json::Value {
+        let config = self.config.lock().unwrap();
+        let site = request_site(&sites(&config), request);
+        openapi::document(&config, &self.registry, &site)
+    }
+
+    /// Reload the configuration file on a blocking thread, keeping the current one if the new one is invalid
+    pub async fn reload(self: &Arc<Self>) -> bool {
+        let reloader = self.clone();
+        tokio::task::spawn_blocking(move || reloader.reload_blocking()).await.unwrap_or(false)
+    }
+
+    /// Replace the configuration on a blocking thread, keeping the current one if the new one is invalid
+    pub async fn apply(self: &Arc<Self>, config: Config) -> bool {
+        let reloader = self.clone();
+        tokio::task::spawn_blocking(move || reloader.apply_blocking(config)).await.unwrap_or(false)
+    }
+
+    /// Reload the configuration file, blocking while it is read and validated and keys are loaded
+    pub fn reload_blocking(&self) -> bool {
+        let Some(config_path) = &self.config_path else {
+            self.log(LogEvent::warn("Reloading needs a configuration file, the server was started without one"));
+            return false;
+        };
+        let _reloading = self.reloading.lock().unwrap();
+        self.log(LogEvent::info(format!("Reloading configuration from {}", config_path)));
+
+        let validation = match validate_file(config_path, &self.registry) {
+            Ok(validation) => validation,
+            Err(e) => {
+                self.log(LogEvent::error(format!("Reload failed, keeping previous configuration: {}", e)));
+                return false;
+            }
+        };
+        self.apply_validated(validation)
+    }
+
+    /// Replace the configuration, blocking while it is validated and keys are loaded
+    pub fn apply_blocking(&self, config: Config) -> bool {
+        let _reloading = self.reloading.lock().unwrap();
+        self.log(LogEvent::info("Applying a new configuration"));
+        self.apply_validated(validate_config("configuration", &config, &self.registry))
+    }
+
+    fn apply_validated(&self, validation: Validation) -> bool {
+        for diagnostic in &validation.diagnostics {
+            self.log(diagnostic_event(&validation.format(diagnostic), diagnostic.severity));
+        }
+
+        let new_config = match validation.config {
+            Some(config) if !validation.has_errors() => config,
+            _ => {
+                self.log(LogEvent::error("Reload failed, keeping previous configuration"));
+                return false;
+            }
+        };
+
+        // Limiters and fault injectors are built in copies that are only installed once the router is
+        let rate_limits = Arc::new(self.rate_limits.stage());
+        let faults = Arc::new(self.faults.stage());
+        let (router, policies) = match build_router(&new_config, &self.registry, &self.log_sender, &self.metrics, &rate_limits, &faults) {
+            Ok(built) => built,
+            Err(e) => {
+                self.log(LogEvent::error(format!("Reload failed, keeping previous configuration: {}", e)));
+                return false;
+            }
+        };
+
+        let mut config = self.config.lock().unwrap();
+        *self.router.write().unwrap() = router;
+        *self.policies.write().unwrap() = policies;
+        rate_limits.retain(&new_config);
+        self.rate_limits.install(&rate_limits);
+        self.faults.install(&faults);
+
+        let changes = endpoint_changes(&config.endpoints, &new_config.endpoints);
+        if changes.is_empty() {
+            self.log(LogEvent::info("Configuration reloaded, no endpoint changes"));
+        } else {
+            self.log(LogEvent::info(format!("Configuration reloaded, {} endpoint change(s):", changes.len())));
+            for change in changes {
+                self.log(LogEvent::info(change));
+            }
+        }
+        if config.server.cors != new_config.server.cors {
+            self.log(LogEvent::info("CORS policy updated"));
+        }
+        if config.server.auth != new_config.server.auth {
+            self.log(LogEvent::info("Authentication updated"));
+        }
+        if config.server.rate_limit != new_config.server.rate_limit {
+            self.log(LogEvent::info("Rate limit updated"));
+        }
+        // The fallback proxy, CORS, authentication and rate limit are part of the router, the
+        // rest of the section is only read at startup
+        let startup_settings = |server: &ServerConfig| ServerConfig {
+            fallback_proxy: None,
+            cors: None,
+            auth: None,
+            rate_limit: None,
+            ..server.clone()
+        };
+        if startup_settings(&config.server) != startup_settings(&new_config.server) {
+            self.log(LogEvent::warn("Changes to the server section take effect after a restart"));
+        }
+
+        self.server_info.send_replace(Some(server_info(&new_config, &self.listeners, &self.metrics, &self.rate_limits, &self.faults)));
+        *config = new_config;
+        true
+    }
+
+    /// Switch fault injection of the endpoint with a key, e.g. `GET /users`, on or off, returning
+    /// whether it is now on, None if the endpoint doesn't exist or has no faults configured
+    pub fn toggle_faults(&self, key: &str) -> Option<bool> {
+        let config = self.config.lock().unwrap();
+        let endpoint = config.endpoints.iter()
+            .find(|endpoint| endpoint.faults.is_some() && endpoint.key() == key);
+        let Some(enabled) = endpoint.and_then(|endpoint| self.faults.toggle(&endpoint.key())) else {
+            self.log(LogEvent::warn(format!("{} has no faults configured", key)));
+            return None;
+        };
+
+        let state = if enabled { "enabled" } else { "disabled" };
+        self.log(LogEvent::info(format!("Fault injection {} for {}", state, key)));
+        self.server_info.send_replace(Some(server_info(&config, &self.listeners, &self.metrics, &self.rate_limits, &self.faults)));
+        Some(enabled)
+    }
+
+    /// Reload whenever the configuration file is modified
+    pub async fn watch(self: Arc<Self>) {
+        let Some(config_path) = self.config_path.clone() else {
+            return;
+        };
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        let mut loaded = modified_time(&config_path);
+        let mut pending = None;
+
+        loop {
+            interval.tick().await;
+            let modified = modified_time(&config_path);
+            if modified == loaded {
+                pending = None;
+                continue;
+            }
+
+            // Wait until the file has stopped changing, editors often write it in several steps
+            if pending == Some(modified) {
+                loaded = modified;
+                pending = None;
+                self.reload().await;
+            } else {
+                pending = Some(modified);
+            }
+        }
+    }
+
+    /// Keep the endpoint statistics in the server info up to date
+    pub async fn publish_stats(self: Arc<Self>) {
+        let mut interval = tokio::time::interval(STATS_INTERVAL);
+        loop {
+            interval.tick().await;
+            let info = server_info(&self.config.lock().unwrap(), &self.listeners, &self.metrics, &self.rate_limits, &self.faults);
+            self.server_info.send_if_modified(|current| {
+                let changed = current.as_ref().is_none_or(|current| {
+                    current.limiter != info.limiter
+                        || current.endpoints.iter().zip(&info.endpoints).any(|(a, b)| a.stats != b.stats || a.limiter != b.limiter)
+                });
+                if changed {
+                    *current = Some(info);
+                }
+                changed
+            });
+        }
+    }
+
+    /// Reload whenever the process receives SIGHUP
+    #[cfg(unix)]
+    pub async fn watch_hangup(self: Arc<Self>) {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let Ok(mut hangup) = signal(SignalKind::hangup()) else {
+            return;
+        };
+        while hangup.recv().await.is_some() {
+            self.reload().await;
+        }
+    }
+
+    fn log(&self, event: LogEvent) {
+        self.log_sender.send(event.with_source("config")).ok();
+    }
+}
+
+fn build_router(
+    config: &Config,
+    registry: &Arc<HandlerRegistry>,
+    log_sender: &mpsc::Sender<LogEvent>,
+    metrics: &Arc<Metrics>,
+    rate_limits: &Arc<RateLimits>,
+    faults: &Arc<Faults>,
+) -> Result<(Router, Arc<ServerPolicies>), Box<dyn std::error::Error>> {
+    let state = Arc::new(AppState {
+        log_sender: log_sender.clone(),
+        config: config.clone(),
+        handler_registry: registry.clone(),
+        metrics: metrics.clone(),
+        rate_limits: rate_limits.clone(),
+        faults: faults.clone(),
+    });
+    let policies = Arc::new(ServerPolicies::new(state.clone())?);
+    Ok((create_router(state)?, policies))
+}
+
+fn sites(config: &Config) -> Vec<Site> {
+    let endpoints: Vec<&EndpointConfig> = config.endpoints.iter().collect();
+    Site::all(&endpoints)
+}
+
+/// Log event for a configuration diagnostic
+pub fn diagnostic_event(message: &str, severity: Severity) -> LogEvent {
+    let event = match severity {
+        Severity::Error => LogEvent::error(message),
+        Severity::Warning => LogEvent::warn(message),
+    };
+    event.with_source("config")
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Server info shown in the UI for a configuration, with the statistics, limiter and fault state of each endpoint
+pub fn server_info(config: &Config, listeners: &[ListenerInfo], metrics: &Metrics, rate_limits: &RateLimits, faults: &Faults) -> ServerInfo {
+    ServerInfo {
+        host: config.server.host.clone(),
+        port: config.server.port,
+        listeners: listeners.to_vec(),
+        endpoints: config.endpoints.iter().map(|e| EndpointInfo {
+            key: e.key(),
+            path: e.path.clone(),
+            method: e.method.to_string(),
+            description: e.description.clone(),
+            listener: e.listener.clone(),
+            host: e.host.clone(),
+            stats: endpoint_stats(config, metrics, e),
+            limiter: e.rate_limit.as_ref().and_then(|_| rate_limits.stats(&e.key())),
+            faults: e.faults.as_ref().and_then(|_| faults.info(&e.key())),
+        }).collect(),
+        limiter: config.server.rate_limit.as_ref().and_then(|_| rate_limits.stats(SERVER_SCOPE)),
+    }
+}
+
+// Statistics of the requests an endpoint served
+fn endpoint_stats(config: &Config, metrics: &Metrics, endpoint: &EndpointConfig) -> EndpointStats {
+    match &endpoint.method {
+        EndpointMethod::Methods(methods) => metrics.endpoint_stats(&endpoint.route(), |method| {
+            // HEAD requests are served by the GET endpoint unless HEAD is declared
+            methods.iter().any(|m| m == method)
+                || (method == "HEAD" && methods.contains(&Method::GET) && !methods.contains(&Method::HEAD))
+        }),
+        // An ANY endpoint serves every method that no other endpoint on the path declares
+        EndpointMethod::Any => {
+            let declared: Vec<&Method> = config.endpoints.iter()
+                .filter(|other| other.route() == endpoint.route())
+                .flat_map(|other| match &other.method {
+                    EndpointMethod::Methods(methods) => methods.iter().collect(),
+                    EndpointMethod::Any => Vec::new(),
+                })
+                .collect();
+            metrics.endpoint_stats(&endpoint.route(), |method| !declared.iter().any(|m| *m == method))
+        },
+    }
+}
+
+// Describe added, removed and changed endpoints, keyed by method, path and scope
+fn endpoint_changes(old: &[EndpointConfig], new: &[EndpointConfig]) -> Vec<String> {
+    let key = |e: &EndpointConfig| e.key();
+    let mut changes = Vec::new();
+
+    for endpoint in new {
+        match old.iter().find(|o| key(o) == key(endpoint)) {
+            None => changes.push(format!("  + {}", endpoint.key())),
+            Some(previous) if previous != endpoint => {
+                let mut fields = Vec::new();
+                if previous.handler != endpoint.handler {
+                    fields.push(format!("handler {} -> {}", previous.handler, endpoint.handler));
+                }
+                if previous.response != endpoint.response {
+                    fields.push("response".to_string());
+                }
+                if previous.description != endpoint.description {
+                    fields.push("description".to_string());
+                }
+                if previous.params != endpoint.params {
+                    fields.push("params".to_string());
+                }
+                if previous.recordings != endpoint.recordings {
+                    fields.push("recordings".to_string());
+                }
+                if previous.request_schema != endpoint.request_schema {
+                    fields.push("request_schema".to_string());
+                }
+                if previous.response_schema != endpoint.response_schema {
+                    fields.push("response_schema".to_string());
+                }
+                if previous.checks != endpoint.checks {
+                    fields.push("checks".to_string());
+                }
+                if previous.cors != endpoint.cors {
+                    fields.push("cors".to_string());
+                }
+                if previous.auth != endpoint.auth {
+                    fields.push("auth".to_string());
+                }
+                if previous.rate_limit != endpoint.rate_limit {
+                    fields.push("rate_limit".to_string());
+                }
+                if previous.faults != endpoint.faults {
+                    fields.push("faults".to_string());
+                }
+                // Fields that are added later are still reported as a change
+                if fields.is_empty() {
+                    fields.push("other fields".to_string());
+                }
+                changes.push(format!("  ~ {} ({})", endpoint.key(), fields.join(", ")));
+            }
+            Some(_) => {}
+        }
+    }
+
+    for endpoint in old {
+        if !new.iter().any(|n| key(n) == key(endpoint)) {
+            changes.push(format!("  - {}", endpoint.key()));
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,101 @@
+use axum::body::Bytes;
+use axum::http::{HeaderName, HeaderValue, StatusCode, Uri};
+use axum_handlers::HandlerResponse;
+
+use crate::config::Recording;
+
+/// Name of the handler that serves recorded responses
+pub const REPLAY_HANDLER: &str = "replay";
+
+/// Recorded responses of an endpoint, with their fixture files loaded
+#[derive(Debug)]
+pub struct Replay {
+    recordings: Vec<LoadedRecording>,
+}
+
+#[derive(Debug)]
+struct LoadedRecording {
+    /// Sorted query parameters, None matches any query
+    query: Option<Vec<(String, String)>>,
+    request_body: Option<Bytes>,
+    response: HandlerResponse,
+}
+
+impl Replay {
+    pub fn load(recordings: &[Recording]) -> Result<Self, String> {
+        let recordings = recordings.iter()
+            .enumerate()
+            .map(|(index, recording)| load_recording(recording).map_err(|e| format!("recordings[{}]: {}", index, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { recordings })
+    }
+
+    /// The recorded response for a request, or a 404 if no recording matches
+    pub fn respond(&self, uri: &Uri, body: &Bytes) -> HandlerResponse {
+        let query = query_pairs(uri.query().unwrap_or_default());
+        let matches = |recording: &&LoadedRecording| {
+            recording.request_body.as_ref().is_none_or(|expected| same_body(expected, body))
+        };
+
+        // Recordings of the exact query win over recordings that match any query
+        let recording = self.recordings.iter()
+            .filter(matches)
+            .find(|recording| recording.query.as_ref() == Some(&query))
+            .or_else(|| self.recordings.iter().filter(matches).find(|recording| recording.query.is_none()));
+
+        match recording {
+            Some(recording) => recording.response.clone(),
+            None => HandlerResponse::text(format!("No recording matches {}", uri)).with_status(StatusCode::NOT_FOUND),
+        }
+    }
+}
+
+fn load_recording(recording: &Recording) -> Result<LoadedRecording, String> {
+    let status = StatusCode::from_u16(recording.status)
+        .map_err(|_| format!("invalid status {}", recording.status))?;
+
+    let body = match &recording.body_file {
+        Some(file) => read_fixture(file)?,
+        None => Bytes::new(),
+    };
+    let mut response = HandlerResponse::new(body).with_status(status);
+    for (name, values) in &recording.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("invalid header name `{}`", name))?;
+        for value in values.iter() {
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| format!("invalid value for header `{}`", name))?;
+            response.headers.append(name.clone(), value);
+        }
+    }
+
+    Ok(LoadedRecording {
+        query: recording.query.as_deref().map(query_pairs),
+        request_body: recording.request_body_file.as_deref().map(read_fixture).transpose()?,
+        response,
+    })
+}
+
+fn read_fixture(file: &str) -> Result<Bytes, String> {
+    std::fs::read(file)
+        .map(Bytes::from)
+        .map_err(|e| format!("failed to read `{}`: {}", file, e))
+}
+
+// Query parameters in a canonical order, so that `a=1&b=2` matches `b=2&a=1`
+fn query_pairs(query: &str) -> Vec<(String, String)> {
+    let mut pairs: Vec<(String, String)> = serde_urlencoded::from_str(query).unwrap_or_default();
+    pairs.sort();
+    pairs
+}
+
+// JSON bodies are compared by value, so formatting differences don't matter
+fn same_body(expected: &[u8], actual: &[u8]) -> bool {
+    if expected == actual {
+        return true;
+    }
+    match (serde_json::from_slice::<serde_json::Value>(expected), serde_json::from_slice::<serde_json::Value>(actual)) {
+        (Ok(expected), Ok(actual)) => expected == actual,
+        _ => false,
+    }
+}
@@ -1,171 +1,676 @@
 use axum::{
     Router,
-    routing::{get, post, put, delete},
-    extract::{State, Path},
-    http::StatusCode,
-    response::IntoResponse,
+    routing::{MethodFilter, MethodRouter},
+    body::Bytes,
+    middleware::{self, Next},
+    extract::{ConnectInfo, State, Path, Query, Request},
+    http::{header, request::Parts, uri::Authority, HeaderMap, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
 };
+use std::net::SocketAddr;
 use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::collections::HashMap;
-use axum_handlers::{HandlerRegistry, HandlerContext};
-use crate::config::Config;
+use std::time::Instant;
+use axum_handlers::{HandlerRegistry, HandlerContext, HandlerResponse, Principal};
+use axum_tui::{BodyPreview, HttpExchange, LogEvent};
+use crate::auth::Auth;
+use crate::config::{Config, EndpointConfig, EndpointMethod, Site, DEFAULT_LISTENER};
+use crate::cors::{self, Cors};
+use crate::faults::{self, Fault, FaultInjector, Faults};
+use crate::metrics::{self, Metrics};
+use crate::proxy::{Proxy, PROXY_HANDLER};
+use crate::rate_limit::{self, Limiter, RateLimits, SERVER_SCOPE};
+use crate::replay::{Replay, REPLAY_HANDLER};
+use tower::ServiceExt;
+
+/// Maximum size of a request body that is buffered for handlers
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Route label of requests handled by the fallback proxy
+const FALLBACK_ROUTE: &str = "*";
+
+/// Path of the endpoint standing for the routes the server serves itself, named in errors
+const SERVER_ROUTES: &str = "server routes";
+
+/// Handler name logged for answered CORS preflight requests
+const CORS_HANDLER: &str = "cors";
+
+/// Handler name logged for requests rejected by authentication
+const AUTH_HANDLER: &str = "auth";
+
+/// Handler name logged for requests rejected by rate or in-flight limits
+const RATE_LIMIT_HANDLER: &str = "rate_limit";
+
+/// Handler name logged for injected errors and closed connections
+const FAULT_HANDLER: &str = "fault";
+
+/// Counter for request ids when the client doesn't send an `X-Request-Id` header
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Name of the listener a request arrived on, each listener's router adds it to the requests
+#[derive(Debug, Clone)]
+pub struct ListenerName(pub String);
+
+/// Scheme of the listener a request arrived on, TLS listeners add it to the requests
+#[derive(Debug, Clone, Copy)]
+pub struct ListenerScheme(pub &'static str);
 
 pub struct AppState {
-    pub log_sender: mpsc::Sender<String>,
+    pub log_sender: mpsc::Sender<LogEvent>,
     pub config: Config,
-    pub handler_registry: HandlerRegistry,
+    pub handler_registry: Arc<HandlerRegistry>,
+    pub metrics: Arc<Metrics>,
+    pub rate_limits: Arc<RateLimits>,
+    pub faults: Arc<Faults>,
 }
 
-// Define handler functions for each HTTP method
-async fn handle_request(
-    State(state): State<Arc<AppState>>,
-    path: String,
-    method: String,
-    handler_name: String,
-    response: String,
-    params: HashMap<String, String>,
-) -> impl IntoResponse {
-    state.log_sender.send(format!("Received {} request to {}", method, path)).unwrap();
-    
-    // Check if the handler exists in the registry
-    if let Some(handler) = state.handler_registry.get(&handler_name) {
-        // Create the handler context
-        let ctx = HandlerContext {
-            path,
-            params,
-            static_response: Some(response),
+/// Handlers implemented by the server itself rather than the handler registry
+pub const SERVER_HANDLERS: [&str; 2] = [PROXY_HANDLER, REPLAY_HANDLER];
+
+/// An endpoint as it is served
+pub struct Route {
+    pub endpoint: EndpointConfig,
+    /// Set for endpoints using the proxy handler
+    pub proxy: Option<Proxy>,
+    /// Set for endpoints using the replay handler
+    pub replay: Option<Replay>,
+    /// Set when the server or the endpoint has a CORS policy
+    pub cors: Option<Cors>,
+    /// Set when the endpoint requires authentication
+    pub auth: Option<Auth>,
+    /// The server's limiter then the endpoint's, requests must get through all of them
+    pub limiters: Vec<Arc<Limiter>>,
+    /// Set when the endpoint has faults configured, they are only injected while it is switched on
+    pub faults: Option<Arc<FaultInjector>>,
+}
+
+impl Route {
+    /// Prepare an endpoint, with the CORS policy, authentication and limits it inherits from the server
+    pub fn new(endpoint: EndpointConfig, state: &AppState) -> Result<Self, Box<dyn std::error::Error>> {
+        let server = &state.config.server;
+        let context = |e: String| format!("{} {}: {}", endpoint.method, endpoint.path, e);
+        let proxy = match endpoint.handler.as_str() {
+            PROXY_HANDLER => Some(Proxy::from_params(&endpoint.params).map_err(context)?),
+            _ => None,
+        };
+        let replay = match endpoint.handler.as_str() {
+            REPLAY_HANDLER => Some(Replay::load(&endpoint.recordings).map_err(context)?),
+            _ => None,
+        };
+        let cors = match (&server.cors, &endpoint.cors) {
+            (None, None) => None,
+            (server, endpoint) => {
+                let config = server.clone().unwrap_or_default().overridden_by(&endpoint.clone().unwrap_or_default());
+                Some(Cors::new(&config).map_err(|e| format!("cors: {}", e)).map_err(context)?)
+            },
+        };
+        // An endpoint's section replaces the server's, an empty one makes the endpoint public
+        let auth = match endpoint.auth.as_ref().or(server.auth.as_ref()) {
+            Some(config) => Some(Auth::new(config).map_err(|e| format!("auth: {}", e)).map_err(context)?)
+                .filter(|auth| !auth.is_public()),
+            None => None,
         };
-        
-        // Execute the handler
-        match handler.handle(ctx).await {
-            Ok(response) => response.into_response(),
-            Err((status, message)) => (status, message).into_response(),
+        let mut limiters = Vec::new();
+        if let Some(config) = &server.rate_limit {
+            limiters.push(state.rate_limits.limiter(SERVER_SCOPE, config).map_err(|e| format!("server rate_limit: {}", e))?);
         }
-    } else {
-        // If the handler doesn't exist, use the default handler
-        if let Some(default_handler) = state.handler_registry.get("default") {
-            let ctx = HandlerContext {
-                path,
-                params,
-                static_response: Some(response),
+        if let Some(config) = &endpoint.rate_limit {
+            limiters.push(state.rate_limits.limiter(&endpoint.key(), config).map_err(|e| format!("rate_limit: {}", e)).map_err(context)?);
+        }
+        let faults = match &endpoint.faults {
+            Some(config) => Some(state.faults.injector(&endpoint.key(), config).map_err(|e| format!("faults: {}", e)).map_err(context)?),
+            None => None,
+        };
+        Ok(Self { endpoint, proxy, replay, cors, auth, limiters, faults })
+    }
+}
+
+/// The server-level policies, applied to the routes the server serves itself: metrics, the
+/// OpenAPI document, the docs page and the reload endpoint. Endpoint sections don't apply to them.
+pub struct ServerPolicies {
+    state: Arc<AppState>,
+    route: Route,
+}
+
+impl ServerPolicies {
+    pub fn new(state: Arc<AppState>) -> Result<Self, Box<dyn std::error::Error>> {
+        let route = Route::new(EndpointConfig::new(EndpointMethod::Any, SERVER_ROUTES), &state)?;
+        Ok(Self { state, route })
+    }
+
+    /// Pass a request on to `next` if it gets through the server's authentication and limits, log
+    /// and answer it otherwise, and add the server's CORS headers
+    pub async fn apply(&self, request: Request, next: Next) -> Response {
+        let start = Instant::now();
+        if let Some(cors) = self.route.cors.as_ref().filter(|_| cors::is_preflight(&request)) {
+            return handle_preflight(&self.state, cors, &request);
+        }
+        let origin = request.headers().get(header::ORIGIN).cloned();
+
+        let authenticated = match &self.route.auth {
+            Some(auth) => auth.authenticate(request.headers(), request.uri()).await,
+            None => Ok(None),
+        };
+
+        let principal = match authenticated {
+            Ok(principal) => principal,
+            Err(rejection) => {
+                let message = rejection.message();
+                let field = ("auth_rejected", rejection.reason.clone());
+                let mut response = rejection.into_response();
+                self.apply_cors(origin.as_ref(), &mut response);
+                return self.rejected(&request, start, AUTH_HANDLER, field, &message, response);
+            },
+        };
+
+        // The permits are held until the response body has been sent
+        let client_ip = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(address)| address.ip());
+        let mut response = match rate_limit::acquire(&self.route.limiters, request.headers(), client_ip, principal.as_ref()) {
+            Ok(permits) => rate_limit::hold(next.run(request).await, permits),
+            Err(limited) => {
+                let message = limited.message();
+                let field = ("rate_limited", limited.reason.clone());
+                let mut response = limited.into_response();
+                self.apply_cors(origin.as_ref(), &mut response);
+                return self.rejected(&request, start, RATE_LIMIT_HANDLER, field, &message, response);
+            },
+        };
+        self.apply_cors(origin.as_ref(), &mut response);
+        response
+    }
+
+    fn apply_cors(&self, origin: Option<&HeaderValue>, response: &mut Response) {
+        if let Some(cors) = &self.route.cors {
+            cors.apply(origin, response.headers_mut());
+        }
+    }
+
+    // Log a request the policies answered themselves
+    fn rejected(&self, request: &Request, start: Instant, handler: &str, (key, reason): (&str, String), message: &str, response: Response) -> Response {
+        let exchange = HttpExchange {
+            query: request.uri().query().map(str::to_string),
+            request_headers: header_pairs(request.headers()),
+            response_headers: header_pairs(response.headers()),
+            response_body: BodyPreview::from_bytes(message.as_bytes()),
+            ..Default::default()
+        };
+        let event = LogEvent::request(request.method().as_str(), request.uri().path(), response.status().as_u16(), start.elapsed())
+            .with_request_id(request_id(request.headers()))
+            .with_handler(handler)
+            .with_field(key, reason)
+            .with_exchange(exchange);
+        self.state.log_sender.send(event).ok();
+        response
+    }
+}
+
+// Run the configured handler for an endpoint and log the outcome
+async fn handle_request(
+    State(state): State<Arc<AppState>>,
+    Path(path_params): Path<HashMap<String, String>>,
+    route: Arc<Route>,
+    request: Request,
+) -> Response {
+    let start = Instant::now();
+    let endpoint = &route.endpoint;
+    let method = request.method().to_string();
+    let method_label = metrics::method_label(request.method(), &endpoint.method).to_string();
+    let path = request.uri().path().to_string();
+    let request_id = request_id(request.headers());
+    let origin = request.headers().get(header::ORIGIN).cloned();
+
+    let mut exchange = HttpExchange {
+        query: request.uri().query().map(str::to_string),
+        request_headers: header_pairs(request.headers()),
+        ..Default::default()
+    };
+
+    let authenticated = match &route.auth {
+        Some(auth) => auth.authenticate(request.headers(), request.uri()).await,
+        None => Ok(None),
+    };
+    let principal_name = authenticated.as_ref().ok().and_then(|principal| principal.as_ref()).map(|principal| principal.name.clone());
+    let mut rejected = None;
+
+    // Limits are checked once the client is known, the permits are held until the response body has been sent
+    let client_ip = request.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(address)| address.ip());
+    let permits = match &authenticated {
+        Ok(principal) => Some(rate_limit::acquire(&route.limiters, request.headers(), client_ip, principal.as_ref())),
+        Err(_) => None,
+    };
+    let mut rate_limited = None;
+
+    // Faults are only injected into requests that get through to the handler
+    let injection = match (&permits, &route.faults) {
+        (Some(Ok(_)), Some(injector)) => injector.inject().map(|injection| (injector, injection)),
+        _ => None,
+    };
+    if let Some(delay) = injection.and_then(|(_, injection)| injection.delay) {
+        tokio::time::sleep(delay).await;
+    }
+    let injected = injection.and_then(|(injector, injection)| match injection.fault {
+        Some(Fault::Truncate) | None => None,
+        Some(fault) => Some((injector, fault)),
+    });
+
+    let mut forwarded = None;
+    let (mut response, handler) = match (authenticated, permits.as_ref(), injected, &route.proxy) {
+        (Err(rejection), _, _, _) => {
+            exchange.response_body = BodyPreview::from_bytes(rejection.message().as_bytes());
+            rejected = Some(rejection.reason.clone());
+            (rejection.into_response(), Some(AUTH_HANDLER.to_string()))
+        },
+        (Ok(_), Some(Err(limited)), _, _) => {
+            exchange.response_body = BodyPreview::from_bytes(limited.message().as_bytes());
+            rate_limited = Some(limited.reason.clone());
+            (limited.clone().into_response(), Some(RATE_LIMIT_HANDLER.to_string()))
+        },
+        (Ok(_), _, Some((_, Fault::Close)), _) => (faults::closed_connection(), Some(FAULT_HANDLER.to_string())),
+        (Ok(_), _, Some((injector, _)), _) => {
+            exchange.response_body = BodyPreview::from_bytes(injector.error_body().as_bytes());
+            (injector.error_response(), Some(FAULT_HANDLER.to_string()))
+        },
+        // Proxied bodies are streamed through, so they are not previewed
+        (Ok(_), _, _, Some(proxy)) => {
+            let (response, upstream) = proxy.forward(request).await;
+            exchange.request_body = BodyPreview::streamed();
+            exchange.response_body = BodyPreview::streamed();
+            forwarded = Some(upstream);
+            (response, Some(PROXY_HANDLER.to_string()))
+        },
+        (Ok(principal), _, _, None) => {
+            let (parts, body) = request.into_parts();
+
+            // Buffer the body so handlers can inspect it
+            let (response, handler) = match axum::body::to_bytes(body, MAX_BODY_SIZE).await {
+                Ok(body) => {
+                    exchange.request_body = BodyPreview::from_bytes(&body);
+                    match &route.replay {
+                        Some(replay) => (replay.respond(&parts.uri, &body), Some(REPLAY_HANDLER.to_string())),
+                        None => run_handler(&state, path_params, endpoint, parts, body, principal).await,
+                    }
+                },
+                Err(e) => (HandlerResponse::text(format!("Failed to read request body: {}", e)).with_status(StatusCode::PAYLOAD_TOO_LARGE), None),
             };
-            
-            match default_handler.handle(ctx).await {
-                Ok(response) => response.into_response(),
-                Err((status, message)) => (status, message).into_response(),
-            }
-        } else {
-            // If there's no default handler, return a 500 error
-            (StatusCode::INTERNAL_SERVER_ERROR, "No handler found").into_response()
+            exchange.response_body = BodyPreview::from_bytes(&response.body);
+            (response.into_response(), handler)
+        },
+    };
+
+    if injection.is_some_and(|(_, injection)| injection.fault == Some(Fault::Truncate)) {
+        response = faults::truncate(response);
+    }
+    if let Some(Ok(permits)) = permits {
+        response = rate_limit::hold(response, permits);
+    }
+    if let Some(cors) = &route.cors {
+        cors.apply(origin.as_ref(), response.headers_mut());
+    }
+    exchange.response_headers = header_pairs(response.headers());
+
+    let latency = start.elapsed();
+    let status = response.status().as_u16();
+    state.metrics.record(&method_label, &endpoint.route(), handler.as_deref().unwrap_or("none"), status, latency);
+
+    let mut event = LogEvent::request(method, path, status, latency)
+        .with_request_id(request_id)
+        .with_exchange(exchange);
+    if let Some(handler) = handler {
+        event = event.with_handler(handler);
+    }
+    if let Some(name) = principal_name {
+        event = event.with_field("principal", name);
+    }
+    if let Some(reason) = rejected {
+        event = event.with_field("auth_rejected", reason);
+    }
+    if let Some(reason) = rate_limited {
+        event = event.with_field("rate_limited", reason);
+    }
+    if let Some(fault) = injection.and_then(|(injector, injection)| injector.describe_injection(&injection)) {
+        event = event.with_field("fault", fault);
+    }
+    if let Some(forwarded) = forwarded {
+        event = event.with_field("upstream", forwarded.url);
+        if let Some(latency) = forwarded.latency {
+            event = event.with_field("upstream_latency", format!("{:.1}ms", latency.as_secs_f64() * 1000.0));
+        }
+        if let Some(error) = forwarded.error {
+            event = event.with_field("upstream_error", error);
         }
     }
+    state.log_sender.send(event).ok();
+
+    response
 }
 
-pub fn create_router(state: Arc<AppState>) -> Router {
+// Answer a CORS preflight request with the policy of the route serving the requested method
+fn handle_preflight(state: &AppState, cors: &Cors, request: &Request) -> Response {
+    let start = Instant::now();
+    let mut exchange = HttpExchange {
+        request_headers: header_pairs(request.headers()),
+        ..Default::default()
+    };
+
+    let (response, rejected) = match cors.preflight(request.headers()) {
+        Ok(headers) => ((StatusCode::NO_CONTENT, headers).into_response(), None),
+        Err(reason) => {
+            let message = format!("CORS preflight rejected: {}", reason);
+            exchange.response_body = BodyPreview::from_bytes(message.as_bytes());
+            ((StatusCode::FORBIDDEN, message).into_response(), Some(reason))
+        },
+    };
+    exchange.response_headers = header_pairs(response.headers());
+
+    let mut event = LogEvent::request(Method::OPTIONS.as_str(), request.uri().path(), response.status().as_u16(), start.elapsed())
+        .with_request_id(request_id(request.headers()))
+        .with_handler(CORS_HANDLER)
+        .with_exchange(exchange);
+    if let Some(reason) = rejected {
+        event = event.with_field("cors_rejected", reason);
+    }
+    state.log_sender.send(event).ok();
+
+    response
+}
+
+// The client's `X-Request-Id`, or the next generated id
+fn request_id(headers: &HeaderMap) -> String {
+    headers.get("x-request-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:06}", NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)))
+}
+
+// Run the configured handler, returning its response and the name of the handler that ran
+async fn run_handler(
+    state: &AppState,
+    path_params: HashMap<String, String>,
+    endpoint: &EndpointConfig,
+    parts: Parts,
+    body: Bytes,
+    principal: Option<Principal>,
+) -> (HandlerResponse, Option<String>) {
+    let query = Query::<HashMap<String, String>>::try_from_uri(&parts.uri)
+        .map(|Query(query)| query)
+        .unwrap_or_default();
+
+    let ctx = HandlerContext {
+        method: parts.method,
+        path: parts.uri.path().to_string(),
+        path_params,
+        headers: parts.headers,
+        query,
+        body,
+        params: endpoint.params.clone(),
+        static_response: Some(endpoint.response.clone()),
+        principal,
+    };
+
+    // Use the configured handler, falling back to the default handler
+    let (name, handler) = match state.handler_registry.get(&endpoint.handler) {
+        Some(handler) => (endpoint.handler.as_str(), Some(handler)),
+        None => ("default", state.handler_registry.get("default")),
+    };
+
+    if let Some(handler) = handler {
+        let response = handler.handle(ctx).await
+            .unwrap_or_else(|(status, message)| HandlerResponse::text(message).with_status(status));
+        (response, Some(name.to_string()))
+    } else {
+        // If there's no default handler, return a 500 error
+        (HandlerResponse::text("No handler found").with_status(StatusCode::INTERNAL_SERVER_ERROR), None)
+    }
+}
+
+// Header names and values for display, non-text values are shown lossily
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers.iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect()
+}
+
+pub fn create_router(state: Arc<AppState>) -> Result<Router, Box<dyn std::error::Error>> {
+    let endpoints: Vec<&EndpointConfig> = state.config.endpoints.iter().collect();
+    let sites = Site::all(&endpoints);
+
+    let router = if sites.len() == 1 {
+        endpoint_router(&state, &endpoints)?
+    } else {
+        // Each listener and virtual host gets a router of its own endpoints
+        let mut routers = HashMap::new();
+        for site in &sites {
+            let served: Vec<&EndpointConfig> = site.endpoints(&endpoints).into_iter().map(|index| endpoints[index]).collect();
+            routers.insert(site.clone(), endpoint_router(&state, &served)?);
+        }
+        Router::new().fallback(move |request: Request| {
+            let site = request_site(&sites, &request);
+            let router = routers[&site].clone();
+            async move { router.oneshot(request).await.into_response() }
+        })
+    };
+
+    // axum adds the path's `Allow` header to responses from a method fallback, which is
+    // empty on paths that only have ANY or custom method endpoints. It is added after route
+    // layers run, so the header is removed in a layer around the whole router.
+    Ok(Router::new()
+        .fallback_service(router)
+        .layer(middleware::map_response(remove_empty_allow)))
+}
+
+/// The site of a request, from the listener it arrived on and the host it is for
+pub fn request_site(sites: &[Site], request: &Request) -> Site {
+    let listener = request.extensions().get::<ListenerName>()
+        .map_or(DEFAULT_LISTENER, |ListenerName(name)| name.as_str());
+    Site::of_request(sites, listener, request_host(request).as_deref())
+}
+
+// Host a request is for without the port, from the `Host` header or the URI of HTTP/2 requests
+fn request_host(request: &Request) -> Option<String> {
+    let authority = match request.headers().get(header::HOST) {
+        Some(host) => host.to_str().ok()?.parse::<Authority>().ok()?,
+        None => request.uri().authority()?.clone(),
+    };
+    Some(authority.host().to_string())
+}
+
+// The router of a set of endpoints, with the fallback proxy for requests none of them handles
+fn endpoint_router(state: &Arc<AppState>, endpoints: &[&EndpointConfig]) -> Result<Router, Box<dyn std::error::Error>> {
     let mut router = Router::new();
-    
+
+    // Group endpoints by path so that each path gets a single method router
+    let mut paths: Vec<(&str, Vec<&EndpointConfig>)> = Vec::new();
+    for &endpoint in endpoints {
+        match paths.iter_mut().find(|(path, _)| *path == endpoint.path) {
+            Some((_, endpoints)) => endpoints.push(endpoint),
+            None => paths.push((&endpoint.path, vec![endpoint])),
+        }
+    }
+
+    // Requests no endpoint handles go to the fallback proxy, if one is configured
+    let fallback = match &state.config.server.fallback_proxy {
+        Some(params) => Some(Arc::new(Route::new(EndpointConfig {
+            handler: PROXY_HANDLER.to_string(),
+            description: "Fallback proxy".to_string(),
+            params: params.clone(),
+            ..EndpointConfig::new(EndpointMethod::Any, FALLBACK_ROUTE)
+        }, state)?)),
+        None => None,
+    };
+
     // Add routes dynamically based on the configuration
-    for endpoint in &state.config.endpoints {
-        let path = endpoint.path.clone();
-        let response = endpoint.response.clone();
-        let method = endpoint.method.to_uppercase();
-        let handler_name = endpoint.handler.clone();
-        let params = endpoint.params.clone();
-        
-        // Check if the path contains path parameters
-        let has_path_params = path.contains('{') && path.contains('}');
-        
-        // Add the route based on the HTTP method
-        match method.as_str() {
-            "GET" => {
-                if has_path_params {
-                    // For paths with parameters
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, get(move |state: State<Arc<AppState>>, path_params: Path<String>| {
-                        let actual_path = path_params.0.clone();
-                        handle_request(state, actual_path, "GET".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
-                } else {
-                    // For paths without parameters
-                    let path_clone = path.clone();
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, get(move |state: State<Arc<AppState>>| {
-                        handle_request(state, path_clone, "GET".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
+    for (path, endpoints) in paths {
+        router = router.route(path, method_router(path, &endpoints, state, fallback.clone())?);
+    }
+
+    if let Some(fallback) = fallback {
+        router = router.fallback(move |state: State<Arc<AppState>>, request: Request| {
+            let fallback = fallback.clone();
+            async move {
+                match &fallback.cors {
+                    Some(cors) if cors::is_preflight(&request) => handle_preflight(&state, cors, &request),
+                    _ => handle_request(state, Path(HashMap::new()), fallback, request).await,
                 }
-            },
-            "POST" => {
-                if has_path_params {
-                    // For paths with parameters
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, post(move |state: State<Arc<AppState>>, path_params: Path<String>| {
-                        let actual_path = path_params.0.clone();
-                        handle_request(state, actual_path, "POST".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
-                } else {
-                    // For paths without parameters
-                    let path_clone = path.clone();
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, post(move |state: State<Arc<AppState>>| {
-                        handle_request(state, path_clone, "POST".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
+            }
+        });
+    }
+
+    Ok(router.with_state(state.clone()))
+}
+
+async fn remove_empty_allow(mut response: Response) -> Response {
+    if response.headers().get(header::ALLOW).is_some_and(|allow| allow.is_empty()) {
+        response.headers_mut().remove(header::ALLOW);
+    }
+    response
+}
+
+// Build the method router for all endpoints sharing a path, methods without an
+// endpoint go to the fallback route if there is one
+fn method_router(
+    path: &str,
+    endpoints: &[&EndpointConfig],
+    state: &AppState,
+    fallback: Option<Arc<Route>>,
+) -> Result<MethodRouter<Arc<AppState>>, Box<dyn std::error::Error>> {
+    let mut method_router = MethodRouter::new();
+    let mut routes: Vec<(Method, Arc<Route>)> = Vec::new();
+    let mut custom: Vec<(Method, Arc<Route>)> = Vec::new();
+    let mut any: Option<Arc<Route>> = None;
+
+    for endpoint in endpoints {
+        let shared = Arc::new(Route::new((*endpoint).clone(), state)?);
+
+        match &endpoint.method {
+            EndpointMethod::Any => {
+                if any.replace(shared).is_some() {
+                    return Err(format!("Duplicate ANY endpoint for {}", path).into());
                 }
             },
-            "PUT" => {
-                if has_path_params {
-                    // For paths with parameters
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, put(move |state: State<Arc<AppState>>, path_params: Path<String>| {
-                        let actual_path = path_params.0.clone();
-                        handle_request(state, actual_path, "PUT".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
-                } else {
-                    // For paths without parameters
-                    let path_clone = path.clone();
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, put(move |state: State<Arc<AppState>>| {
-                        handle_request(state, path_clone, "PUT".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
+            EndpointMethod::Methods(methods) => {
+                for method in methods {
+                    if routes.iter().any(|(declared, _)| declared == method) {
+                        return Err(format!("Duplicate endpoint for {} {}", method, path).into());
+                    }
+                    routes.push((method.clone(), shared.clone()));
                 }
             },
-            "DELETE" => {
-                if has_path_params {
-                    // For paths with parameters
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, delete(move |state: State<Arc<AppState>>, path_params: Path<String>| {
-                        let actual_path = path_params.0.clone();
-                        handle_request(state, actual_path, "DELETE".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
-                } else {
-                    // For paths without parameters
-                    let path_clone = path.clone();
-                    let response_clone = response.clone();
-                    let handler_name_clone = handler_name.clone();
-                    let params_clone = params.clone();
-                    router = router.route(&endpoint.path, delete(move |state: State<Arc<AppState>>| {
-                        handle_request(state, path_clone, "DELETE".to_string(), handler_name_clone, response_clone, params_clone)
-                    }));
-                }
+        }
+    }
+
+    // With a CORS policy on the path, OPTIONS requests are dispatched by the preflight handler below
+    let cors = routes.iter().map(|(_, route)| route).chain(&any).chain(&fallback).any(|route| route.cors.is_some());
+
+    for (method, route) in &routes {
+        if cors && method == Method::OPTIONS {
+            continue;
+        }
+        // Standard methods are routed by axum, anything else goes through the fallback
+        match MethodFilter::try_from(method.clone()) {
+            Ok(filter) => {
+                let endpoint = route.clone();
+                method_router = method_router.on(filter, move |state: State<Arc<AppState>>, path_params: Path<HashMap<String, String>>, request: Request| {
+                    handle_request(state, path_params, endpoint.clone(), request)
+                });
             },
-            _ => {
-                eprintln!("Unsupported HTTP method: {}", method);
+            Err(_) => custom.push((method.clone(), route.clone())),
+        }
+    }
+
+    let declared: Vec<Method> = routes.iter().map(|(method, _)| method.clone()).collect();
+    let allow = allow_header(&declared);
+
+    if cors {
+        // Preflights get the policy of the route serving the requested method, other
+        // OPTIONS requests go to the route serving OPTIONS or get the allowed methods
+        let routes = routes.clone();
+        let options = routes.iter()
+            .find(|(method, _)| method == Method::OPTIONS)
+            .map(|(_, route)| route.clone())
+            .or_else(|| any.clone())
+            .or_else(|| fallback.clone());
+        let any = any.clone();
+        let fallback = fallback.clone();
+        let allow = allow.clone();
+        method_router = method_router.options(move |state: State<Arc<AppState>>, path_params: Path<HashMap<String, String>>, request: Request| {
+            let preflight = cors::is_preflight(&request)
+                .then(|| preflight_route(request.headers(), &routes, any.as_ref(), fallback.as_ref()))
+                .flatten();
+            let options = options.clone();
+            let allow = allow.clone();
+
+            async move {
+                match (preflight, options) {
+                    (Some(route), _) => handle_preflight(&state, route.cors.as_ref().expect("preflight routes have a CORS policy"), &request),
+                    (None, Some(options)) => handle_request(state, path_params, options, request).await,
+                    (None, None) => options_response(allow),
+                }
             }
-        };
+        });
+    } else if any.is_none() && fallback.is_none() && !declared.contains(&Method::OPTIONS) {
+        // Answer OPTIONS with the allowed methods unless an endpoint or the fallback handles it
+        let allow = allow.clone();
+        method_router = method_router.options(move || async move { options_response(allow) });
+    }
+
+    // HEAD is answered by the GET handler when it isn't declared, which axum does by default.
+    // Custom methods, ANY endpoints and the fallback route are dispatched from the fallback.
+    if any.is_some() || !custom.is_empty() || fallback.is_some() {
+        method_router = method_router.fallback(move |state: State<Arc<AppState>>, path_params: Path<HashMap<String, String>>, request: Request| {
+            let endpoint = custom.iter()
+                .find(|(method, _)| method == request.method())
+                .map(|(_, endpoint)| endpoint.clone())
+                .or_else(|| any.clone())
+                .or_else(|| fallback.clone());
+            let allow = allow.clone();
+
+            async move {
+                match endpoint {
+                    Some(endpoint) => handle_request(state, path_params, endpoint, request).await,
+                    None => method_not_allowed(allow),
+                }
+            }
+        });
+    }
+
+    Ok(method_router)
+}
+
+// The route serving the method a preflight asks for, None if there is none or it has no CORS policy
+fn preflight_route(
+    headers: &HeaderMap,
+    routes: &[(Method, Arc<Route>)],
+    any: Option<&Arc<Route>>,
+    fallback: Option<&Arc<Route>>,
+) -> Option<Arc<Route>> {
+    let method = cors::requested_method(headers)?;
+    let declared = |method: &Method| routes.iter().find(|(declared, _)| declared == method).map(|(_, route)| route);
+    declared(&method)
+        // HEAD is answered by the GET endpoint unless it is declared
+        .or_else(|| (method == Method::HEAD).then(|| declared(&Method::GET)).flatten())
+        .or(any)
+        .or(fallback)
+        .filter(|route| route.cors.is_some())
+        .cloned()
+}
+
+// Value of the `Allow` header for a set of declared methods
+fn allow_header(declared: &[Method]) -> String {
+    let mut allowed: Vec<&str> = declared.iter().map(Method::as_str).collect();
+    if declared.contains(&Method::GET) && !declared.contains(&Method::HEAD) {
+        allowed.push("HEAD");
     }
-    
-    router.with_state(state)
-} 
\ No newline at end of file
+    if !declared.contains(&Method::OPTIONS) {
+        allowed.push("OPTIONS");
+    }
+    allowed.join(", ")
+}
+
+fn options_response(allow: String) -> Response {
+    (StatusCode::NO_CONTENT, [(header::ALLOW, allow)]).into_response()
+}
+
+fn method_not_allowed(allow: String) -> Response {
+    (StatusCode::METHOD_NOT_ALLOWED, [(header::ALLOW, allow)]).into_response()
+}
@@ -0,0 +1,569 @@
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+
+use axum::extract::Request;
+use axum::response::IntoResponse;
+use axum::routing::Route;
+use axum::serve::ListenerExt;
+use axum::{Extension, Router};
+use axum_handlers::{register_default_handlers, Handler, HandlerRegistry};
+use axum_tui::{run_ui, ListenerInfo, LogEvent, UiCommand};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tower::{Layer, Service};
+
+use crate::config::{Config, EndpointConfig, ListenAddress, TlsConfig, DEFAULT_LISTENER};
+use crate::headless::{HeadlessLogger, LogFormat};
+use crate::proxy::PROXY_HANDLER;
+use crate::reload::{diagnostic_event, Reloader};
+use crate::replay::REPLAY_HANDLER;
+use crate::routes::{ListenerName, ListenerScheme};
+use crate::tls::{redirect_router, write_self_signed, CertificateFile, Tls, TlsListener};
+use crate::validate::{validate_config, validate_file, Severity, Validation};
+
+type Signal = Pin<Box<dyn Future<Output = ()> + Send>>;
+type RouterLayer = Box<dyn FnOnce(Router) -> Router + Send>;
+type Serving = tokio::task::JoinHandle<std::io::Result<()>>;
+
+/// Where the server's log events go
+pub enum LogOutput {
+    /// The terminal UI, closing it stops the server
+    Ui,
+    /// Printed to stdout and stderr
+    Print(LogFormat),
+    /// Sent to a channel
+    Channel(mpsc::Sender<LogEvent>),
+}
+
+/// Configures and starts a server
+pub struct ServerBuilder {
+    config: Config,
+    config_file: Option<String>,
+    registry: HandlerRegistry,
+    routers: Vec<Router>,
+    layers: Vec<RouterLayer>,
+    log_output: LogOutput,
+    log_sender: mpsc::Sender<LogEvent>,
+    log_receiver: mpsc::Receiver<LogEvent>,
+    signals: Vec<Signal>,
+    reload_on_hangup: bool,
+    self_signed: bool,
+}
+
+impl ServerBuilder {
+    /// Serve a configuration with the default handlers, printing logs as text
+    pub fn new(config: Config) -> Self {
+        let mut registry = HandlerRegistry::new();
+        register_default_handlers(&mut registry);
+        let (log_sender, log_receiver) = mpsc::channel();
+
+        Self {
+            config,
+            config_file: None,
+            registry,
+            routers: Vec::new(),
+            layers: Vec::new(),
+            log_output: LogOutput::Print(LogFormat::Text),
+            log_sender,
+            log_receiver,
+            signals: Vec::new(),
+            reload_on_hangup: false,
+            self_signed: false,
+        }
+    }
+
+    /// Replace the configuration
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    #[cfg(feature = "testing")]
+    pub(crate) fn config_mut(&mut self) -> &mut Config {
+        &mut self.config
+    }
+
+    /// Add an endpoint to the configuration
+    pub fn endpoint(mut self, endpoint: EndpointConfig) -> Self {
+        self.config.endpoints.push(endpoint);
+        self
+    }
+
+    /// File the configuration was read from, it is reloaded from there and watched if `watch_config` is set
+    pub fn config_file(mut self, path: impl Into<String>) -> Self {
+        self.config_file = Some(path.into());
+        self
+    }
+
+    /// Replace the handler registry
+    pub fn handlers(mut self, registry: HandlerRegistry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    /// Register a handler in addition to the ones already registered
+    pub fn handler<H: Handler>(mut self, name: &str, handler: H) -> Self {
+        self.registry.register(name, handler);
+        self
+    }
+
+    /// Serve the routes of another router next to the configured endpoints
+    ///
+    /// The router's routes take precedence over the endpoints and must not overlap
+    /// the admin routes of the configuration.
+    pub fn merge(mut self, router: Router) -> Self {
+        self.routers.push(router);
+        self
+    }
+
+    /// Wrap every route, including merged ones, in a layer
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: Layer<Route> + Clone + Send + Sync + 'static,
+        L::Service: Service<Request> + Clone + Send + Sync + 'static,
+        <L::Service as Service<Request>>::Response: IntoResponse + 'static,
+        <L::Service as Service<Request>>::Error: Into<Infallible> + 'static,
+        <L::Service as Service<Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Box::new(move |router: Router| router.layer(layer)));
+        self
+    }
+
+    /// Where log events go, printed as text by default
+    pub fn log_output(mut self, output: LogOutput) -> Self {
+        self.log_output = output;
+        self
+    }
+
+    /// Sender for adding events to the server's log
+    pub fn log_sender(&self) -> mpsc::Sender<LogEvent> {
+        self.log_sender.clone()
+    }
+
+    /// Shut down gracefully once `signal` completes, e.g. on Ctrl+C
+    pub fn shutdown_signal(mut self, signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.signals.push(Box::pin(signal));
+        self
+    }
+
+    /// Reload the configuration file when the process receives SIGHUP
+    pub fn reload_on_hangup(mut self, enabled: bool) -> Self {
+        self.reload_on_hangup = enabled;
+        self
+    }
+
+    /// Serve HTTPS with a certificate for localhost generated at startup, in place of the configured one
+    pub fn tls_self_signed(mut self, enabled: bool) -> Self {
+        self.self_signed = enabled;
+        self
+    }
+
+    /// Validate the configuration, bind the listener and start serving in the background
+    pub async fn start(self) -> Result<ServerHandle, Box<dyn std::error::Error>> {
+        let validation = self.validation();
+        // Events of the server go straight to a channel output, so they have arrived once a response has
+        let log_sender = match &self.log_output {
+            LogOutput::Channel(sender) => sender.clone(),
+            _ => self.log_sender,
+        };
+        if validation.has_errors() {
+            let errors: Vec<String> = validation.diagnostics.iter()
+                .filter(|d| d.severity == Severity::Error)
+                .map(|d| validation.format(d))
+                .collect();
+            return Err(format!("Invalid configuration:\n{}", errors.join("\n")).into());
+        }
+        let config = self.config;
+
+        let tls_config = config.server.tls.clone().or_else(|| self.self_signed.then(TlsConfig::default));
+        let tls = match &tls_config {
+            Some(tls_config) => Some(Arc::new(Tls::new(tls_config, self.self_signed)
+                .map_err(|e| format!("Invalid configuration:\nserver.tls: {}", e))?)),
+            None => None,
+        };
+
+        let listener = TcpListener::bind(config.get_socket_addr()?).await?;
+        let local_addr = listener.local_addr()?;
+        let scheme = if tls.is_some() { "https://" } else { "" };
+        log_sender.send(format!("Server listening on {}{}", scheme, local_addr).into()).ok();
+
+        // Bind the additional listeners, Unix domain sockets are served without TLS
+        let mut listeners = vec![(DEFAULT_LISTENER.to_string(), ListenAddress::Tcp(local_addr))];
+        let mut bound = Vec::new();
+        for (name, address) in config.listen_addresses()?.into_iter().skip(1) {
+            let (listener, address) = bind(&address).await
+                .map_err(|e| format!("Listener {} failed to bind {}: {}", name, address, e))?;
+            let scheme = if matches!(listener, Bound::Tcp(_)) { scheme } else { "" };
+            log_sender.send(format!("Server listening on {}{} ({})", scheme, address, name).into()).ok();
+            listeners.push((name.clone(), address));
+            bound.push((name, listener));
+        }
+
+        let mut redirect = None;
+        let mut certificate_file = None;
+        if let Some(tls) = &tls {
+            if let Some(pem) = tls.self_signed_certificate() {
+                let file = write_self_signed(pem, local_addr.port())?;
+                log_sender.send(format!("Using a self-signed certificate for localhost, trust it with e.g. curl --cacert {}", file.path().display()).into()).ok();
+                certificate_file = Some(file);
+            }
+            if let Some(port) = tls_config.and_then(|tls_config| tls_config.http_redirect_port) {
+                let redirect_listener = TcpListener::bind(SocketAddr::new(local_addr.ip(), port)).await?;
+                let redirect_addr = redirect_listener.local_addr()?;
+                log_sender.send(format!("Redirecting HTTP on {} to HTTPS", redirect_addr).into()).ok();
+                redirect = Some((redirect_listener, redirect_addr));
+            }
+        }
+
+        // Log configuration warnings
+        for diagnostic in validation.diagnostics.iter().filter(|d| d.severity == Severity::Warning) {
+            log_sender.send(diagnostic_event(&validation.format(diagnostic), diagnostic.severity)).ok();
+        }
+        log_endpoints(&config, &self.registry, &log_sender);
+
+        // Build the router behind a reloader so configuration changes apply without a restart
+        let watch_config = config.server.watch_config;
+        let listener_info = listeners.iter()
+            .map(|(name, address)| ListenerInfo { name: name.clone(), address: address.to_string() })
+            .collect();
+        let reloader = Arc::new(Reloader::new(self.config_file.as_deref(), config, Arc::new(self.registry), log_sender.clone(), listener_info)?);
+        let mut app = reloader.router();
+        for router in self.routers {
+            app = app.merge(router);
+        }
+        for layer in self.layers {
+            app = layer(app);
+        }
+
+        // Watch the configuration file for changes
+        if watch_config && self.config_file.is_some() {
+            tokio::spawn(reloader.clone().watch());
+        }
+        #[cfg(unix)]
+        if self.reload_on_hangup {
+            tokio::spawn(reloader.clone().watch_hangup());
+        }
+        // Watch the certificate files for renewals
+        if let Some(tls) = tls.as_ref().filter(|tls| tls.watches_files()) {
+            tokio::spawn(tls.clone().watch(log_sender.clone()));
+        }
+
+        // Every source of a shutdown stops the server through one channel
+        let (stop, stopped) = watch::channel(());
+        let stop = Arc::new(stop);
+        for signal in self.signals {
+            let stop = stop.clone();
+            tokio::spawn(async move {
+                signal.await;
+                stop.send(()).ok();
+            });
+        }
+
+        let logger = match self.log_output {
+            LogOutput::Ui => Logger::Ui(spawn_ui(self.log_receiver, &reloader, &stop)),
+            LogOutput::Print(format) => Logger::Print(HeadlessLogger::spawn(self.log_receiver, format)),
+            // Events sent through earlier `log_sender()` clones are passed on
+            LogOutput::Channel(sender) => {
+                let receiver = self.log_receiver;
+                thread::spawn(move || {
+                    while let Ok(event) = receiver.recv() {
+                        if sender.send(event).is_err() {
+                            break;
+                        }
+                    }
+                });
+                Logger::Channel
+            },
+        };
+
+        let mut servers = vec![serve_tcp(listener, app.clone(), tls.as_deref(), &log_sender, stopped.clone())?];
+        // Requests are routed to the endpoints of the listener they arrive on
+        for (name, listener) in bound {
+            let app = app.clone().layer(Extension(ListenerName(name)));
+            servers.push(match listener {
+                Bound::Tcp(listener) => serve_tcp(listener, app, tls.as_deref(), &log_sender, stopped.clone())?,
+                #[cfg(unix)]
+                Bound::Unix(listener, path) => serve_unix(listener, path, app, stopped.clone()),
+            });
+        }
+        let redirect_addr = redirect.as_ref().map(|(_, addr)| *addr);
+        if let Some((redirect_listener, _)) = redirect {
+            let mut stopped = stopped.clone();
+            let router = redirect_router(local_addr.port());
+            servers.push(tokio::spawn(async move {
+                axum::serve(redirect_listener, router)
+                    .with_graceful_shutdown(async move {
+                        stopped.changed().await.ok();
+                    })
+                    .await
+            }));
+        }
+
+        Ok(ServerHandle { local_addr, listeners, redirect_addr, tls, certificate_file, reloader, stop, servers, logger, log_sender })
+    }
+
+    // Diagnostics located in the configuration file when the configuration is the file's
+    fn validation(&self) -> Validation {
+        if let Some(path) = &self.config_file {
+            if let Ok(validation) = validate_file(path, &self.registry) {
+                if validation.config.as_ref() == Some(&self.config) {
+                    return validation;
+                }
+            }
+        }
+        validate_config(self.config_file.as_deref().unwrap_or("configuration"), &self.config, &self.registry)
+    }
+}
+
+impl Default for ServerBuilder {
+    /// A server on 127.0.0.1:3000 without endpoints, add them with [`ServerBuilder::endpoint`]
+    fn default() -> Self {
+        Self::new(Config::default())
+    }
+}
+
+/// A running server
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    listeners: Vec<(String, ListenAddress)>,
+    redirect_addr: Option<SocketAddr>,
+    tls: Option<Arc<Tls>>,
+    // The self-signed certificate written for clients, removed when the server stops
+    certificate_file: Option<CertificateFile>,
+    reloader: Arc<Reloader>,
+    stop: Arc<watch::Sender<()>>,
+    servers: Vec<Serving>,
+    logger: Logger,
+    log_sender: mpsc::Sender<LogEvent>,
+}
+
+enum Logger {
+    Ui(JoinHandle<()>),
+    Print(HeadlessLogger),
+    Channel,
+}
+
+impl ServerHandle {
+    /// Address the server is listening on, with the actual port when the configured port is 0
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Name and address of every listener, the one on the configured host and port first
+    pub fn listeners(&self) -> &[(String, ListenAddress)] {
+        &self.listeners
+    }
+
+    /// Address of the listener redirecting plain HTTP to HTTPS, if one is configured
+    pub fn redirect_addr(&self) -> Option<SocketAddr> {
+        self.redirect_addr
+    }
+
+    /// Whether the server serves HTTPS
+    pub fn is_tls(&self) -> bool {
+        self.tls.is_some()
+    }
+
+    /// PEM of the certificate generated at startup, for clients to trust
+    pub fn self_signed_certificate(&self) -> Option<&str> {
+        self.tls.as_ref().and_then(|tls| tls.self_signed_certificate())
+    }
+
+    /// File the certificate generated at startup was written to, removed when the server stops
+    pub fn self_signed_certificate_file(&self) -> Option<&Path> {
+        self.certificate_file.as_ref().map(CertificateFile::path)
+    }
+
+    /// Reload the configuration file, returning false and keeping the current configuration on failure
+    pub async fn reload(&self) -> bool {
+        self.reloader.reload().await
+    }
+
+    /// Serve a new configuration, returning false and keeping the current one if it is invalid
+    pub async fn apply_config(&self, config: Config) -> bool {
+        self.reloader.apply(config).await
+    }
+
+    /// OpenAPI document of the endpoints served on the default listener to hosts no endpoint is scoped to
+    ///
+    /// Endpoints scoped to a listener or virtual host are documented by [`ServerHandle::site_openapi`].
+    pub fn openapi(&self) -> serde_json::Value {
+        self.reloader.openapi(DEFAULT_LISTENER, None)
+    }
+
+    /// OpenAPI document of the endpoints served on a listener for a host, as `openapi_path` serves it
+    pub fn site_openapi(&self, listener: &str, host: Option<&str>) -> serde_json::Value {
+        self.reloader.openapi(listener, host)
+    }
+
+    /// Switch fault injection of an endpoint on or off, as the terminal UI does, returning whether
+    /// it is now on, None if the endpoint has no faults configured
+    ///
+    /// Endpoints scoped to a listener or virtual host are toggled with [`ServerHandle::toggle_endpoint_faults`].
+    pub fn toggle_faults(&self, method: &str, path: &str) -> Option<bool> {
+        self.reloader.toggle_faults(&format!("{} {}", method, path))
+    }
+
+    /// Switch fault injection of the endpoint with a key on or off, e.g. `GET /users on admin for api.local`
+    pub fn toggle_endpoint_faults(&self, key: &str) -> Option<bool> {
+        self.reloader.toggle_faults(key)
+    }
+
+    /// Stop accepting connections and wait for the open ones to finish
+    pub async fn shutdown(self) -> Result<(), Box<dyn std::error::Error>> {
+        self.stop.send(()).ok();
+        self.wait().await
+    }
+
+    /// Wait until the server stops, with the terminal UI also until the UI is closed
+    pub async fn wait(self) -> Result<(), Box<dyn std::error::Error>> {
+        for server in self.servers {
+            server.await??;
+        }
+        self.log_sender.send("Server stopped".into()).ok();
+
+        match self.logger {
+            Logger::Ui(thread) => {
+                thread.join().ok();
+            },
+            Logger::Print(logger) => logger.finish(),
+            Logger::Channel => {},
+        }
+        Ok(())
+    }
+}
+
+// A listener bound in addition to the one on the configured host and port
+enum Bound {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixListener, String),
+}
+
+// Bind a listener, returning it with its actual address
+async fn bind(address: &ListenAddress) -> std::io::Result<(Bound, ListenAddress)> {
+    match address {
+        ListenAddress::Tcp(address) => {
+            let listener = TcpListener::bind(address).await?;
+            let address = listener.local_addr()?;
+            Ok((Bound::Tcp(listener), ListenAddress::Tcp(address)))
+        },
+        #[cfg(unix)]
+        ListenAddress::Unix(path) => {
+            // A socket left behind by a server that didn't shut down cleanly would fail the bind
+            use std::os::unix::fs::FileTypeExt;
+            if std::fs::metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+                std::fs::remove_file(path)?;
+            }
+            Ok((Bound::Unix(tokio::net::UnixListener::bind(path)?, path.clone()), address.clone()))
+        },
+        #[cfg(not(unix))]
+        ListenAddress::Unix(_) => Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "Unix domain sockets are not supported on this platform")),
+    }
+}
+
+// Serve a TCP listener until the server stops, over TLS if the server has it
+fn serve_tcp(
+    listener: TcpListener,
+    app: Router,
+    tls: Option<&Tls>,
+    log_sender: &mpsc::Sender<LogEvent>,
+    mut stopped: watch::Receiver<()>,
+) -> std::io::Result<Serving> {
+    let app = match tls {
+        Some(_) => app.layer(Extension(ListenerScheme("https"))),
+        None => app,
+    };
+    let make_service = app.into_make_service_with_connect_info::<SocketAddr>();
+    let shutdown = async move {
+        stopped.changed().await.ok();
+    };
+    Ok(match tls {
+        // Tapping the listener keeps the client addresses available to the handlers
+        Some(tls) => {
+            let listener = TlsListener::new(listener, tls, log_sender.clone())?.tap_io(|_| {});
+            tokio::spawn(async move {
+                axum::serve(listener, make_service).with_graceful_shutdown(shutdown).await
+            })
+        },
+        None => tokio::spawn(async move {
+            axum::serve(listener, make_service).with_graceful_shutdown(shutdown).await
+        }),
+    })
+}
+
+// Serve a Unix domain socket until the server stops, then remove it
+#[cfg(unix)]
+fn serve_unix(listener: tokio::net::UnixListener, path: String, app: Router, mut stopped: watch::Receiver<()>) -> Serving {
+    tokio::spawn(async move {
+        let result = axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                stopped.changed().await.ok();
+            })
+            .await;
+        std::fs::remove_file(&path).ok();
+        result
+    })
+}
+
+// Run the terminal UI, it reloads the configuration on request and stops the server when closed
+fn spawn_ui(log_receiver: mpsc::Receiver<LogEvent>, reloader: &Arc<Reloader>, stop: &Arc<watch::Sender<()>>) -> JoinHandle<()> {
+    let (command_tx, command_rx) = mpsc::channel::<UiCommand>();
+    let command_reloader = reloader.clone();
+    thread::spawn(move || {
+        while let Ok(command) = command_rx.recv() {
+            match command {
+                UiCommand::ReloadConfig => {
+                    command_reloader.reload_blocking();
+                },
+                UiCommand::ToggleFaults { endpoint } => {
+                    command_reloader.toggle_faults(&endpoint);
+                },
+            }
+        }
+    });
+
+    // Keep the endpoint statistics in the UI up to date
+    tokio::spawn(reloader.clone().publish_stats());
+
+    let (ui_shutdown_tx, mut ui_shutdown_rx) = watch::channel::<()>(());
+    let stop = stop.clone();
+    tokio::spawn(async move {
+        // The UI also stops the server when it exits without asking to
+        ui_shutdown_rx.changed().await.ok();
+        stop.send(()).ok();
+    });
+
+    let server_info_rx = reloader.server_info();
+    thread::spawn(move || {
+        run_ui(log_receiver, ui_shutdown_tx, server_info_rx, command_tx);
+    })
+}
+
+// Log the configured endpoints and how each is served
+fn log_endpoints(config: &Config, registry: &HandlerRegistry, log_sender: &mpsc::Sender<LogEvent>) {
+    for endpoint in &config.endpoints {
+        log_sender.send(format!("Configured endpoint: {} {} - {}",
+            endpoint.method,
+            endpoint.path,
+            endpoint.description).into()).ok();
+
+        // Log whether the endpoint uses a custom handler or the default
+        let handler = if endpoint.handler == PROXY_HANDLER {
+            let upstream = endpoint.params.get("upstream").map(String::as_str).unwrap_or_default();
+            format!("  Proxying to {}", upstream)
+        } else if endpoint.handler == REPLAY_HANDLER {
+            format!("  Replaying {} recording(s)", endpoint.recordings.len())
+        } else if registry.contains(&endpoint.handler) {
+            format!("  Using custom handler: {}", endpoint.handler)
+        } else {
+            "  Using default handler with static response".to_string()
+        };
+        log_sender.send(handler.into()).ok();
+    }
+}
@@ -0,0 +1,201 @@
+use std::net::SocketAddr;
+use std::sync::{mpsc, Mutex};
+
+use axum::body::{Body, Bytes};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue, Method, Request, StatusCode};
+use axum_handlers::{register_default_handlers, HandlerRegistry};
+use axum_tui::LogEvent;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::server::{LogOutput, ServerBuilder, ServerHandle};
+
+/// A server for tests, listening on a free port of 127.0.0.1 without the terminal UI
+///
+/// Log events are captured and can be inspected with [`TestServer::logs`]. The client
+/// methods panic when the server can't be reached, as a test would fail anyway.
+pub struct TestServer {
+    handle: ServerHandle,
+    client: Client<HttpConnector, Body>,
+    log_receiver: Mutex<mpsc::Receiver<LogEvent>>,
+    logs: Mutex<Vec<LogEvent>>,
+}
+
+impl TestServer {
+    /// Serve a YAML configuration with the default handlers
+    pub async fn from_yaml(yaml: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut registry = HandlerRegistry::new();
+        register_default_handlers(&mut registry);
+        Self::with_handlers(yaml, registry).await
+    }
+
+    /// Serve a YAML configuration with the handlers of a registry
+    pub async fn with_handlers(yaml: &str, registry: HandlerRegistry) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = Config::from_yaml(yaml)?;
+        Self::start(ServerBuilder::new(config).handlers(registry)).await
+    }
+
+    /// Serve the configuration of a builder, its host and port are replaced
+    pub async fn start(mut builder: ServerBuilder) -> Result<Self, Box<dyn std::error::Error>> {
+        let server = &mut builder.config_mut().server;
+        server.host = "127.0.0.1".to_string();
+        server.port = 0;
+        server.watch_config = false;
+
+        let (log_sender, log_receiver) = mpsc::channel();
+        let handle = builder
+            .log_output(LogOutput::Channel(log_sender))
+            .start()
+            .await?;
+
+        Ok(Self {
+            handle,
+            client: Client::builder(TokioExecutor::new()).build_http(),
+            log_receiver: Mutex::new(log_receiver),
+            logs: Mutex::default(),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.handle.local_addr()
+    }
+
+    /// URL of a path on the server, e.g. `http://127.0.0.1:41234/api/users`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.local_addr(), path)
+    }
+
+    /// The running server, e.g. to apply another configuration
+    pub fn handle(&self) -> &ServerHandle {
+        &self.handle
+    }
+
+    /// Every log event so far
+    pub fn logs(&self) -> Vec<LogEvent> {
+        let mut logs = self.logs.lock().unwrap();
+        logs.extend(self.log_receiver.lock().unwrap().try_iter());
+        logs.clone()
+    }
+
+    /// Log events of the requests served so far
+    pub fn requests(&self) -> Vec<LogEvent> {
+        self.logs().into_iter().filter(|event| event.status.is_some()).collect()
+    }
+
+    pub fn get(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::POST, path)
+    }
+
+    pub fn put(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::PUT, path)
+    }
+
+    pub fn patch(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: &str) -> TestRequest<'_> {
+        self.request(Method::DELETE, path)
+    }
+
+    pub fn request(&self, method: Method, path: &str) -> TestRequest<'_> {
+        TestRequest {
+            server: self,
+            method,
+            path: path.to_string(),
+            headers: HeaderMap::new(),
+            body: Bytes::new(),
+        }
+    }
+
+    /// Stop the server and wait for open connections to finish
+    pub async fn shutdown(self) -> Result<(), Box<dyn std::error::Error>> {
+        self.handle.shutdown().await
+    }
+}
+
+/// A request to a [`TestServer`], sent with [`TestRequest::send`]
+pub struct TestRequest<'a> {
+    server: &'a TestServer,
+    method: Method,
+    path: String,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl TestRequest<'_> {
+    /// Add a header, panics if the name or value is invalid
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        let name = HeaderName::from_bytes(name.as_bytes()).unwrap_or_else(|_| panic!("invalid header name `{}`", name));
+        let value = HeaderValue::from_str(value).unwrap_or_else(|_| panic!("invalid value for header `{}`", name));
+        self.headers.append(name, value);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Send a value as JSON, setting the content type
+    pub fn json<T: Serialize>(mut self, value: &T) -> Self {
+        self.body = serde_json::to_vec(value).expect("value can't be serialized as JSON").into();
+        self.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        self
+    }
+
+    pub async fn send(self) -> TestResponse {
+        let mut request = Request::builder()
+            .method(self.method)
+            .uri(self.server.url(&self.path))
+            .body(Body::from(self.body))
+            .unwrap_or_else(|e| panic!("invalid request for `{}`: {}", self.path, e));
+        request.headers_mut().extend(self.headers);
+
+        let response = self.server.client.request(request).await
+            .unwrap_or_else(|e| panic!("request to `{}` failed: {}", self.path, e));
+        let (parts, body) = response.into_parts();
+        let body = axum::body::to_bytes(Body::new(body), usize::MAX).await
+            .unwrap_or_else(|e| panic!("failed to read the response of `{}`: {}", self.path, e));
+
+        TestResponse {
+            status: parts.status,
+            headers: parts.headers,
+            body,
+        }
+    }
+}
+
+/// A response received by a [`TestRequest`]
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl TestResponse {
+    /// Value of a header, None if it is missing or not text
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    /// The body as text, non-UTF-8 bytes are replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// The body parsed as JSON, panics showing the body if it isn't
+    pub fn json<T: DeserializeOwned>(&self) -> T {
+        serde_json::from_slice(&self.body)
+            .unwrap_or_else(|e| panic!("response body is not the expected JSON ({}): {}", e, self.text()))
+    }
+}
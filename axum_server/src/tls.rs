@@ -0,0 +1,300 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::serve::Listener;
+use axum::Router;
+use axum_tui::LogEvent;
+use rustls::crypto::ring::{default_provider, sign::any_supported_type};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::TlsConfig;
+
+/// How often the certificate files are checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a client may take to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Names a generated certificate is valid for
+const SELF_SIGNED_NAMES: [&str; 3] = ["localhost", "127.0.0.1", "::1"];
+
+/// The certificate the server presents, replaced when its files change
+pub struct Tls {
+    config: TlsConfig,
+    resolver: Arc<CertResolver>,
+    acceptor: TlsAcceptor,
+    /// PEM of the certificate when it was generated at startup
+    self_signed: Option<String>,
+}
+
+#[derive(Debug)]
+struct CertResolver {
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.key.read().unwrap().clone())
+    }
+}
+
+impl Tls {
+    /// TLS with the configured certificate, or a generated one for localhost if `self_signed` is set
+    pub fn new(config: &TlsConfig, self_signed: bool) -> Result<Self, String> {
+        let (key, pem) = if self_signed {
+            let (key, pem) = generate_self_signed()?;
+            (key, Some(pem))
+        } else {
+            match (&config.cert_file, &config.key_file) {
+                (Some(cert_file), Some(key_file)) => (load_certificate(cert_file, key_file)?, None),
+                _ => return Err("`cert_file` and `key_file` are required unless a self-signed certificate is generated".to_string()),
+            }
+        };
+
+        let resolver = Arc::new(CertResolver { key: RwLock::new(Arc::new(key)) });
+        let mut server_config = rustls::ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .with_no_client_auth()
+            .with_cert_resolver(resolver.clone());
+        server_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+        Ok(Self {
+            config: config.clone(),
+            resolver,
+            acceptor: TlsAcceptor::from(Arc::new(server_config)),
+            self_signed: pem,
+        })
+    }
+
+    /// PEM of the generated certificate, None if it was read from files
+    pub fn self_signed_certificate(&self) -> Option<&str> {
+        self.self_signed.as_deref()
+    }
+
+    /// Whether the certificate files are watched for changes
+    pub fn watches_files(&self) -> bool {
+        self.self_signed.is_none() && self.config.watch
+    }
+
+    /// Read the certificate files again, keeping the current certificate on failure
+    pub fn reload(&self) -> Result<(), String> {
+        let (Some(cert_file), Some(key_file)) = (&self.config.cert_file, &self.config.key_file) else {
+            return Err("no certificate files configured".to_string());
+        };
+        let key = load_certificate(cert_file, key_file)?;
+        *self.resolver.key.write().unwrap() = Arc::new(key);
+        Ok(())
+    }
+
+    /// Reload the certificate when its files change
+    pub async fn watch(self: Arc<Self>, log_sender: mpsc::Sender<LogEvent>) {
+        let (Some(cert_file), Some(key_file)) = (self.config.cert_file.clone(), self.config.key_file.clone()) else {
+            return;
+        };
+        let modified = || (modified_time(&cert_file), modified_time(&key_file));
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        let mut loaded = modified();
+        let mut pending = None;
+
+        loop {
+            interval.tick().await;
+            let modified = modified();
+            if modified == loaded {
+                pending = None;
+                continue;
+            }
+
+            // Wait until both files have stopped changing, certificate and key are often renewed one after the other
+            if pending == Some(modified) {
+                loaded = modified;
+                pending = None;
+                let event = match self.reload() {
+                    Ok(()) => LogEvent::from(format!("TLS certificate reloaded from {}", cert_file)),
+                    Err(e) => LogEvent::error(format!("Failed to reload TLS certificate, keeping the current one: {}", e)),
+                };
+                log_sender.send(event).ok();
+            } else {
+                pending = Some(modified);
+            }
+        }
+    }
+}
+
+/// Connections of a TCP listener after their TLS handshake
+///
+/// Handshakes run concurrently so a slow client doesn't hold up the others, failed ones are logged
+/// as warnings and the connection dropped.
+pub struct TlsListener {
+    connections: tokio::sync::mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, tls: &Tls, log_sender: mpsc::Sender<LogEvent>) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, connections) = tokio::sync::mpsc::channel(64);
+        tokio::spawn(accept_connections(listener, tls.acceptor.clone(), sender, log_sender));
+        Ok(Self { connections, local_addr })
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+// Accept connections and hand them over after their handshake, until the listener is dropped
+async fn accept_connections(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    sender: tokio::sync::mpsc::Sender<(TlsStream<TcpStream>, SocketAddr)>,
+    log_sender: mpsc::Sender<LogEvent>,
+) {
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                // Usually too many open files, which resolves itself as connections close
+                Err(_) => {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    continue;
+                },
+            },
+            _ = sender.closed() => return,
+        };
+
+        let (acceptor, sender, log_sender) = (acceptor.clone(), sender.clone(), log_sender.clone());
+        tokio::spawn(async move {
+            let error = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => {
+                    sender.send((stream, addr)).await.ok();
+                    return;
+                },
+                Ok(Err(e)) => e.to_string(),
+                Err(_) => "timed out".to_string(),
+            };
+            log_sender.send(LogEvent::warn(format!("TLS handshake with {} failed: {}", addr, error))).ok();
+        });
+    }
+}
+
+/// Router of the plain HTTP listener, redirecting every request to the same URL on the HTTPS port
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |request: Request| async move { redirect_to_https(&request, https_port) })
+}
+
+fn redirect_to_https(request: &Request, https_port: u16) -> Response {
+    let Some(host) = request.headers().get(header::HOST).and_then(|host| host.to_str().ok()) else {
+        return (StatusCode::BAD_REQUEST, "Bad Request: missing Host header").into_response();
+    };
+    // Drop the port of the HTTP listener, keeping the brackets of IPv6 addresses
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    let path = request.uri().path_and_query().map(|path| path.as_str()).unwrap_or("/");
+    let location = if https_port == 443 {
+        format!("https://{}{}", host, path)
+    } else {
+        format!("https://{}:{}{}", host, https_port, path)
+    };
+    Redirect::permanent(&location).into_response()
+}
+
+/// Check that a certificate and its key can be loaded and belong together
+pub fn check_certificate(cert_file: &str, key_file: &str) -> Result<(), String> {
+    load_certificate(cert_file, key_file).map(|_| ())
+}
+
+fn load_certificate(cert_file: &str, key_file: &str) -> Result<CertifiedKey, String> {
+    let certs = CertificateDer::pem_file_iter(cert_file)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("cannot read certificates from `{}`: {}", cert_file, e))?;
+    if certs.is_empty() {
+        return Err(format!("no certificates in `{}`", cert_file));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_file)
+        .map_err(|e| format!("cannot read private key from `{}`: {}", key_file, e))?;
+    let key = any_supported_type(&key)
+        .map_err(|e| format!("unsupported private key in `{}`: {}", key_file, e))?;
+
+    let certified = CertifiedKey::new(certs, key);
+    certified.keys_match()
+        .map_err(|_| format!("the private key in `{}` doesn't belong to the certificate in `{}`", key_file, cert_file))?;
+    Ok(certified)
+}
+
+/// Write a generated certificate to a new file in the temp directory, named per run so that a
+/// file or symlink someone else left at the path is never written through, and only readable by
+/// the current user
+///
+/// The file is removed when the returned [`CertificateFile`] is dropped, with the server's handle.
+pub fn write_self_signed(pem: &str, port: u16) -> io::Result<CertificateFile> {
+    let path = std::env::temp_dir().join(format!("axum_server-{}-{:016x}.pem", port, rand::random::<u64>()));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut writer = options.open(&path)?;
+    // Removed again if it can't be written
+    let file = CertificateFile(path);
+    writer.write_all(pem.as_bytes())?;
+    Ok(file)
+}
+
+/// A certificate written for clients to trust, removed on drop
+pub struct CertificateFile(PathBuf);
+
+impl CertificateFile {
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for CertificateFile {
+    fn drop(&mut self) {
+        std::fs::remove_file(&self.0).ok();
+    }
+}
+
+// A certificate for localhost signed by its own key, with its PEM for clients to trust
+fn generate_self_signed() -> Result<(CertifiedKey, String), String> {
+    let names: Vec<String> = SELF_SIGNED_NAMES.iter().map(|name| name.to_string()).collect();
+    let generated = rcgen::generate_simple_self_signed(names)
+        .map_err(|e| format!("cannot generate a self-signed certificate: {}", e))?;
+    let key = PrivateKeyDer::try_from(generated.key_pair.serialize_der())?;
+    let key = any_supported_type(&key).map_err(|e| e.to_string())?;
+    Ok((CertifiedKey::new(vec![generated.cert.der().clone()], key), generated.cert.pem()))
+}
+
+fn modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
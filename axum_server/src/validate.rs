@@ -0,0 +1,909 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
+
+use axum::http::{HeaderName, Method};
+use axum_handlers::{template, HandlerRegistry};
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::Marker;
+
+use crate::config::{Check, Config, EndpointConfig, EndpointMethod, ServerConfig, Site, DEFAULT_LISTENER};
+use crate::auth::Auth;
+use crate::cors::Cors;
+use crate::faults::FaultInjector;
+use crate::proxy::{Proxy, PROXY_HANDLER};
+use crate::rate_limit::Limiter;
+use crate::tls::check_certificate;
+use crate::replay::{Replay, REPLAY_HANDLER};
+use crate::routes::SERVER_HANDLERS;
+
+/// Severity of a configuration problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Position in a configuration file, both 1-based
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A problem found in a configuration
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Path of the offending field, e.g. `endpoints[2].method`
+    pub field: String,
+    pub message: String,
+    pub location: Option<Location>,
+}
+
+impl Diagnostic {
+    fn error(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            field: field.into(),
+            message: message.into(),
+            location: None,
+        }
+    }
+
+    fn warning(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(field, message)
+        }
+    }
+}
+
+/// Result of validating a configuration file
+pub struct Validation {
+    /// Name of the validated file
+    pub file: String,
+    /// The parsed configuration, if the file could be deserialized
+    pub config: Option<Config>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Validation {
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Error).count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.diagnostics.iter().filter(|d| d.severity == Severity::Warning).count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    /// Format a diagnostic as `file:line:column: severity: field: message`
+    pub fn format(&self, diagnostic: &Diagnostic) -> String {
+        let position = match diagnostic.location {
+            Some(location) => format!("{}:{}:{}", self.file, location.line, location.column),
+            None => self.file.clone(),
+        };
+        if diagnostic.field.is_empty() {
+            format!("{}: {}: {}", position, diagnostic.severity, diagnostic.message)
+        } else {
+            format!("{}: {}: {}: {}", position, diagnostic.severity, diagnostic.field, diagnostic.message)
+        }
+    }
+
+    /// One-line summary of the number of problems
+    pub fn summary(&self) -> String {
+        format!("{}: {} error(s), {} warning(s)", self.file, self.error_count(), self.warning_count())
+    }
+}
+
+/// Read and validate a configuration file
+pub fn validate_file(path: &str, registry: &HandlerRegistry) -> std::io::Result<Validation> {
+    let source = std::fs::read_to_string(path)?;
+    // Fixture files are relative to the configuration file, wherever the server is started from
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    Ok(validate_source(path, &source, dir, registry))
+}
+
+/// Validate configuration source, reporting every problem that can be found
+///
+/// Fixture files are relative to the working directory, see [`validate_file`] for a configuration file's.
+pub fn validate_str(file: &str, source: &str, registry: &HandlerRegistry) -> Validation {
+    validate_source(file, source, Path::new(""), registry)
+}
+
+fn validate_source(file: &str, source: &str, dir: &Path, registry: &HandlerRegistry) -> Validation {
+    let mut validation = Validation {
+        file: file.to_string(),
+        config: None,
+        diagnostics: Vec::new(),
+    };
+
+    // Syntax errors stop validation, there is nothing further to inspect
+    let root: Value = match serde_yaml::from_str(source) {
+        Ok(root) => root,
+        Err(e) => {
+            validation.diagnostics.push(Diagnostic {
+                location: yaml_location(&e),
+                ..Diagnostic::error("", yaml_message(&e))
+            });
+            return validation;
+        }
+    };
+    let spans = Spans::collect(source);
+
+    match Config::from_yaml(source) {
+        Ok(mut config) => {
+            config.resolve_fixtures(dir);
+            validation.diagnostics = config.validate(registry);
+            validation.config = Some(config);
+        }
+        Err(e) => {
+            // Check each section on its own so that every broken endpoint is reported
+            let (diagnostics, server, mut endpoints) = check_sections(source, &root, &spans);
+            if diagnostics.is_empty() {
+                validation.diagnostics.push(Diagnostic {
+                    location: yaml_location(&e),
+                    ..Diagnostic::error("", yaml_message(&e))
+                });
+            } else {
+                validation.diagnostics = diagnostics;
+            }
+
+            // Sections that did parse still get the semantic checks
+            for (_, endpoint) in &mut endpoints {
+                endpoint.resolve_fixtures(dir);
+            }
+            let endpoints: Vec<(usize, &EndpointConfig)> = endpoints.iter().map(|(i, e)| (*i, e)).collect();
+            if let Some(server) = &server {
+                validation.diagnostics.extend(check_server(server));
+                validation.diagnostics.extend(check_scopes(server, &endpoints));
+            }
+            let reserved = server.as_ref().map(reserved_routes).unwrap_or_default();
+            let server = server.clone().unwrap_or_default();
+            validation.diagnostics.extend(check_endpoints(&server, &reserved, &endpoints, registry));
+        }
+    }
+
+    for diagnostic in &mut validation.diagnostics {
+        if diagnostic.location.is_none() {
+            diagnostic.location = spans.locate(&diagnostic.field);
+        }
+    }
+    validation.diagnostics.sort_by_key(|d| d.location.map_or((usize::MAX, 0), |l| (l.line, l.column)));
+
+    validation
+}
+
+/// Validate a configuration that wasn't read from a file, `name` stands in for the file name
+pub fn validate_config(name: &str, config: &Config, registry: &HandlerRegistry) -> Validation {
+    Validation {
+        file: name.to_string(),
+        config: Some(config.clone()),
+        diagnostics: check_config(config, registry),
+    }
+}
+
+/// Run the semantic checks on a parsed configuration
+pub(crate) fn check_config(config: &Config, registry: &HandlerRegistry) -> Vec<Diagnostic> {
+    let mut diagnostics = check_server(&config.server);
+    let reserved = reserved_routes(&config.server);
+    let endpoints: Vec<(usize, &EndpointConfig)> = config.endpoints.iter().enumerate().collect();
+    diagnostics.extend(check_scopes(&config.server, &endpoints));
+    diagnostics.extend(check_endpoints(&config.server, &reserved, &endpoints, registry));
+    diagnostics
+}
+
+// Routes the server adds on its own, as (field, path) pairs
+fn reserved_routes(server: &ServerConfig) -> Vec<(&'static str, &str)> {
+    let mut routes = Vec::new();
+    if let Some(path) = &server.reload_path {
+        routes.push(("server.reload_path", path.as_str()));
+    }
+    if let Some(path) = &server.metrics_path {
+        routes.push(("server.metrics_path", path.as_str()));
+    }
+    if let Some(path) = &server.openapi_path {
+        routes.push(("server.openapi_path", path.as_str()));
+    }
+    if let Some(path) = &server.docs_path {
+        routes.push(("server.docs_path", path.as_str()));
+    }
+    routes
+}
+
+fn check_server(server: &ServerConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if IpAddr::from_str(&server.host).is_err() {
+        diagnostics.push(Diagnostic::error(
+            "server.host",
+            format!("`{}` is not an IP address, use e.g. 127.0.0.1 or 0.0.0.0", server.host),
+        ));
+    }
+    if let Some(params) = &server.fallback_proxy {
+        if let Err(message) = Proxy::from_params(params) {
+            diagnostics.push(Diagnostic::error("server.fallback_proxy", message));
+        }
+    }
+    if let Some(cors) = &server.cors {
+        if let Err(message) = Cors::new(cors) {
+            diagnostics.push(Diagnostic::error("server.cors", message));
+        }
+    }
+    if let Some(auth) = &server.auth {
+        if let Err(message) = Auth::new(auth) {
+            diagnostics.push(Diagnostic::error("server.auth", message));
+        }
+    }
+    if let Some(rate_limit) = &server.rate_limit {
+        if let Err(message) = Limiter::new(rate_limit) {
+            diagnostics.push(Diagnostic::error("server.rate_limit", message));
+        }
+    }
+    if let Some(tls) = &server.tls {
+        match (&tls.cert_file, &tls.key_file) {
+            (Some(cert_file), Some(key_file)) => {
+                if let Err(message) = check_certificate(cert_file, key_file) {
+                    diagnostics.push(Diagnostic::error("server.tls", message));
+                }
+            },
+            (None, None) => {},
+            _ => diagnostics.push(Diagnostic::error("server.tls", "`cert_file` and `key_file` must be set together")),
+        }
+        if tls.http_redirect_port.is_some_and(|port| port == server.port && port != 0) {
+            diagnostics.push(Diagnostic::error(
+                "server.tls.http_redirect_port",
+                format!("port {} is already the HTTPS port", server.port),
+            ));
+        }
+    }
+    diagnostics.extend(check_listeners(server));
+    diagnostics
+}
+
+// Listeners need unique names and addresses of their own
+fn check_listeners(server: &ServerConfig) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut ports = vec![("server.port".to_string(), server.port)];
+    if let Some(port) = server.tls.as_ref().and_then(|tls| tls.http_redirect_port) {
+        ports.push(("server.tls.http_redirect_port".to_string(), port));
+    }
+    let mut sockets: Vec<(String, &str)> = Vec::new();
+
+    for (index, listener) in server.listeners.iter().enumerate() {
+        let field = |name: &str| format!("server.listeners[{}].{}", index, name);
+        if listener.name.trim().is_empty() {
+            diagnostics.push(Diagnostic::error(field("name"), "listener name must not be empty"));
+        } else if listener.name == DEFAULT_LISTENER {
+            diagnostics.push(Diagnostic::error(
+                field("name"),
+                format!("`{}` is the name of the listener on `server.host` and `server.port`", DEFAULT_LISTENER),
+            ));
+        } else if let Some(other) = server.listeners[..index].iter().position(|other| other.name == listener.name) {
+            diagnostics.push(Diagnostic::error(
+                field("name"),
+                format!("`{}` is already the name of server.listeners[{}]", listener.name, other),
+            ));
+        }
+
+        match listener.address(server) {
+            Err(message) => diagnostics.push(Diagnostic::error(format!("server.listeners[{}]", index), message)),
+            // Port 0 picks a free port on each bind, so it never clashes
+            Ok(_) => match (&listener.unix, listener.port) {
+                (None, Some(port)) if port != 0 => {
+                    if let Some((owner, _)) = ports.iter().find(|(_, other)| *other == port) {
+                        diagnostics.push(Diagnostic::error(field("port"), format!("port {} is already used by {}", port, owner)));
+                    }
+                    ports.push((field("port"), port));
+                },
+                (Some(path), _) => {
+                    if let Some((owner, _)) = sockets.iter().find(|(_, other)| *other == path) {
+                        diagnostics.push(Diagnostic::error(field("unix"), format!("`{}` is already used by {}", path, owner)));
+                    }
+                    sockets.push((field("unix"), path));
+                },
+                _ => {},
+            },
+        }
+    }
+    diagnostics
+}
+
+// Endpoints must be scoped to configured listeners and to hosts as they appear without a port
+fn check_scopes(server: &ServerConfig, endpoints: &[(usize, &EndpointConfig)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    for &(index, endpoint) in endpoints {
+        if let Some(listener) = &endpoint.listener {
+            if listener != DEFAULT_LISTENER && !server.listeners.iter().any(|other| other.name == *listener) {
+                diagnostics.push(Diagnostic::error(
+                    format!("endpoints[{}].listener", index),
+                    format!("no listener named `{}` in server.listeners", listener),
+                ));
+            }
+        }
+        if let Some(host) = &endpoint.host {
+            let message = if host.trim().is_empty() {
+                Some("host must not be empty".to_string())
+            } else if host.parse::<axum::http::uri::Authority>().ok().is_none_or(|authority| authority.port().is_some() || authority.host() != host) {
+                Some(format!("`{}` is not a host name, it is matched without the port, e.g. api.local", host))
+            } else {
+                None
+            };
+            if let Some(message) = message {
+                diagnostics.push(Diagnostic::error(format!("endpoints[{}].host", index), message));
+            }
+        }
+    }
+    diagnostics
+}
+
+fn check_endpoints(
+    server: &ServerConfig,
+    reserved: &[(&str, &str)],
+    endpoints: &[(usize, &EndpointConfig)],
+    registry: &HandlerRegistry,
+) -> Vec<Diagnostic> {
+    // Each listener and virtual host is routed on its own, endpoints served on several of them
+    // are only reported once
+    let configs: Vec<&EndpointConfig> = endpoints.iter().map(|&(_, endpoint)| endpoint).collect();
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for site in Site::all(&configs) {
+        let served: Vec<(usize, &EndpointConfig)> = site.endpoints(&configs).into_iter().map(|position| endpoints[position]).collect();
+        for diagnostic in check_routes(reserved, &served) {
+            if !diagnostics.iter().any(|d| d.field == diagnostic.field && d.message == diagnostic.message) {
+                diagnostics.push(diagnostic);
+            }
+        }
+    }
+
+    for &(index, endpoint) in endpoints {
+        let field = |name: &str| format!("endpoints[{}].{}", index, name);
+
+        if endpoint.handler.trim().is_empty() {
+            diagnostics.push(Diagnostic::error(field("handler"), "handler name must not be empty"));
+        } else if !registry.contains(&endpoint.handler) && !SERVER_HANDLERS.contains(&endpoint.handler.as_str()) {
+            diagnostics.push(Diagnostic::warning(
+                field("handler"),
+                format!("handler `{}` is not registered, the static response will be served", endpoint.handler),
+            ));
+        }
+
+        // Template syntax errors would otherwise only show up on the first request
+        if endpoint.handler == "template" {
+            if let Err(message) = template::check_template(&endpoint.response) {
+                diagnostics.push(Diagnostic::error(field("response"), message));
+            }
+        }
+
+        if endpoint.handler == "static_files" {
+            diagnostics.extend(check_static_files(endpoint, &field("params")));
+        }
+
+        if endpoint.handler == PROXY_HANDLER {
+            if let Err(message) = Proxy::from_params(&endpoint.params) {
+                diagnostics.push(Diagnostic::error(field("params"), message));
+            }
+        }
+
+        if endpoint.handler == REPLAY_HANDLER {
+            if endpoint.recordings.is_empty() {
+                diagnostics.push(Diagnostic::warning(field("recordings"), "no recordings, every request will get a 404"));
+            } else if let Err(message) = Replay::load(&endpoint.recordings) {
+                diagnostics.push(Diagnostic::error(field("recordings"), message));
+            }
+        } else if !endpoint.recordings.is_empty() {
+            diagnostics.push(Diagnostic::warning(
+                field("recordings"),
+                format!("recordings are only served by the replay handler, not by `{}`", endpoint.handler),
+            ));
+        }
+
+        // The policy is checked merged with the server's, problems of the server's policy are reported there
+        if let Some(cors) = &endpoint.cors {
+            let server_cors = server.cors.clone().unwrap_or_default();
+            if Cors::new(&server_cors).is_ok() {
+                if let Err(message) = Cors::new(&server_cors.overridden_by(cors)) {
+                    diagnostics.push(Diagnostic::error(field("cors"), message));
+                }
+            }
+        }
+
+        if let Some(auth) = &endpoint.auth {
+            if let Err(message) = Auth::new(auth) {
+                diagnostics.push(Diagnostic::error(field("auth"), message));
+            }
+        }
+
+        if let Some(rate_limit) = &endpoint.rate_limit {
+            if let Err(message) = Limiter::new(rate_limit) {
+                diagnostics.push(Diagnostic::error(field("rate_limit"), message));
+            }
+        }
+
+        if let Some(faults) = &endpoint.faults {
+            if let Err(message) = FaultInjector::new(faults) {
+                diagnostics.push(Diagnostic::error(field("faults"), message));
+            }
+        }
+
+        for (check, contract) in endpoint.checks.iter().enumerate() {
+            diagnostics.extend(check_contract(endpoint, contract, &field(&format!("checks[{}]", check))));
+        }
+    }
+
+    diagnostics
+}
+
+// Paths and methods of the endpoints served on one listener and virtual host
+fn check_routes(reserved: &[(&str, &str)], endpoints: &[(usize, &EndpointConfig)]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut routes = matchit::Router::new();
+    // Inserted paths and the field that owns them
+    let mut inserted: HashMap<&str, String> = HashMap::new();
+    let mut methods: Vec<(usize, &str, &EndpointMethod)> = Vec::new();
+
+    for &(field, path) in reserved {
+        if let Err(message) = check_path(path) {
+            diagnostics.push(Diagnostic::error(field, message));
+        } else if let Err(e) = routes.insert(path, ()) {
+            diagnostics.push(Diagnostic::error(field, format!("`{}`: {}", path, e)));
+        } else {
+            inserted.insert(path, field.to_string());
+        }
+    }
+
+    for &(index, endpoint) in endpoints {
+        let field = |name: &str| format!("endpoints[{}].{}", index, name);
+
+        // Paths are checked the same way axum checks them when building the router
+        if let Err(message) = check_path(&endpoint.path) {
+            diagnostics.push(Diagnostic::error(field("path"), message));
+        } else if let Some(owner) = inserted.get(endpoint.path.as_str()) {
+            if !owner.starts_with("endpoints[") {
+                diagnostics.push(Diagnostic::error(
+                    field("path"),
+                    format!("`{}` is already used by {}", endpoint.path, owner),
+                ));
+            }
+        } else {
+            match routes.insert(endpoint.path.as_str(), ()) {
+                Ok(()) => {
+                    inserted.insert(&endpoint.path, format!("endpoints[{}]", index));
+                }
+                Err(matchit::InsertError::Conflict { with }) => {
+                    let owner = inserted.get(with.as_str())
+                        .map(|owner| format!(" ({})", owner))
+                        .unwrap_or_default();
+                    diagnostics.push(Diagnostic::error(
+                        field("path"),
+                        format!("`{}` conflicts with route `{}`{}", endpoint.path, with, owner),
+                    ));
+                }
+                Err(e) => {
+                    diagnostics.push(Diagnostic::error(field("path"), format!("`{}`: {}", endpoint.path, e)));
+                }
+            }
+        }
+
+        // The same method must not be routed twice for a path
+        for &(other, path, method) in &methods {
+            if path != endpoint.path {
+                continue;
+            }
+            if let Some(duplicate) = overlapping_method(method, &endpoint.method) {
+                diagnostics.push(Diagnostic::error(
+                    field("method"),
+                    format!("{} {} is already defined by endpoints[{}]", duplicate, path, other),
+                ));
+            }
+        }
+        methods.push((index, &endpoint.path, &endpoint.method));
+    }
+
+    diagnostics
+}
+
+// Check a request of the `check` command against its endpoint
+fn check_contract(endpoint: &EndpointConfig, check: &Check, field: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if let Some(method) = &check.method {
+        match EndpointMethod::parse(method) {
+            Ok(EndpointMethod::Methods(methods)) => {
+                if let EndpointMethod::Methods(routed) = &endpoint.method {
+                    if !routed.contains(&methods[0]) {
+                        diagnostics.push(Diagnostic::warning(
+                            format!("{}.method", field),
+                            format!("the endpoint is not routed for {}", methods[0]),
+                        ));
+                    }
+                }
+            },
+            Ok(EndpointMethod::Any) => diagnostics.push(Diagnostic::error(format!("{}.method", field), "a check needs a single method, not ANY")),
+            Err(message) => diagnostics.push(Diagnostic::error(format!("{}.method", field), message)),
+        }
+    }
+
+    if let Some(path) = &check.path {
+        let request_path = path.split('?').next().unwrap_or_default();
+        let mut route = matchit::Router::new();
+        if !path.starts_with('/') {
+            diagnostics.push(Diagnostic::error(format!("{}.path", field), format!("`{}` must start with `/`", path)));
+        } else if route.insert(endpoint.path.as_str(), ()).is_ok() && route.at(request_path).is_err() {
+            diagnostics.push(Diagnostic::warning(
+                format!("{}.path", field),
+                format!("`{}` is not matched by the endpoint path `{}`", path, endpoint.path),
+            ));
+        }
+    }
+
+    let headers = check.headers.keys().map(|name| ("headers", name))
+        .chain(check.expect.headers.keys().map(|name| ("expect.headers", name)));
+    for (key, name) in headers {
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            diagnostics.push(Diagnostic::error(format!("{}.{}", field, key), format!("invalid header name `{}`", name)));
+        }
+    }
+    if let Some(status) = check.expect.status {
+        if !(100..=999).contains(&status) {
+            diagnostics.push(Diagnostic::error(format!("{}.expect.status", field), format!("{} is not a status code", status)));
+        }
+    }
+
+    diagnostics
+}
+
+// Check the params of a `static_files` endpoint
+fn check_static_files(endpoint: &EndpointConfig, field: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    match endpoint.params.get("root") {
+        None => diagnostics.push(Diagnostic::error(field, "the `root` param is required by the static_files handler")),
+        Some(root) if !Path::new(root).is_dir() => diagnostics.push(Diagnostic::warning(
+            format!("{}.root", field),
+            format!("`{}` is not a directory", root),
+        )),
+        Some(_) => {}
+    }
+
+    let param = endpoint.params.get("param").map(String::as_str).unwrap_or("path");
+    if !endpoint.path.contains(&format!("{{*{}}}", param)) && !endpoint.path.contains(&format!("{{{}}}", param)) {
+        diagnostics.push(Diagnostic::warning(
+            field,
+            format!("`{}` has no `{{*{}}}` parameter, only the root directory will be served", endpoint.path, param),
+        ));
+    }
+
+    diagnostics
+}
+
+// Mirror the path checks axum performs before panicking
+pub fn check_path(path: &str) -> Result<(), String> {
+    if !path.starts_with('/') {
+        return Err(format!("`{}` must start with `/`", path));
+    }
+    for segment in path.split('/') {
+        if segment.starts_with(':') {
+            return Err(format!("`{}`: use `{{name}}` instead of `:name` for placeholders", path));
+        }
+        if segment.starts_with('*') {
+            return Err(format!("`{}`: use `{{*name}}` instead of `*name` for wildcards", path));
+        }
+    }
+    Ok(())
+}
+
+fn overlapping_method(a: &EndpointMethod, b: &EndpointMethod) -> Option<String> {
+    match (a, b) {
+        (EndpointMethod::Any, EndpointMethod::Any) => Some("ANY".to_string()),
+        (EndpointMethod::Methods(a), EndpointMethod::Methods(b)) => {
+            a.iter().find(|method| b.contains(method)).map(Method::to_string)
+        }
+        _ => None,
+    }
+}
+
+type Sections = (Vec<Diagnostic>, Option<ServerConfig>, Vec<(usize, EndpointConfig)>);
+
+// Deserialize `server` and each endpoint separately, returning the sections that parsed
+fn check_sections(source: &str, root: &Value, spans: &Spans) -> Sections {
+    let mut diagnostics = Vec::new();
+    let mut server = None;
+    let mut endpoints = Vec::new();
+
+    let Some(root) = root.as_mapping() else {
+        diagnostics.push(Diagnostic::error("", "configuration must be a mapping with `server` and `endpoints`"));
+        return (diagnostics, server, endpoints);
+    };
+
+    match root.get("server") {
+        Some(value) => match check_section::<ServerConfig>(source, value, "server", spans) {
+            Ok(parsed) => server = Some(parsed),
+            Err(diagnostic) => diagnostics.push(diagnostic),
+        },
+        None => diagnostics.push(Diagnostic::error("", "missing `server` section")),
+    }
+
+    match root.get("endpoints") {
+        Some(Value::Sequence(items)) => {
+            for (index, item) in items.iter().enumerate() {
+                let field = format!("endpoints[{}]", index);
+                match check_section::<EndpointConfig>(source, item, &field, spans) {
+                    Ok(endpoint) => endpoints.push((index, endpoint)),
+                    Err(diagnostic) => diagnostics.push(diagnostic),
+                }
+            }
+        }
+        Some(_) => diagnostics.push(Diagnostic::error("endpoints", "`endpoints` must be a list")),
+        None => diagnostics.push(Diagnostic::error("", "missing `endpoints` section")),
+    }
+
+    (diagnostics, server, endpoints)
+}
+
+fn check_section<T: DeserializeOwned>(source: &str, value: &Value, field: &str, spans: &Spans) -> Result<T, Diagnostic> {
+    let error = match serde_yaml::from_value::<T>(value.clone()) {
+        Ok(parsed) => return Ok(parsed),
+        Err(e) => e,
+    };
+
+    // `from_value` has no positions, so re-parse the section's own source text to locate the error
+    let located = spans.get(field).and_then(|span| {
+        let error = serde_yaml::from_str::<T>(&span.text(source)).err()?;
+        let inner = error.location()?;
+        let location = Location {
+            line: span.start.line() + inner.line() - 1,
+            column: inner.column(),
+        };
+        Some((location, yaml_message(&error)))
+    });
+
+    let Some((location, message)) = located else {
+        return Err(Diagnostic::error(field, yaml_message(&error)));
+    };
+
+    // Messages are prefixed with the path of the nested field, e.g. `method: invalid HTTP method`
+    let diagnostic = match message.split_once(": ") {
+        Some((path, rest)) if !path.is_empty() && !path.contains(' ') => {
+            Diagnostic::error(format!("{}.{}", field, path), rest)
+        }
+        _ => Diagnostic::error(field, message),
+    };
+    Err(Diagnostic {
+        location: Some(location),
+        ..diagnostic
+    })
+}
+
+fn yaml_location(error: &serde_yaml::Error) -> Option<Location> {
+    error.location().map(|location| Location {
+        line: location.line(),
+        column: location.column(),
+    })
+}
+
+// serde_yaml appends ` at line L column C` to messages, which diagnostics already carry
+fn yaml_message(error: &serde_yaml::Error) -> String {
+    let message = error.to_string();
+    let Some(location) = error.location() else {
+        return message;
+    };
+    let suffix = format!(" at line {} column {}", location.line(), location.column());
+    message.replacen(&suffix, "", 1)
+}
+
+/// Source span of a YAML node
+#[derive(Clone, Copy)]
+struct Span {
+    start: Marker,
+    end: Marker,
+}
+
+impl Span {
+    // Source text of the node, indented so that columns match the original file
+    fn text(&self, source: &str) -> String {
+        let mut text = " ".repeat(self.start.col());
+        let mut chars = source.chars().skip(self.start.index());
+        text.extend(chars.by_ref().take(self.end.index() - self.start.index()));
+        // Flow collections end on their closing bracket
+        if let Some(close @ ('}' | ']')) = chars.next() {
+            text.push(close);
+        }
+        text
+    }
+}
+
+/// Positions of every node in a YAML document, keyed by field path
+#[derive(Default)]
+struct Spans {
+    spans: HashMap<String, Span>,
+    stack: Vec<Frame>,
+}
+
+enum Frame {
+    Mapping { path: String, key: Option<String>, start: Marker },
+    Sequence { path: String, index: usize, start: Marker },
+}
+
+impl Spans {
+    fn collect(source: &str) -> Self {
+        let mut spans = Spans::default();
+        // Syntax errors have already been reported by serde_yaml
+        let _ = Parser::new_from_str(source).load(&mut spans, false);
+        spans
+    }
+
+    fn get(&self, field: &str) -> Option<&Span> {
+        self.spans.get(field)
+    }
+
+    // Find the closest located node for a field path, walking up to its parents
+    fn locate(&self, field: &str) -> Option<Location> {
+        let mut field = field;
+        loop {
+            if let Some(span) = self.spans.get(field) {
+                return Some(Location {
+                    line: span.start.line(),
+                    column: span.start.col() + 1,
+                });
+            }
+            field = &field[..field.rfind(['.', '['])?];
+        }
+    }
+
+    // Path of the node that starts now, or `None` if it is a mapping key
+    fn next_path(&mut self) -> Option<String> {
+        match self.stack.last_mut() {
+            None => Some(String::new()),
+            Some(Frame::Sequence { path, index, .. }) => {
+                let child = format!("{}[{}]", path, index);
+                *index += 1;
+                Some(child)
+            }
+            Some(Frame::Mapping { path, key, .. }) => match key.take() {
+                Some(key) if path.is_empty() => Some(key),
+                Some(key) => Some(format!("{}.{}", path, key)),
+                None => None,
+            },
+        }
+    }
+
+    fn set_key(&mut self, name: String, mark: Marker) {
+        if let Some(Frame::Mapping { key, start, .. }) = self.stack.last_mut() {
+            *key = Some(name);
+            // Block mappings are marked after their first key, so move the start back to it
+            if mark.index() < start.index() {
+                *start = mark;
+            }
+        }
+    }
+}
+
+impl MarkedEventReceiver for Spans {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, ..) => match self.next_path() {
+                Some(path) => {
+                    self.spans.insert(path, Span { start: mark, end: mark });
+                }
+                None => self.set_key(value, mark),
+            },
+            Event::Alias(_) => {
+                // Aliases are not located, but still take up a key or value slot
+                let path = self.next_path();
+                if path.is_none() {
+                    self.set_key("*".to_string(), mark);
+                }
+            }
+            Event::MappingStart(..) => {
+                let path = self.next_path().unwrap_or_default();
+                self.stack.push(Frame::Mapping { path, key: None, start: mark });
+            }
+            Event::SequenceStart(..) => {
+                let path = self.next_path().unwrap_or_default();
+                self.stack.push(Frame::Sequence { path, index: 0, start: mark });
+            }
+            Event::MappingEnd | Event::SequenceEnd => {
+                if let Some(Frame::Mapping { path, start, .. } | Frame::Sequence { path, start, .. }) = self.stack.pop() {
+                    self.spans.insert(path, Span { start, end: mark });
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum_handlers::register_default_handlers;
+
+    fn validate(source: &str) -> Vec<String> {
+        let mut registry = HandlerRegistry::new();
+        register_default_handlers(&mut registry);
+        let validation = validate_str("config.yaml", source, &registry);
+        validation.diagnostics.iter().map(|diagnostic| validation.format(diagnostic)).collect()
+    }
+
+    // A configuration with the given endpoints, each `(path, method)` served by the default handler
+    fn endpoints(endpoints: &[(&str, &str)]) -> String {
+        let mut source = "server:\n  host: 127.0.0.1\n  port: 3000\nendpoints:\n".to_string();
+        for (path, method) in endpoints {
+            source.push_str(&format!(
+                "  - path: {}\n    method: {}\n    handler: default\n    response: \"\"\n    description: \"\"\n",
+                path, method,
+            ));
+        }
+        source
+    }
+
+    #[test]
+    fn accepts_valid_configurations() {
+        assert_eq!(validate(&endpoints(&[("/users", "GET"), ("/users/{id}", "[GET, PUT]"), ("/users", "ANY")])), Vec::<String>::new());
+    }
+
+    #[test]
+    fn locates_invalid_methods() {
+        let diagnostics = validate(&endpoints(&[("/users", "GET"), ("/users", "\"GET POST\"")]));
+        assert_eq!(diagnostics, ["config.yaml:11:13: error: endpoints[1].method: invalid HTTP method: \"GET POST\""]);
+    }
+
+    #[test]
+    fn locates_syntax_errors() {
+        let diagnostics = validate("server:\n  host: 127.0.0.1\n  port: [3000\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("config.yaml:4:1: error: "), "{:?}", diagnostics);
+    }
+
+    #[test]
+    fn reports_conflicting_routes() {
+        let diagnostics = validate(&endpoints(&[("/users/{id}", "GET"), ("/users/{name}", "POST"), ("/users/{id}", "[PUT, GET]")]));
+        assert_eq!(diagnostics, [
+            "config.yaml:10:11: error: endpoints[1].path: `/users/{name}` conflicts with route `/users/{id}` (endpoints[0])",
+            "config.yaml:16:13: error: endpoints[2].method: GET /users/{id} is already defined by endpoints[0]",
+        ]);
+    }
+
+    #[test]
+    fn reports_colon_and_star_segments() {
+        let diagnostics = validate(&endpoints(&[("/users/:id", "GET"), ("/files/*path", "GET"), ("users", "GET")]));
+        assert_eq!(diagnostics, [
+            "config.yaml:5:11: error: endpoints[0].path: `/users/:id`: use `{name}` instead of `:name` for placeholders",
+            "config.yaml:10:11: error: endpoints[1].path: `/files/*path`: use `{*name}` instead of `*name` for wildcards",
+            "config.yaml:15:11: error: endpoints[2].path: `users` must start with `/`",
+        ]);
+    }
+
+    #[test]
+    fn reports_routes_reserved_by_the_server() {
+        let source = endpoints(&[("/metrics", "GET")]).replace("  port: 3000\n", "  port: 3000\n  metrics_path: /metrics\n");
+        assert_eq!(validate(&source), ["config.yaml:6:11: error: endpoints[0].path: `/metrics` is already used by server.metrics_path"]);
+    }
+
+    #[test]
+    fn reports_every_broken_endpoint() {
+        let source = endpoints(&[("/a", "FOO BAR"), ("/b", "GET"), ("/c", "[]")])
+            .replace("    method: GET\n    handler: default\n", "    method: GET\n");
+        assert_eq!(validate(&source), [
+            "config.yaml:6:13: error: endpoints[0].method: invalid HTTP method: \"FOO BAR\"",
+            "config.yaml:10:5: error: endpoints[1]: missing field `handler`",
+            "config.yaml:15:13: error: endpoints[2].method: method list must not be empty",
+        ]);
+    }
+}
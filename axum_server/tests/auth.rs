@@ -0,0 +1,182 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use axum::http::StatusCode;
+use axum_server::TestServer;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use serde_json::{json, Value};
+
+const SECRET: &str = "test-secret";
+
+// A server where `/me` shows the principal, `/public` needs no credentials and
+// `/rs256` only accepts tokens signed with the fixture key, with reload and metrics routes
+fn config() -> String {
+    let hash = bcrypt::hash("hunter2", 4).unwrap();
+    format!(r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  reload_path: /reload
+  metrics_path: /metrics
+  auth:
+    api_key:
+      header: x-api-key
+      query: api_key
+      keys:
+        ci: ci-key
+    basic:
+      realm: mock
+      users:
+        alice: "{hash}"
+        bob: "sha256:5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"
+    jwt:
+      algorithm: HS256
+      secret: {SECRET}
+      issuer: tests
+endpoints:
+  - path: /me
+    method: GET
+    handler: template
+    response: '{{"name": "{{{{ principal.name }}}}", "scheme": "{{{{ principal.scheme }}}}", "email": "{{{{ principal.claims.email }}}}"}}'
+    description: "Principal"
+  - path: /public
+    method: GET
+    handler: default
+    response: "public"
+    description: "Public"
+    auth: {{}}
+  - path: /rs256
+    method: GET
+    handler: default
+    response: "rs256"
+    description: "RS256"
+    auth:
+      jwt:
+        algorithm: RS256
+        public_key_file: {dir}/tests/fixtures/jwt_public.pem
+"#, dir = env!("CARGO_MANIFEST_DIR"))
+}
+
+fn basic(user: &str, password: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{}:{}", user, password)))
+}
+
+fn token(claims: Value, algorithm: Algorithm) -> String {
+    let key = match algorithm {
+        Algorithm::RS256 => EncodingKey::from_rsa_pem(include_bytes!("fixtures/jwt_private.pem")).unwrap(),
+        _ => EncodingKey::from_secret(SECRET.as_bytes()),
+    };
+    format!("Bearer {}", jsonwebtoken::encode(&Header::new(algorithm), &claims, &key).unwrap())
+}
+
+// Why the last request was rejected, as logged, the response only says `Unauthorized`
+fn rejection(server: &TestServer) -> String {
+    server.requests().pop().unwrap().field("auth_rejected").unwrap().to_string()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+#[tokio::test]
+async fn accepts_api_keys() {
+    let server = TestServer::from_yaml(&config()).await.unwrap();
+
+    let response = server.get("/me").header("x-api-key", "ci-key").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json::<Value>(), json!({ "name": "ci", "scheme": "api_key", "email": "" }));
+    assert_eq!(server.get("/me?api_key=ci-key").send().await.status, StatusCode::OK);
+
+    let response = server.get("/me").header("x-api-key", "wrong").send().await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.text(), "Unauthorized");
+    assert_eq!(rejection(&server), "invalid API key");
+}
+
+#[tokio::test]
+async fn accepts_basic_credentials() {
+    let server = TestServer::from_yaml(&config()).await.unwrap();
+
+    let response = server.get("/me").header("authorization", &basic("alice", "hunter2")).send().await;
+    assert_eq!(response.json::<Value>()["name"], "alice");
+    let response = server.get("/me").header("authorization", &basic("bob", "password")).send().await;
+    assert_eq!(response.json::<Value>()["scheme"], "basic");
+
+    let response = server.get("/me").header("authorization", &basic("alice", "wrong")).send().await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(rejection(&server), "wrong password for user `alice`");
+
+    // Unknown users get the same response as wrong passwords
+    let unknown = server.get("/me").header("authorization", &basic("mallory", "wrong")).send().await;
+    assert_eq!(unknown.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(unknown.text(), response.text());
+    assert_eq!(rejection(&server), "unknown user `mallory`");
+}
+
+#[tokio::test]
+async fn verifies_tokens() {
+    let server = TestServer::from_yaml(&config()).await.unwrap();
+    let claims = json!({ "sub": "carol", "email": "carol@example.com", "iss": "tests", "exp": now() + 60 });
+
+    let response = server.get("/me").header("authorization", &token(claims.clone(), Algorithm::HS256)).send().await;
+    assert_eq!(response.json::<Value>(), json!({ "name": "carol", "scheme": "jwt", "email": "carol@example.com" }));
+
+    let expired = json!({ "sub": "carol", "iss": "tests", "exp": now() - 60 });
+    let response = server.get("/me").header("authorization", &token(expired, Algorithm::HS256)).send().await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(rejection(&server), "token expired");
+
+    let other_issuer = json!({ "sub": "carol", "iss": "other", "exp": now() + 60 });
+    server.get("/me").header("authorization", &token(other_issuer, Algorithm::HS256)).send().await;
+    assert_eq!(rejection(&server), "token has the wrong issuer");
+
+    // The endpoint's section replaces the server's
+    let response = server.get("/rs256").header("authorization", &token(claims.clone(), Algorithm::RS256)).send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    server.get("/rs256").header("authorization", &token(claims, Algorithm::HS256)).send().await;
+    assert_eq!(rejection(&server), "token is signed with another algorithm");
+}
+
+#[tokio::test]
+async fn rejects_requests_without_credentials() {
+    let server = TestServer::from_yaml(&config()).await.unwrap();
+
+    let response = server.get("/me").send().await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    let challenges: Vec<_> = response.headers.get_all("www-authenticate").iter().collect();
+    assert_eq!(challenges, ["Basic realm=\"mock\", charset=\"UTF-8\"", "Bearer"]);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].handler.as_deref(), Some("auth"));
+    assert!(requests[0].fields.iter().any(|(key, value)| key == "auth_rejected" && value.starts_with("missing credentials")),
+        "{:?}", requests[0].fields);
+
+    assert_eq!(server.get("/public").send().await.text(), "public");
+}
+
+#[tokio::test]
+async fn protects_the_server_routes() {
+    let server = TestServer::from_yaml(&config()).await.unwrap();
+
+    let response = server.post("/reload").send().await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.text(), "Unauthorized");
+    assert_eq!(server.get("/metrics").send().await.status, StatusCode::UNAUTHORIZED);
+
+    let requests = server.requests();
+    assert_eq!(requests[0].path.as_deref(), Some("/reload"));
+    assert_eq!(requests[0].handler.as_deref(), Some("auth"));
+
+    // The server was built in code, so there is no file to reload
+    let response = server.post("/reload").header("x-api-key", "ci-key").send().await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(server.get("/metrics").header("x-api-key", "ci-key").send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn rejects_plain_passwords() {
+    let config = config().replace("sha256:5e88", "5e88");
+    let error = TestServer::from_yaml(&config).await.err().unwrap();
+    assert!(error.to_string().contains("server.auth: basic: `users.bob`: not a bcrypt hash"), "{}", error);
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use axum_server::check;
+use axum_server::{Config, TestServer};
+
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /hello
+    method: GET
+    handler: default
+    response: "Hello, World!"
+    description: "Greeting"
+  - path: /users/{id}
+    method: POST
+    handler: template
+    response: '{"id": "{{ path.id }}", "name": {{ body.name | tojson }}}'
+    description: "User"
+    params:
+      status: "201"
+    checks:
+      - path: /users/7
+        body: '{"name": "Ann"}'
+        expect:
+          status: 201
+          headers:
+            content-type: application/json
+          json: { "name": "Ann" }
+      - name: "Wrong expectations"
+        body: '{"name": "Ann"}'
+        expect:
+          status: 200
+          body_contains: "Bob"
+"#;
+
+#[tokio::test]
+async fn checks_endpoints_against_a_server() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+    let config = Config::from_yaml(CONFIG).unwrap();
+
+    let results = check::run(&config, &server.url(""), Duration::from_secs(5)).await.unwrap();
+    let names: Vec<&str> = results.iter().map(|result| result.name.as_str()).collect();
+    assert_eq!(names, ["GET /hello", "POST /users/7", "Wrong expectations"]);
+    assert!(results[0].passed(), "{:?}", results[0].failures);
+    assert!(results[1].passed(), "{:?}", results[1].failures);
+    assert_eq!(results[2].failures.len(), 2);
+    assert_eq!(results[2].failures[0], "status: expected 200, got 201");
+
+    let report = check::junit("config.yaml", &results);
+    assert!(report.contains(r#"<testsuite name="config.yaml" tests="3" failures="1""#), "{}", report);
+    assert!(check::table(&results).ends_with("2 passed, 1 failed\n"));
+}
+
+#[tokio::test]
+async fn reports_unreachable_servers() {
+    let config = Config::from_yaml(CONFIG).unwrap();
+    let results = check::run(&config, "http://127.0.0.1:1", Duration::from_secs(5)).await.unwrap();
+    assert!(results.iter().all(|result| result.status.is_none() && !result.passed()));
+}
+
+#[tokio::test]
+async fn checks_endpoints_of_virtual_hosts() {
+    let yaml = CONFIG.replace("    description: \"Greeting\"\n", "    description: \"Greeting\"\n    host: greeting.local\n");
+    let server = TestServer::from_yaml(&yaml).await.unwrap();
+    let config = Config::from_yaml(&yaml).unwrap();
+
+    let results = check::run(&config, &server.url(""), Duration::from_secs(5)).await.unwrap();
+    assert_eq!(results[0].name, "GET /hello");
+    assert!(results[0].passed(), "{:?}", results[0].failures);
+}
@@ -0,0 +1,139 @@
+use axum::http::{Method, StatusCode};
+use axum_server::TestServer;
+
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  cors:
+    allowed_origins: ["http://localhost:5173"]
+    expose_headers: [x-total-count]
+    max_age: 600
+endpoints:
+  - path: /users
+    method: [GET, POST]
+    handler: default
+    response: "[]"
+    description: "Users"
+  - path: /session
+    method: POST
+    handler: default
+    response: "{}"
+    description: "Session"
+    cors:
+      allowed_origins: ["http://localhost:5173", "http://other.test"]
+      allowed_headers: [content-type]
+      allow_credentials: true
+"#;
+
+const SERVER_CORS: &str = r#"
+  cors:
+    allowed_origins: ["http://localhost:5173"]
+    expose_headers: [x-total-count]
+    max_age: 600"#;
+
+const ORIGIN: &str = "http://localhost:5173";
+
+#[tokio::test]
+async fn adds_headers_for_allowed_origins() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let response = server.get("/users").header("origin", ORIGIN).send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("access-control-allow-origin"), Some(ORIGIN));
+    assert_eq!(response.header("access-control-expose-headers"), Some("x-total-count"));
+    assert_eq!(response.header("vary"), Some("Origin"));
+
+    let response = server.get("/users").header("origin", "http://evil.test").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("access-control-allow-origin"), None);
+}
+
+#[tokio::test]
+async fn answers_preflight_requests() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let response = server.request(Method::OPTIONS, "/users")
+        .header("origin", ORIGIN)
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "content-type, x-trace")
+        .send().await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    assert_eq!(response.header("access-control-allow-origin"), Some(ORIGIN));
+    assert_eq!(response.header("access-control-allow-methods"), Some("POST"));
+    assert_eq!(response.header("access-control-allow-headers"), Some("content-type, x-trace"));
+    assert_eq!(response.header("access-control-max-age"), Some("600"));
+
+    let requests = server.requests();
+    assert_eq!(requests[0].handler.as_deref(), Some("cors"));
+}
+
+#[tokio::test]
+async fn rejects_preflight_requests() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+    let preflight = |origin, method| server.request(Method::OPTIONS, "/session")
+        .header("origin", origin)
+        .header("access-control-request-method", method);
+
+    let response = preflight("http://evil.test", "POST").send().await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    assert_eq!(response.header("access-control-allow-origin"), None);
+
+    // The endpoint overrides the allowed headers
+    let response = preflight(ORIGIN, "POST").header("access-control-request-headers", "x-trace").send().await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    assert_eq!(response.text(), "CORS preflight rejected: header x-trace is not allowed");
+
+    let response = preflight(ORIGIN, "POST").header("access-control-request-headers", "Content-Type").send().await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    assert_eq!(response.header("access-control-allow-credentials"), Some("true"));
+
+    // Methods without an endpoint are not allowed
+    let response = preflight(ORIGIN, "DELETE").send().await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    assert_eq!(response.header("access-control-allow-origin"), None);
+    assert_eq!(response.header("allow"), Some("POST, OPTIONS"));
+}
+
+#[tokio::test]
+async fn enables_cors_per_endpoint() {
+    let config = CONFIG.replace(SERVER_CORS, "");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    let response = server.get("/users").header("origin", ORIGIN).send().await;
+    assert_eq!(response.header("access-control-allow-origin"), None);
+
+    let response = server.post("/session").header("origin", "http://other.test").send().await;
+    assert_eq!(response.header("access-control-allow-origin"), Some("http://other.test"));
+    assert_eq!(response.header("access-control-allow-credentials"), Some("true"));
+}
+
+#[tokio::test]
+async fn applies_the_server_policy_to_the_server_routes() {
+    let config = CONFIG.replace("  port: 3000\n", "  port: 3000\n  openapi_path: /openapi.json\n");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    let response = server.get("/openapi.json").header("origin", ORIGIN).send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("access-control-allow-origin"), Some(ORIGIN));
+
+    let response = server.request(Method::OPTIONS, "/openapi.json")
+        .header("origin", ORIGIN)
+        .header("access-control-request-method", "GET")
+        .send().await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    assert_eq!(response.header("access-control-allow-methods"), Some("GET"));
+    assert_eq!(server.requests()[0].handler.as_deref(), Some("cors"));
+}
+
+#[tokio::test]
+async fn rejects_invalid_policies() {
+    let config = CONFIG.replace("[\"http://localhost:5173\"]", "[\"localhost:5173/app\"]");
+    let error = TestServer::from_yaml(&config).await.err().unwrap();
+    assert!(error.to_string().contains("server.cors: `allowed_origins`: `localhost:5173/app` is not an origin"), "{}", error);
+
+    // Credentials can't be combined with any origin
+    let config = CONFIG.replace("      allowed_origins: [\"http://localhost:5173\", \"http://other.test\"]\n", "      allowed_origins: [\"*\"]\n");
+    let error = TestServer::from_yaml(&config).await.err().unwrap();
+    assert!(error.to_string().contains("endpoints[1].cors: `allow_credentials` needs an `allowed_origins` list without `*`"), "{}", error);
+}
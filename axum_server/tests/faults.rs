@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use axum_server::TestServer;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// A server where `/error` always fails after a delay, `/truncated` sends half its body,
+// `/closed` drops the connection and `/degraded` fails once its faults are switched on
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /error
+    method: GET
+    handler: default
+    response: "ok"
+    description: "Always fails"
+    faults:
+      delay: 50ms
+      error_rate: 1
+      error_status: 502
+      error_body: "upstream unavailable"
+  - path: /truncated
+    method: GET
+    handler: default
+    response: "0123456789"
+    description: "Half a body"
+    faults:
+      truncate_rate: 1
+  - path: /closed
+    method: GET
+    handler: default
+    response: "ok"
+    description: "No response"
+    faults:
+      close_rate: 1
+  - path: /degraded
+    method: GET
+    handler: default
+    response: "ok"
+    description: "Switched off"
+    faults:
+      enabled: false
+      error_rate: 1
+  - path: /healthy
+    method: GET
+    handler: default
+    response: "ok"
+    description: "No faults"
+"#;
+
+// Everything the server sends for a GET request until it closes the connection
+async fn raw_get(server: &TestServer, path: &str) -> String {
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.ok();
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+#[tokio::test]
+async fn injects_delays_and_errors() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let start = Instant::now();
+    let response = server.get("/error").send().await;
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+    assert_eq!(response.text(), "upstream unavailable");
+
+    let request = server.requests().pop().unwrap();
+    assert_eq!(request.handler.as_deref(), Some("fault"));
+    assert_eq!(request.field("fault"), Some("delay 50ms, error 502"));
+}
+
+#[tokio::test]
+async fn truncates_bodies_and_closes_connections() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let truncated = raw_get(&server, "/truncated").await;
+    assert!(truncated.starts_with("HTTP/1.1 200 OK"), "{}", truncated);
+    assert!(truncated.contains("content-length: 10"), "{}", truncated);
+    assert!(truncated.ends_with("\r\n\r\n01234"), "{}", truncated);
+
+    assert_eq!(raw_get(&server, "/closed").await, "");
+    assert_eq!(server.requests().pop().unwrap().field("fault"), Some("closed"));
+}
+
+#[tokio::test]
+async fn toggles_faults_at_runtime() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+    assert_eq!(server.get("/degraded").send().await.status, StatusCode::OK);
+
+    assert_eq!(server.handle().toggle_faults("GET", "/degraded"), Some(true));
+    let response = server.get("/degraded").send().await;
+    assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.text(), "Injected fault: 503 Service Unavailable");
+
+    assert_eq!(server.handle().toggle_faults("GET", "/degraded"), Some(false));
+    assert_eq!(server.get("/degraded").send().await.status, StatusCode::OK);
+
+    assert_eq!(server.handle().toggle_faults("GET", "/healthy"), None);
+}
+
+#[tokio::test]
+async fn rejects_invalid_faults() {
+    let config = CONFIG.replace("error_status: 502", "error_status: 502\n      truncate_rate: 1.5");
+    let error = TestServer::from_yaml(&config).await.err().unwrap();
+    assert!(error.to_string().contains("endpoints[0].faults: `truncate_rate` must be between 0 and 1, got 1.5"), "{}", error);
+}
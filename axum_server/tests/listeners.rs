@@ -0,0 +1,136 @@
+use axum::http::StatusCode;
+use axum_server::config::ListenAddress;
+use axum_server::TestServer;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+// A server with an `admin` listener, `/users` served differently for `api.local`,
+// and a Unix socket listener at `{socket}`
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  listeners:
+    - name: admin
+      port: 0
+    - name: socket
+      unix: "{socket}"
+endpoints:
+  - path: /users
+    method: GET
+    handler: default
+    response: "default users"
+    description: "Users of any host"
+  - path: /users
+    method: GET
+    handler: default
+    response: "api users"
+    description: "Users of the API host"
+    host: api.local
+    faults:
+      enabled: false
+      error_rate: 1
+  - path: /status
+    method: GET
+    handler: default
+    response: "api status"
+    description: "Only on the API host"
+    host: api.local
+  - path: /reset
+    method: POST
+    handler: default
+    response: "reset"
+    description: "Admin only"
+    listener: admin
+  - path: /users
+    method: GET
+    handler: default
+    response: "admin users"
+    description: "Users on the admin listener"
+    listener: admin
+"#;
+
+fn config(name: &str) -> String {
+    let socket = std::env::temp_dir().join(format!("axum_server_{}_{}.sock", name, std::process::id()));
+    CONFIG.replace("{socket}", &socket.display().to_string())
+}
+
+// Everything the server sends for a request until it closes the connection
+async fn exchange(mut stream: impl AsyncRead + AsyncWrite + Unpin, method: &str, path: &str, host: &str) -> String {
+    let request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", method, path, host);
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+fn listener<'a>(server: &'a TestServer, name: &str) -> &'a ListenAddress {
+    &server.handle().listeners().iter().find(|(listener, _)| listener == name).unwrap().1
+}
+
+#[tokio::test]
+async fn scopes_endpoints_to_listeners() {
+    let server = TestServer::from_yaml(&config("listeners")).await.unwrap();
+    assert_eq!(server.get("/users").send().await.text(), "default users");
+    assert_eq!(server.post("/reset").send().await.status, StatusCode::NOT_FOUND);
+
+    let ListenAddress::Tcp(admin) = *listener(&server, "admin") else {
+        panic!("admin listens on a TCP port");
+    };
+    let response = exchange(TcpStream::connect(admin).await.unwrap(), "POST", "/reset", "localhost").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK") && response.ends_with("reset"), "{}", response);
+    let response = exchange(TcpStream::connect(admin).await.unwrap(), "GET", "/users", "localhost").await;
+    assert!(response.ends_with("admin users"), "{}", response);
+    assert!(server.logs().iter().any(|event| event.message == format!("Server listening on {} (admin)", admin)));
+}
+
+#[tokio::test]
+async fn routes_virtual_hosts() {
+    let server = TestServer::from_yaml(&config("hosts")).await.unwrap();
+    let get = |path: &'static str, host: &'static str| server.get(path).header("host", host).send();
+
+    assert_eq!(get("/users", "api.local").await.text(), "api users");
+    assert_eq!(get("/users", "API.local:8080").await.text(), "api users");
+    assert_eq!(get("/users", "other.local").await.text(), "default users");
+    assert_eq!(get("/status", "api.local").await.text(), "api status");
+    assert_eq!(get("/status", "other.local").await.status, StatusCode::NOT_FOUND);
+
+    // Endpoints with the same method and path are told apart by their scope
+    assert_eq!(server.handle().toggle_faults("GET", "/users"), None);
+    assert_eq!(server.handle().toggle_endpoint_faults("GET /users for api.local"), Some(true));
+    assert_eq!(get("/users", "api.local").await.status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(get("/users", "other.local").await.status, StatusCode::OK);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn serves_unix_domain_sockets() {
+    let server = TestServer::from_yaml(&config("unix")).await.unwrap();
+    let ListenAddress::Unix(path) = listener(&server, "socket").clone() else {
+        panic!("socket listens on a Unix domain socket");
+    };
+
+    let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+    let response = exchange(stream, "GET", "/users", "localhost").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK") && response.ends_with("default users"), "{}", response);
+
+    server.shutdown().await.unwrap();
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[tokio::test]
+async fn rejects_invalid_scopes() {
+    let config = config("invalid")
+        .replace("listener: admin\n", "listener: missing\n")
+        .replace("host: api.local\n    faults", "host: api.local:80\n    faults")
+        .replace("- name: socket", "- name: admin");
+    let error = TestServer::from_yaml(&config).await.err().unwrap().to_string();
+    assert!(error.contains("server.listeners[1].name: `admin` is already the name of server.listeners[0]"), "{}", error);
+    assert!(error.contains("endpoints[1].host: `api.local:80` is not a host name"), "{}", error);
+    assert!(error.contains("endpoints[3].listener: no listener named `missing` in server.listeners"), "{}", error);
+
+    // Endpoints only conflict with those served on the same listener and host
+    let duplicate = CONFIG.trim_end().strip_suffix("listener: admin").unwrap().replace("{socket}", "unused.sock");
+    let error = TestServer::from_yaml(&duplicate).await.err().unwrap().to_string();
+    assert!(error.contains("endpoints[4].method: GET /users is already defined by endpoints[0]"), "{}", error);
+}
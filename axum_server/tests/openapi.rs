@@ -0,0 +1,45 @@
+use axum_server::TestServer;
+use serde_json::Value;
+
+// `GET /users` is served by one endpoint for `api.local` and another for every other host
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  openapi_path: /openapi.json
+endpoints:
+  - path: /users
+    method: GET
+    handler: default
+    response: "[]"
+    description: "Users"
+  - path: /users
+    method: GET
+    handler: default
+    response: "[]"
+    description: "Users of the API host"
+    host: api.local
+  - path: /health
+    method: GET
+    handler: default
+    response: "ok"
+    description: "Health"
+"#;
+
+#[tokio::test]
+async fn documents_the_endpoints_of_the_requested_host() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let document: Value = server.get("/openapi.json").send().await.json();
+    assert_eq!(document["paths"]["/users"]["get"]["summary"], "Users");
+    assert_eq!(document["paths"]["/health"]["get"]["summary"], "Health");
+    assert!(document["servers"][0]["url"].as_str().unwrap().starts_with("http://127.0.0.1:"), "{}", document["servers"]);
+
+    let document: Value = server.get("/openapi.json").header("host", "api.local").send().await.json();
+    assert_eq!(document["paths"]["/users"]["get"]["summary"], "Users of the API host");
+    assert_eq!(document["paths"]["/health"]["get"]["summary"], "Health");
+    assert!(document["servers"][0]["url"].as_str().unwrap().starts_with("http://api.local:"), "{}", document["servers"]);
+
+    let document = server.handle().site_openapi("default", Some("api.local"));
+    assert_eq!(document["paths"]["/users"]["get"]["summary"], "Users of the API host");
+}
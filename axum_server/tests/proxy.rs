@@ -0,0 +1,86 @@
+use axum::http::StatusCode;
+use axum_server::TestServer;
+use serde_json::Value;
+
+// The upstream answers with the request it got, as rendered by the template handler
+const UPSTREAM: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /{*rest}
+    method: ANY
+    handler: template
+    response: '{"method": "{{ method }}", "rest": "{{ path.rest }}", "query": "{{ query.q }}", "host": "{{ headers[''x-forwarded-host''] }}", "proto": "{{ headers[''x-forwarded-proto''] }}", "added": "{{ headers[''x-added''] }}"}'
+    description: "Echo"
+"#;
+
+// `{upstream}` is replaced by the URL of the upstream server
+const PROXY: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  fallback_proxy:
+    upstream: "{upstream}"
+endpoints:
+  - path: /api/{*rest}
+    method: ANY
+    handler: proxy
+    response: ""
+    description: "Proxied API"
+    params:
+      upstream: "{upstream}/v1"
+      strip_prefix: /api
+      request_header.x-added: "yes"
+      response_header.x-proxied: "true"
+  - path: /local
+    method: GET
+    handler: default
+    response: "local"
+    description: "Served by the proxy server itself"
+"#;
+
+async fn servers() -> (TestServer, TestServer) {
+    let upstream = TestServer::from_yaml(UPSTREAM).await.unwrap();
+    let proxy = TestServer::from_yaml(&PROXY.replace("{upstream}", &upstream.url(""))).await.unwrap();
+    (upstream, proxy)
+}
+
+#[tokio::test]
+async fn forwards_requests_to_the_upstream() {
+    let (_upstream, proxy) = servers().await;
+
+    let response = proxy.post("/api/users?q=x").header("host", "mock.local").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("x-proxied"), Some("true"));
+    let echoed: Value = response.json();
+    assert_eq!(echoed["method"], "POST");
+    assert_eq!(echoed["rest"], "v1/users");
+    assert_eq!(echoed["query"], "x");
+    assert_eq!(echoed["host"], "mock.local");
+    assert_eq!(echoed["proto"], "http");
+    assert_eq!(echoed["added"], "yes");
+
+    let request = proxy.requests().pop().unwrap();
+    assert_eq!(request.handler.as_deref(), Some("proxy"));
+}
+
+#[tokio::test]
+async fn sends_unmatched_routes_to_the_fallback_proxy() {
+    let (_upstream, proxy) = servers().await;
+
+    assert_eq!(proxy.get("/local").send().await.text(), "local");
+    let echoed: Value = proxy.delete("/other/path").send().await.json();
+    assert_eq!(echoed["method"], "DELETE");
+    assert_eq!(echoed["rest"], "other/path");
+}
+
+#[tokio::test]
+async fn answers_with_bad_gateway_when_the_upstream_is_down() {
+    let (upstream, proxy) = servers().await;
+    upstream.shutdown().await.unwrap();
+
+    let response = proxy.get("/api/users").send().await;
+    assert_eq!(response.status, StatusCode::BAD_GATEWAY);
+    assert!(response.text().starts_with("Upstream request to http://127.0.0.1:"), "{}", response.text());
+}
@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use axum::http::StatusCode;
+use axum_handlers::{register_default_handlers, Handler, HandlerContext, HandlerRegistry, HandlerResponse, HandlerResult};
+use axum_server::TestServer;
+use tokio::sync::{Notify, Semaphore};
+
+// A server where `/limited` allows two requests a minute per IP, `/tenants` two a minute
+// per `x-tenant` header and `/slow` one request at a time
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /limited
+    method: GET
+    handler: default
+    response: "ok"
+    description: "Limited per IP"
+    rate_limit:
+      requests: 2
+      per: 1m
+  - path: /tenants
+    method: GET
+    handler: default
+    response: "ok"
+    description: "Limited per tenant"
+    rate_limit:
+      requests: 2
+      per: 1m
+      key: "header:x-tenant"
+  - path: /slow
+    method: GET
+    handler: gate
+    response: ""
+    description: "One at a time"
+    rate_limit:
+      max_in_flight: 1
+"#;
+
+// Answers once the test adds a permit, so requests stay in flight until then
+struct GateHandler {
+    entered: Arc<Notify>,
+    release: Arc<Semaphore>,
+}
+
+#[async_trait]
+impl Handler for GateHandler {
+    async fn handle(&self, _ctx: HandlerContext) -> HandlerResult {
+        self.entered.notify_one();
+        self.release.acquire().await.unwrap().forget();
+        Ok(HandlerResponse::text("done"))
+    }
+}
+
+#[tokio::test]
+async fn limits_requests_per_client() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(server.get("/limited").send().await.status, StatusCode::OK);
+    }
+    let response = server.get("/limited").send().await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.header("retry-after").unwrap().parse().unwrap();
+    assert!((1..=30).contains(&retry_after), "{}", retry_after);
+    assert_eq!(response.text(), "Too Many Requests: rate limit of 2 per 1m exceeded");
+
+    let rejected = server.requests().into_iter().find(|event| event.status == Some(429)).unwrap();
+    assert_eq!(rejected.handler.as_deref(), Some("rate_limit"));
+    assert_eq!(rejected.field("rate_limited"), Some("rate limit of 2 per 1m exceeded"));
+}
+
+#[tokio::test]
+async fn keys_clients_by_header() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(server.get("/tenants").header("x-tenant", "a").send().await.status, StatusCode::OK);
+    }
+    assert_eq!(server.get("/tenants").header("x-tenant", "a").send().await.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(server.get("/tenants").header("x-tenant", "b").send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn limits_requests_in_flight() {
+    let entered = Arc::new(Notify::new());
+    let release = Arc::new(Semaphore::new(0));
+    let mut registry = HandlerRegistry::new();
+    register_default_handlers(&mut registry);
+    registry.register("gate", GateHandler { entered: entered.clone(), release: release.clone() });
+    let server = Arc::new(TestServer::with_handlers(CONFIG, registry).await.unwrap());
+
+    let first = tokio::spawn({
+        let server = server.clone();
+        async move { server.get("/slow").send().await }
+    });
+    entered.notified().await;
+
+    let response = server.get("/slow").send().await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.header("retry-after"), Some("1"));
+
+    release.add_permits(1);
+    assert_eq!(first.await.unwrap().status, StatusCode::OK);
+    release.add_permits(1);
+    assert_eq!(server.get("/slow").send().await.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn limits_requests_to_the_server_routes() {
+    let config = CONFIG.replace("  port: 3000\n", "  port: 3000\n  metrics_path: /metrics\n  rate_limit:\n    requests: 2\n    per: 1m\n");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    assert_eq!(server.get("/tenants").send().await.status, StatusCode::OK);
+    assert_eq!(server.get("/metrics").send().await.status, StatusCode::OK);
+    let response = server.get("/metrics").send().await;
+    assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(response.text(), "Too Many Requests: rate limit of 2 per 1m exceeded");
+
+    let rejected = server.requests().into_iter().find(|event| event.status == Some(429)).unwrap();
+    assert_eq!(rejected.handler.as_deref(), Some("rate_limit"));
+}
+
+#[tokio::test]
+async fn only_counts_requests_every_limiter_lets_through() {
+    let config = CONFIG.replace("  port: 3000\n", "  port: 3000\n  rate_limit:\n    requests: 3\n    per: 1m\n");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    for _ in 0..2 {
+        assert_eq!(server.get("/limited").send().await.status, StatusCode::OK);
+    }
+    // Rejected by the endpoint's limiter, so the server's last token is left
+    assert_eq!(server.get("/limited").send().await.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(server.get("/tenants").send().await.status, StatusCode::OK);
+    let response = server.get("/tenants").send().await;
+    assert_eq!(response.text(), "Too Many Requests: rate limit of 3 per 1m exceeded");
+}
+
+#[tokio::test]
+async fn rejects_invalid_limits() {
+    let config = CONFIG.replace("key: \"header:x-tenant\"", "key: tenant");
+    let error = TestServer::from_yaml(&config).await.err().unwrap();
+    assert!(error.to_string().contains("endpoints[1].rate_limit: `key`: unknown key `tenant`"), "{}", error);
+}
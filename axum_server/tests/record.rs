@@ -0,0 +1,107 @@
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use axum::http::header;
+use axum::response::AppendHeaders;
+use axum::routing::get;
+use axum::{middleware, Router};
+use axum_handlers::HandlerRegistry;
+use axum_server::config::HeaderValues;
+use axum_server::record::{self, Recorder};
+use axum_server::validate::validate_file;
+use axum_server::{Config, ServerBuilder, TestServer};
+
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints: []
+"#;
+
+// The recording server serves its own document and metrics
+const RECORDING: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  metrics_path: /metrics
+  openapi_path: /openapi.json
+  docs_path: /docs
+endpoints: []
+"#;
+
+// The upstream sets two cookies on login
+async fn upstream() -> TestServer {
+    let router = Router::new().route("/login", get(|| async {
+        (AppendHeaders([(header::SET_COOKIE, "session=abc; HttpOnly"), (header::SET_COOKIE, "theme=dark; Path=/")]), "welcome")
+    }));
+    TestServer::start(ServerBuilder::new(Config::from_yaml(CONFIG).unwrap()).merge(router)).await.unwrap()
+}
+
+// A server recording the exchanges with `upstream` into `dir`
+async fn recorder(upstream: &TestServer, dir: &Path) -> TestServer {
+    let config = Config::from_yaml(RECORDING).unwrap();
+    let (log_sender, _) = mpsc::channel();
+    let recorder = Recorder::new(&upstream.url(""), &dir.join("recorded.yaml"), &dir.join("fixtures"), false, &config.server, log_sender).unwrap();
+    let builder = ServerBuilder::new(recorder.recording_config(&config))
+        .layer(middleware::from_fn_with_state(Arc::new(recorder), record::record));
+    TestServer::start(builder).await.unwrap()
+}
+
+// The recorded configuration once it has `count` endpoints
+async fn recorded(dir: &Path, count: usize) -> Config {
+    for _ in 0..50 {
+        if let Ok(config) = std::fs::read_to_string(dir.join("recorded.yaml")).map(|yaml| Config::from_yaml(&yaml).unwrap()) {
+            if config.endpoints.len() == count {
+                return config;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("nothing was recorded into {}", dir.display());
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("axum_server_record_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+#[tokio::test]
+async fn records_repeated_headers_as_lists() {
+    let dir = temp_dir("headers");
+    let upstream = upstream().await;
+    let server = recorder(&upstream, &dir).await;
+    assert_eq!(server.get("/login").send().await.text(), "welcome");
+
+    let config = recorded(&dir, 1).await;
+    let recording = &config.endpoints[0].recordings[0];
+    assert_eq!(recording.headers["set-cookie"], HeaderValues::Many(vec!["session=abc; HttpOnly".to_string(), "theme=dark; Path=/".to_string()]));
+    // Fixtures are found next to the recorded file, wherever it is replayed from
+    assert_eq!(recording.body_file.as_deref(), Some("fixtures/0001-GET-login.txt"));
+
+    // Replaying sends each cookie on its own
+    let registry = HandlerRegistry::new();
+    let replayed = validate_file(&dir.join("recorded.yaml").display().to_string(), &registry).unwrap().config.unwrap();
+    let replay = TestServer::start(ServerBuilder::new(replayed)).await.unwrap();
+    let response = replay.get("/login").send().await;
+    assert_eq!(response.text(), "welcome");
+    assert_eq!(response.headers.get_all("set-cookie").iter().count(), 2);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn skips_the_routes_of_the_server_itself() {
+    let dir = temp_dir("ignored");
+    let upstream = upstream().await;
+    let server = recorder(&upstream, &dir).await;
+
+    assert!(server.get("/openapi.json").send().await.text().contains("openapi"));
+    assert!(server.get("/docs").send().await.text().contains("<html"));
+    assert!(server.get("/metrics").send().await.status.is_success());
+    server.get("/login").send().await;
+
+    let config = recorded(&dir, 1).await;
+    assert_eq!(config.endpoints[0].path, "/login");
+    std::fs::remove_dir_all(&dir).ok();
+}
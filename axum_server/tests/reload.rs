@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use axum::http::StatusCode;
+use axum_server::config::Recording;
+use axum_server::{Config, ServerBuilder, TestServer};
+
+// A server with a reload endpoint, `{response}` is the greeting it serves
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  reload_path: /__reload
+endpoints:
+  - path: /hello
+    method: GET
+    handler: default
+    response: "{response}"
+    description: "Greeting"
+"#;
+
+fn config(response: &str) -> String {
+    CONFIG.replace("{response}", response)
+}
+
+// A configuration file in a fresh directory, serving `response`
+fn write_config(name: &str, response: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("axum_server_reload_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.yaml");
+    std::fs::write(&path, config(response)).unwrap();
+    path
+}
+
+async fn start(path: &PathBuf) -> TestServer {
+    let config = Config::from_yaml(&std::fs::read_to_string(path).unwrap()).unwrap();
+    TestServer::start(ServerBuilder::new(config).config_file(path.display().to_string())).await.unwrap()
+}
+
+#[tokio::test]
+async fn reloads_the_configuration_file() {
+    let path = write_config("file", "first");
+    let server = start(&path).await;
+    assert_eq!(server.get("/hello").send().await.text(), "first");
+
+    std::fs::write(&path, config("second").replace("/hello", "/hi")).unwrap();
+    assert!(server.handle().reload().await);
+    assert_eq!(server.get("/hi").send().await.text(), "second");
+    assert_eq!(server.get("/hello").send().await.status, StatusCode::NOT_FOUND);
+
+    let logs: Vec<String> = server.logs().into_iter().map(|event| event.message).collect();
+    assert!(logs.contains(&"Configuration reloaded, 2 endpoint change(s):".to_string()), "{:?}", logs);
+    assert!(logs.contains(&"  + GET /hi".to_string()), "{:?}", logs);
+    assert!(logs.contains(&"  - GET /hello".to_string()), "{:?}", logs);
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[tokio::test]
+async fn keeps_the_configuration_when_the_file_is_invalid() {
+    let path = write_config("invalid", "first");
+    let server = start(&path).await;
+
+    std::fs::write(&path, config("second").replace("method: GET", "method: \"GET POST\"")).unwrap();
+    let response = server.post("/__reload").send().await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(server.get("/hello").send().await.text(), "first");
+    assert!(server.logs().iter().any(|event| event.message.contains("endpoints[0].method: invalid HTTP method")));
+
+    std::fs::write(&path, config("second")).unwrap();
+    assert_eq!(server.post("/__reload").send().await.status, StatusCode::OK);
+    assert_eq!(server.get("/hello").send().await.text(), "second");
+    std::fs::remove_dir_all(path.parent().unwrap()).ok();
+}
+
+#[tokio::test]
+async fn applies_configurations_built_in_code() {
+    let server = TestServer::from_yaml(&config("first")).await.unwrap();
+
+    let mut changed = Config::from_yaml(&config("second")).unwrap();
+    assert!(server.handle().apply_config(changed.clone()).await);
+    assert_eq!(server.get("/hello").send().await.text(), "second");
+
+    changed.endpoints[0].path = "hello".to_string();
+    assert!(!server.handle().apply_config(changed).await);
+    assert_eq!(server.get("/hello").send().await.text(), "second");
+}
+
+#[tokio::test]
+async fn names_the_changed_fields() {
+    let server = TestServer::from_yaml(&config("first")).await.unwrap();
+
+    let mut changed = Config::from_yaml(&config("second")).unwrap();
+    changed.endpoints[0].handler = "template".to_string();
+    changed.endpoints[0].recordings.push(Recording { query: None, request_body_file: None, status: 204, headers: Default::default(), body_file: None });
+    assert!(server.handle().apply_config(changed).await);
+
+    let mut changed = Config::from_yaml(&config("second")).unwrap();
+    changed.endpoints[0].handler = "template".to_string();
+    changed.endpoints[0].response_schema = Some(serde_json::json!({ "type": "string" }));
+    changed.endpoints[0].checks.push(Default::default());
+    assert!(server.handle().apply_config(changed).await);
+
+    let logs: Vec<String> = server.logs().into_iter().map(|event| event.message).collect();
+    assert!(logs.contains(&"  ~ GET /hello (handler default -> template, response, recordings)".to_string()), "{:?}", logs);
+    assert!(logs.contains(&"  ~ GET /hello (recordings, response_schema, checks)".to_string()), "{:?}", logs);
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use axum::http::StatusCode;
+use axum_handlers::HandlerRegistry;
+use axum_server::validate::validate_file;
+use axum_server::{ServerBuilder, TestServer};
+
+// Recordings of `/users`, the first one matching a request is replayed, fixtures are in `{dir}`
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /users
+    method: [GET, POST]
+    handler: replay
+    response: ""
+    description: "Recorded users"
+    recordings:
+      - query: page=2
+        status: 200
+        headers:
+          content-type: application/json
+        body_file: "{dir}/page2.json"
+      - request_body_file: "{dir}/new_user.json"
+        status: 201
+        body_file: "{dir}/created.json"
+      - status: 200
+        headers:
+          content-type: application/json
+          set-cookie: ["session=abc; HttpOnly", "theme=dark; Expires=Wed, 21 Oct 2026 07:28:00 GMT"]
+        body_file: "{dir}/users.json"
+"#;
+
+// Fixture files in a fresh directory, and the configuration replaying them
+fn fixtures(name: &str) -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("axum_server_replay_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("page2.json"), r#"[{"id": 3}]"#).unwrap();
+    std::fs::write(dir.join("users.json"), r#"[{"id": 1}, {"id": 2}]"#).unwrap();
+    std::fs::write(dir.join("new_user.json"), r#"{"name": "ada"}"#).unwrap();
+    std::fs::write(dir.join("created.json"), r#"{"id": 4}"#).unwrap();
+    let config = CONFIG.replace("{dir}", &dir.display().to_string());
+    (dir, config)
+}
+
+#[tokio::test]
+async fn replays_recorded_responses() {
+    let (dir, config) = fixtures("responses");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    let response = server.get("/users").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    assert_eq!(response.text(), r#"[{"id": 1}, {"id": 2}]"#);
+    // Repeated headers are replayed one by one, cookies with commas in them stay intact
+    let cookies: Vec<&str> = response.headers.get_all("set-cookie").iter().map(|value| value.to_str().unwrap()).collect();
+    assert_eq!(cookies, ["session=abc; HttpOnly", "theme=dark; Expires=Wed, 21 Oct 2026 07:28:00 GMT"]);
+    assert_eq!(server.get("/users?page=2").send().await.text(), r#"[{"id": 3}]"#);
+    assert_eq!(server.requests().pop().unwrap().handler.as_deref(), Some("replay"));
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn matches_request_bodies() {
+    let (dir, config) = fixtures("bodies");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    // JSON bodies match regardless of formatting
+    let response = server.post("/users").body(r#"{ "name":"ada" }"#).send().await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.text(), r#"{"id": 4}"#);
+
+    let response = server.post("/users").body(r#"{"name": "grace"}"#).send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn rejects_missing_fixtures() {
+    let (dir, config) = fixtures("missing");
+    std::fs::remove_file(dir.join("created.json")).unwrap();
+
+    let error = TestServer::from_yaml(&config).await.err().unwrap().to_string();
+    assert!(error.contains("recordings[1]"), "{}", error);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn finds_fixtures_relative_to_the_configuration_file() {
+    let (dir, config) = fixtures("relative");
+    let file = dir.join("config.yaml");
+    std::fs::write(&file, config.replace(&format!("{}/", dir.display()), "")).unwrap();
+
+    let registry = HandlerRegistry::new();
+    let config = validate_file(&file.display().to_string(), &registry).unwrap().config.unwrap();
+    let server = TestServer::start(ServerBuilder::new(config)).await.unwrap();
+    assert_eq!(server.get("/users?page=2").send().await.text(), r#"[{"id": 3}]"#);
+    std::fs::remove_dir_all(&dir).ok();
+}
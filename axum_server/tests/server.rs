@@ -0,0 +1,132 @@
+use axum::http::{Method, StatusCode};
+use axum_handlers::{register_default_handlers, HandlerRegistry};
+use custom_handlers::register_custom_handlers;
+use axum_server::{Config, TestServer};
+use serde_json::{json, Value};
+
+// The example handlers of the binary, served by the example configuration
+#[path = "../src/custom_handlers.rs"]
+mod custom_handlers;
+
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /hello
+    method: GET
+    handler: default
+    response: "Hello, World!"
+    description: "Greeting"
+  - path: /users/{id}
+    method: [GET, POST]
+    handler: template
+    response: '{"id": "{{ path.id }}", "method": "{{ method }}"}'
+    description: "User"
+    params:
+      status: "201"
+"#;
+
+fn handlers() -> HandlerRegistry {
+    let mut registry = HandlerRegistry::new();
+    register_default_handlers(&mut registry);
+    register_custom_handlers(&mut registry);
+    registry
+}
+
+#[tokio::test]
+async fn serves_static_responses() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let response = server.get("/hello").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text(), "Hello, World!");
+    assert!(server.local_addr().port() != 3000);
+}
+
+#[tokio::test]
+async fn renders_templates() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let response = server.post("/users/7").send().await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    assert_eq!(response.json::<Value>(), json!({ "id": "7", "method": "POST" }));
+}
+
+#[tokio::test]
+async fn answers_unknown_routes_and_methods() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    assert_eq!(server.get("/missing").send().await.status, StatusCode::NOT_FOUND);
+    assert_eq!(server.delete("/hello").send().await.status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn captures_request_logs() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+    server.get("/users/1").header("x-request-id", "abc").send().await;
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path.as_deref(), Some("/users/1"));
+    assert_eq!(requests[0].status, Some(201));
+    assert_eq!(requests[0].handler.as_deref(), Some("template"));
+    assert_eq!(requests[0].request_id.as_deref(), Some("abc"));
+    assert!(server.logs().iter().any(|event| event.message.starts_with("Server listening on")));
+}
+
+#[tokio::test]
+async fn rejects_invalid_configurations() {
+    let config = CONFIG.replace("/users/{id}", "users");
+    let error = TestServer::from_yaml(&config).await.err().unwrap();
+    assert!(error.to_string().contains("must start with `/`"), "{}", error);
+}
+
+#[tokio::test]
+async fn applies_new_configurations() {
+    let server = TestServer::from_yaml(CONFIG).await.unwrap();
+
+    let mut config = Config::from_yaml(CONFIG).unwrap();
+    config.endpoints[0].response = "Changed".to_string();
+    assert!(server.handle().apply_config(config).await);
+    assert_eq!(server.get("/hello").send().await.text(), "Changed");
+}
+
+#[tokio::test]
+async fn serves_the_example_configuration() {
+    let server = TestServer::with_handlers(include_str!("../../config.yaml"), handlers()).await.unwrap();
+
+    let response = server.get("/api/user/42").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json::<Value>()["name"], "User 42");
+
+    let response = server.post("/api/resource").json(&json!({ "name": "new" })).send().await;
+    assert_eq!(response.status, StatusCode::CREATED);
+    assert_eq!(response.header("location"), Some("/api/resource/2"));
+    assert_eq!(response.json::<Value>()["data"], json!({ "name": "new" }));
+
+    let response = server.put("/api/resource").json(&json!([1, 2])).send().await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    assert_eq!(server.delete("/api/resource").send().await.status, StatusCode::NO_CONTENT);
+    server.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+async fn labels_undeclared_methods_as_other_in_metrics() {
+    let config = CONFIG.replace("  port: 3000\n", "  port: 3000\n  metrics_path: /metrics\n")
+        + "  - path: /any\n    method: ANY\n    handler: default\n    response: \"any\"\n    description: \"Any method\"\n"
+        + "  - path: /dav\n    method: PROPFIND\n    handler: default\n    response: \"dav\"\n    description: \"Custom method\"\n";
+    let server = TestServer::from_yaml(&config).await.unwrap();
+    for method in ["GET", "FOO", "BAR"] {
+        server.request(Method::from_bytes(method.as_bytes()).unwrap(), "/any").send().await;
+    }
+    server.request(Method::from_bytes(b"PROPFIND").unwrap(), "/dav").send().await;
+
+    let metrics = server.get("/metrics").send().await.text();
+    assert!(metrics.contains(r#"http_requests_total{method="GET",route="/any",handler="default",status="200"} 1"#), "{}", metrics);
+    assert!(metrics.contains(r#"http_requests_total{method="OTHER",route="/any",handler="default",status="200"} 2"#), "{}", metrics);
+    assert!(metrics.contains(r#"http_requests_total{method="PROPFIND",route="/dav",handler="default",status="200"} 1"#), "{}", metrics);
+    assert!(!metrics.contains("FOO"), "{}", metrics);
+}
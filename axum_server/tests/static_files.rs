@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+
+use axum::http::StatusCode;
+use axum_server::TestServer;
+
+// Files below `{root}`, with a secret next to the root that must not be served
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+endpoints:
+  - path: /files/{*path}
+    method: GET
+    handler: static_files
+    response: ""
+    description: "Static files"
+    params:
+      root: "{root}"
+      precompressed: "true"
+"#;
+
+// A fresh directory with `public/` as the root and `secret.txt` outside of it
+fn files(name: &str) -> (PathBuf, String) {
+    let dir = std::env::temp_dir().join(format!("axum_server_static_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    let root = dir.join("public");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(root.join("hello.txt"), "Hello, World!").unwrap();
+    std::fs::write(root.join("app.js"), "console.log(1)").unwrap();
+    std::fs::write(root.join("app.js.gz"), "gzipped").unwrap();
+    std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+    let config = CONFIG.replace("{root}", &root.display().to_string());
+    (dir, config)
+}
+
+#[tokio::test]
+async fn refuses_paths_outside_the_root() {
+    let (dir, config) = files("traversal");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    assert_eq!(server.get("/files/hello.txt").send().await.text(), "Hello, World!");
+    for path in ["/files/../secret.txt", "/files/%2e%2e/secret.txt", "/files/..%2fsecret.txt", "/files/%2fetc%2fpasswd"] {
+        let response = server.get(path).send().await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND, "{}", path);
+        assert!(!response.text().contains("secret"), "{}", path);
+    }
+
+    #[cfg(unix)]
+    {
+        // Symlinks may not lead out of the root either
+        std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("public/link.txt")).unwrap();
+        assert_eq!(server.get("/files/link.txt").send().await.status, StatusCode::NOT_FOUND);
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn serves_byte_ranges() {
+    let (dir, config) = files("ranges");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    let response = server.get("/files/hello.txt").header("range", "bytes=0-4").send().await;
+    assert_eq!(response.status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.header("content-range"), Some("bytes 0-4/13"));
+    assert_eq!(response.text(), "Hello");
+
+    assert_eq!(server.get("/files/hello.txt").header("range", "bytes=-6").send().await.text(), "World!");
+    assert_eq!(server.get("/files/hello.txt").header("range", "bytes=7-").send().await.text(), "World!");
+
+    let response = server.get("/files/hello.txt").header("range", "bytes=20-30").send().await;
+    assert_eq!(response.status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.header("content-range"), Some("bytes */13"));
+
+    // A stale If-Range gets the whole file
+    let response = server.get("/files/hello.txt").header("range", "bytes=0-4").header("if-range", "\"stale\"").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.text(), "Hello, World!");
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn answers_conditional_requests_with_not_modified() {
+    let (dir, config) = files("conditional");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    let response = server.get("/files/hello.txt").send().await;
+    let etag = response.header("etag").unwrap().to_string();
+    let last_modified = response.header("last-modified").unwrap().to_string();
+
+    let response = server.get("/files/hello.txt").header("if-none-match", &etag).send().await;
+    assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+    assert!(response.text().is_empty());
+    assert_eq!(server.get("/files/hello.txt").header("if-none-match", &format!("W/{}", etag)).send().await.status, StatusCode::NOT_MODIFIED);
+    assert_eq!(server.get("/files/hello.txt").header("if-none-match", "\"other\"").send().await.status, StatusCode::OK);
+    assert_eq!(server.get("/files/hello.txt").header("if-modified-since", &last_modified).send().await.status, StatusCode::NOT_MODIFIED);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn serves_precompressed_variants() {
+    let (dir, config) = files("precompressed");
+    let server = TestServer::from_yaml(&config).await.unwrap();
+
+    let response = server.get("/files/app.js").header("accept-encoding", "br, gzip").send().await;
+    assert_eq!(response.header("content-encoding"), Some("gzip"));
+    assert_eq!(response.header("content-type"), Some("text/javascript; charset=utf-8"));
+    assert_eq!(response.text(), "gzipped");
+
+    let response = server.get("/files/app.js").header("accept-encoding", "gzip;q=0").send().await;
+    assert_eq!(response.header("content-encoding"), None);
+    assert_eq!(response.text(), "console.log(1)");
+
+    #[cfg(unix)]
+    {
+        // Nor may a variant lead out of the root
+        std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("public/hello.txt.gz")).unwrap();
+        let response = server.get("/files/hello.txt").header("accept-encoding", "gzip").send().await;
+        assert_eq!(response.header("content-encoding"), None);
+        assert_eq!(response.text(), "Hello, World!");
+    }
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum_server::{Config, ServerBuilder, TestServer};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, ServerName};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+// A server with one endpoint, `{tls}` is replaced by the server's `tls` section
+const CONFIG: &str = r#"
+server:
+  host: 127.0.0.1
+  port: 3000
+  {tls}
+endpoints:
+  - path: /hello
+    method: GET
+    handler: default
+    response: "secure hello"
+    description: "Greeting"
+"#;
+
+fn config(tls: &str) -> String {
+    CONFIG.replace("{tls}", tls)
+}
+
+// A certificate for localhost written to `cert.pem` and `key.pem` in a fresh directory, returning its PEM
+fn write_certificate(dir: &PathBuf) -> String {
+    std::fs::create_dir_all(dir).unwrap();
+    let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    std::fs::write(dir.join("cert.pem"), generated.cert.pem()).unwrap();
+    std::fs::write(dir.join("key.pem"), generated.key_pair.serialize_pem()).unwrap();
+    generated.cert.pem()
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("axum_server_tls_{}_{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+    dir
+}
+
+// Everything the server sends for a GET request over TLS, trusting only `certificate`
+async fn https_get(server: &TestServer, certificate: &str, path: &str) -> std::io::Result<String> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_slice(certificate.as_bytes()).unwrap()).unwrap();
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    let stream = TcpStream::connect(server.local_addr()).await?;
+    let mut stream = TlsConnector::from(Arc::new(config))
+        .connect(ServerName::try_from("localhost").unwrap(), stream)
+        .await?;
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n", path);
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+#[tokio::test]
+async fn serves_https_and_reloads_certificates() {
+    let dir = temp_dir("reload");
+    let first = write_certificate(&dir);
+    let tls = format!("tls:\n    cert_file: {}\n    key_file: {}", dir.join("cert.pem").display(), dir.join("key.pem").display());
+    let server = TestServer::from_yaml(&config(&tls)).await.unwrap();
+    assert!(server.logs().iter().any(|event| event.message.starts_with("Server listening on https://")));
+
+    let response = https_get(&server, &first, "/hello").await.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    assert!(response.ends_with("secure hello"), "{}", response);
+    let request = server.requests().pop().unwrap();
+    assert_eq!(request.status, Some(200), "{}", request.message);
+
+    // A renewed certificate is picked up without a restart
+    let second = write_certificate(&dir);
+    for _ in 0..50 {
+        if server.logs().iter().any(|event| event.message.starts_with("TLS certificate reloaded")) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(https_get(&server, &second, "/hello").await.unwrap().ends_with("secure hello"));
+    assert!(https_get(&server, &first, "/hello").await.is_err());
+
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn generates_self_signed_certificates() {
+    let config = Config::from_yaml(&config("")).unwrap();
+    let server = TestServer::start(ServerBuilder::new(config).tls_self_signed(true)).await.unwrap();
+    let certificate = server.handle().self_signed_certificate().unwrap().to_string();
+
+    let response = https_get(&server, &certificate, "/hello").await.unwrap();
+    assert!(response.ends_with("secure hello"), "{}", response);
+
+    // Plain HTTP fails the handshake and is logged
+    let mut stream = TcpStream::connect(server.local_addr()).await.unwrap();
+    stream.write_all(b"GET /hello HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.ok();
+    assert!(!String::from_utf8_lossy(&response).contains("secure hello"));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(server.logs().iter().any(|event| event.message.starts_with("TLS handshake with 127.0.0.1:")));
+}
+
+#[tokio::test]
+async fn writes_self_signed_certificates_to_new_private_files_removed_on_shutdown() {
+    let config = Config::from_yaml(&config("")).unwrap();
+    let server = TestServer::start(ServerBuilder::new(config).tls_self_signed(true)).await.unwrap();
+    let path = server.logs().into_iter()
+        .find_map(|event| event.message.split_once("--cacert ").map(|(_, path)| path.to_string()))
+        .unwrap();
+
+    assert_eq!(server.handle().self_signed_certificate_file(), Some(std::path::Path::new(&path)));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), server.handle().self_signed_certificate().unwrap());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    drop(server);
+    assert!(!std::path::Path::new(&path).exists());
+}
+
+#[tokio::test]
+async fn tells_upstreams_and_the_openapi_document_about_https() {
+    let upstream = TestServer::from_yaml(&CONFIG.replace("{tls}", "")
+        .replace("handler: default\n    response: \"secure hello\"", "handler: template\n    response: \"{{ headers['x-forwarded-proto'] }}\"")).await.unwrap();
+    let proxied = format!("handler: proxy\n    response: \"\"\n    params:\n      upstream: \"{}\"", upstream.url(""));
+    let dir = temp_dir("scheme");
+    let certificate = write_certificate(&dir);
+    let tls = format!("openapi_path: /openapi.json\n  tls:\n    cert_file: {}\n    key_file: {}", dir.join("cert.pem").display(), dir.join("key.pem").display());
+    let server = TestServer::from_yaml(&config(&tls).replace("handler: default\n    response: \"secure hello\"", &proxied)).await.unwrap();
+
+    let response = https_get(&server, &certificate, "/hello").await.unwrap();
+    assert!(response.ends_with("\r\n\r\nhttps"), "{}", response);
+    let response = https_get(&server, &certificate, "/openapi.json").await.unwrap();
+    assert!(response.contains("\"servers\":[{\"url\":\"https://127.0.0.1:"), "{}", response);
+    std::fs::remove_dir_all(&dir).ok();
+}
+
+#[tokio::test]
+async fn redirects_http_to_https() {
+    let config = Config::from_yaml(&config("tls:\n    http_redirect_port: 0")).unwrap();
+    let server = TestServer::start(ServerBuilder::new(config).tls_self_signed(true)).await.unwrap();
+    let redirect_addr = server.handle().redirect_addr().unwrap();
+
+    let mut stream = TcpStream::connect(redirect_addr).await.unwrap();
+    let request = format!("GET /hello?name=x HTTP/1.1\r\nHost: localhost:{}\r\nConnection: close\r\n\r\n", redirect_addr.port());
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 308 Permanent Redirect"), "{}", response);
+    let location = format!("location: https://localhost:{}/hello?name=x", server.local_addr().port());
+    assert!(response.contains(&location), "{}", response);
+}
+
+#[tokio::test]
+async fn rejects_invalid_tls_configuration() {
+    let error = TestServer::from_yaml(&config("tls:\n    cert_file: cert.pem")).await.err().unwrap();
+    assert!(error.to_string().contains("server.tls: `cert_file` and `key_file` must be set together"), "{}", error);
+
+    let error = TestServer::from_yaml(&config("tls:\n    cert_file: missing.pem\n    key_file: missing.pem")).await.err().unwrap();
+    assert!(error.to_string().contains("server.tls: cannot read certificates from `missing.pem`"), "{}", error);
+
+    let error = TestServer::from_yaml(&config("tls: {}")).await.err().unwrap();
+    assert!(error.to_string().contains("`cert_file` and `key_file` are required"), "{}", error);
+}
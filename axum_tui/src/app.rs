@@ -1,10 +1,54 @@
-use std::time::{SystemTime, UNIX_EPOCH, Duration};
 use std::io::Write;
 use std::fs::File;
+use std::time::{Duration, Instant};
+use crate::log_event::{LogEvent, LogFilter};
 
-pub struct LogEntry {
-    pub timestamp: u64,
-    pub message: String,
+/// Index of the Logs tab
+pub const TAB_LOGS: usize = 0;
+/// Index of the Requests tab
+pub const TAB_REQUESTS: usize = 1;
+/// Index of the Server Info tab
+pub const TAB_SERVER_INFO: usize = 2;
+/// Number of tabs
+pub const TAB_COUNT: usize = 3;
+
+/// Maximum number of requests kept for the Requests tab
+const MAX_REQUESTS: usize = 500;
+
+/// Columns of the Requests table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestColumn {
+    Time,
+    Method,
+    Path,
+    Status,
+    Latency,
+    Size,
+    Handler,
+}
+
+impl RequestColumn {
+    pub const ALL: [RequestColumn; 7] = [
+        RequestColumn::Time,
+        RequestColumn::Method,
+        RequestColumn::Path,
+        RequestColumn::Status,
+        RequestColumn::Latency,
+        RequestColumn::Size,
+        RequestColumn::Handler,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            RequestColumn::Time => "Time",
+            RequestColumn::Method => "Method",
+            RequestColumn::Path => "Path",
+            RequestColumn::Status => "Status",
+            RequestColumn::Latency => "Latency",
+            RequestColumn::Size => "Size",
+            RequestColumn::Handler => "Handler",
+        }
+    }
 }
 
 pub enum AppMode {
@@ -13,22 +57,87 @@ pub enum AppMode {
     Filter,
 }
 
-#[derive(Clone)]
+/// Requests sent from the UI to the server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UiCommand {
+    /// Reload the configuration file
+    ReloadConfig,
+    /// Turn fault injection on or off for an endpoint, identified by its key, e.g. `GET /users`
+    ToggleFaults { endpoint: String },
+}
+
+/// Request statistics for an endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EndpointStats {
+    pub requests: u64,
+    /// Requests answered with a 4xx or 5xx status
+    pub errors: u64,
+    pub average_latency: Option<Duration>,
+    /// Upper bound of the 95th percentile latency, None if unknown
+    pub p95_latency: Option<Duration>,
+}
+
+/// Current state of a rate limit
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LimiterStats {
+    /// The configured limits, e.g. `10 per 1s, burst 20, per ip`
+    pub limits: String,
+    /// Clients with a partly used bucket or requests in flight
+    pub active_clients: usize,
+    /// Tokens left in the emptiest bucket, None without a rate limit
+    pub min_tokens: Option<f64>,
+    pub in_flight: u64,
+    /// Requests answered with 429 so far
+    pub rejected: u64,
+}
+
+/// Fault injection configured for an endpoint
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultInfo {
+    pub enabled: bool,
+    /// The configured faults, e.g. `delay 100ms-500ms, 10% 503`
+    pub faults: String,
+}
+
+#[derive(Clone, Default)]
 pub struct EndpointInfo {
+    /// Method and path with the listener and virtual host the endpoint is scoped to
+    pub key: String,
     pub path: String,
     pub method: String,
     pub description: String,
+    /// Set when only one listener serves the endpoint
+    pub listener: Option<String>,
+    /// Set when the endpoint is served for one virtual host
+    pub host: Option<String>,
+    pub stats: EndpointStats,
+    /// Set when the endpoint has its own rate limit
+    pub limiter: Option<LimiterStats>,
+    /// Set when the endpoint has faults configured
+    pub faults: Option<FaultInfo>,
+}
+
+/// An address the server accepts connections on
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListenerInfo {
+    pub name: String,
+    /// e.g. `127.0.0.1:3000` or `unix:/tmp/mock.sock`
+    pub address: String,
 }
 
 #[derive(Clone)]
 pub struct ServerInfo {
     pub host: String,
     pub port: u16,
+    /// Every listener, the one on the server's host and port first
+    pub listeners: Vec<ListenerInfo>,
     pub endpoints: Vec<EndpointInfo>,
+    /// Set when the server has a rate limit for all endpoints
+    pub limiter: Option<LimiterStats>,
 }
 
 pub struct AppUi {
-    pub logs: Vec<LogEntry>,
+    pub logs: Vec<LogEvent>,
     pub filtered_logs: Vec<usize>,
     pub scroll: usize,
     pub filter: String,
@@ -37,6 +146,25 @@ pub struct AppUi {
     pub show_timestamps: bool,
     pub selected_tab: usize,
     pub server_info: Option<ServerInfo>,
+    /// Completed requests, oldest first
+    pub requests: Vec<LogEvent>,
+    /// Selected row in the Requests table
+    pub request_selected: usize,
+    pub request_sort: RequestColumn,
+    pub request_sort_descending: bool,
+    /// Only show requests with this status class, e.g. 4 for 4xx
+    pub request_status_class: Option<u16>,
+    pub show_request_detail: bool,
+    /// Selected endpoint in the Server Info tab
+    pub endpoint_selected: usize,
+    /// When the UI was started, for the uptime
+    pub started: Instant,
+}
+
+impl Default for AppUi {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AppUi {
@@ -49,23 +177,30 @@ impl AppUi {
             filter_input: String::new(),
             mode: AppMode::Normal,
             show_timestamps: true,
-            selected_tab: 0,
+            selected_tab: TAB_LOGS,
             server_info: None,
+            requests: Vec::new(),
+            request_selected: 0,
+            request_sort: RequestColumn::Time,
+            request_sort_descending: true,
+            request_status_class: None,
+            show_request_detail: false,
+            endpoint_selected: 0,
+            started: Instant::now(),
         }
     }
 
-    pub fn add_log(&mut self, message: String) {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or(Duration::from_secs(0))
-            .as_secs();
-        
-        let log_entry = LogEntry {
-            timestamp,
-            message,
-        };
-        
-        self.logs.push(log_entry);
+    /// Add a log event, plain strings are logged as info messages
+    pub fn add_log(&mut self, event: impl Into<LogEvent>) {
+        let event = event.into();
+        if event.is_request() {
+            self.requests.push(event.clone());
+            if self.requests.len() > MAX_REQUESTS {
+                self.requests.remove(0);
+            }
+        }
+
+        self.logs.push(event);
         
         if self.logs.len() > 1000 {
             self.logs.remove(0);
@@ -75,11 +210,12 @@ impl AppUi {
     }
     
     pub fn update_filtered_logs(&mut self) {
-        if self.filter.is_empty() {
+        let filter = LogFilter::parse(&self.filter);
+        if filter.is_empty() {
             self.filtered_logs = (0..self.logs.len()).collect();
         } else {
             self.filtered_logs = self.logs.iter().enumerate()
-                .filter(|(_, log)| log.message.to_lowercase().contains(&self.filter.to_lowercase()))
+                .filter(|(_, log)| filter.matches(log))
                 .map(|(i, _)| i)
                 .collect();
         }
@@ -101,6 +237,68 @@ impl AppUi {
         self.scroll = 0;
     }
     
+    /// Indices into `requests` in display order, after filtering and sorting
+    pub fn visible_requests(&self) -> Vec<usize> {
+        let mut visible: Vec<usize> = (0..self.requests.len())
+            .filter(|&i| match self.request_status_class {
+                Some(class) => self.requests[i].status.is_some_and(|status| status / 100 == class),
+                None => true,
+            })
+            .collect();
+
+        // Requests are stored in arrival order, so sorting by index sorts by time
+        visible.sort_by(|&a, &b| {
+            let (ra, rb) = (&self.requests[a], &self.requests[b]);
+            let ordering = match self.request_sort {
+                RequestColumn::Time => a.cmp(&b),
+                RequestColumn::Method => ra.method.cmp(&rb.method),
+                RequestColumn::Path => ra.path.cmp(&rb.path),
+                RequestColumn::Status => ra.status.cmp(&rb.status),
+                RequestColumn::Latency => ra.latency.cmp(&rb.latency),
+                RequestColumn::Size => ra.response_size.cmp(&rb.response_size),
+                RequestColumn::Handler => ra.handler.cmp(&rb.handler),
+            };
+            let ordering = ordering.then(a.cmp(&b));
+            if self.request_sort_descending { ordering.reverse() } else { ordering }
+        });
+
+        visible
+    }
+
+    /// The request selected in the Requests table
+    pub fn selected_request(&self) -> Option<&LogEvent> {
+        self.visible_requests()
+            .get(self.request_selected)
+            .map(|&i| &self.requests[i])
+    }
+
+    /// Sort the Requests table by a column, reversing the order if it is already sorted by it
+    pub fn sort_requests_by(&mut self, column: RequestColumn) {
+        if self.request_sort == column {
+            self.request_sort_descending = !self.request_sort_descending;
+        } else {
+            self.request_sort = column;
+            self.request_sort_descending = false;
+        }
+        self.request_selected = 0;
+    }
+
+    /// Cycle the status filter through all, 2xx to 5xx and back
+    pub fn cycle_request_status_filter(&mut self) {
+        self.request_status_class = match self.request_status_class {
+            None => Some(2),
+            Some(class) if class < 5 => Some(class + 1),
+            Some(_) => None,
+        };
+        self.request_selected = 0;
+    }
+
+    pub fn clear_requests(&mut self) {
+        self.requests.clear();
+        self.request_selected = 0;
+        self.show_request_detail = false;
+    }
+
     pub fn format_timestamp(&self, timestamp: u64) -> String {
         let secs = timestamp % 60;
         let mins = (timestamp / 60) % 60;
@@ -113,14 +311,14 @@ impl AppUi {
         
         writeln!(file, "Server Logs Export")?;
         writeln!(file, "===================")?;
-        writeln!(file, "")?;
+        writeln!(file)?;
         
         for idx in &self.filtered_logs {
             let log = &self.logs[*idx];
             if self.show_timestamps {
-                writeln!(file, "[{}] {}", self.format_timestamp(log.timestamp), log.message)?;
+                writeln!(file, "[{}] {:<5} [{}] {}", self.format_timestamp(log.timestamp), log.level, log.source, log.text())?;
             } else {
-                writeln!(file, "{}", log.message)?;
+                writeln!(file, "{:<5} [{}] {}", log.level, log.source, log.text())?;
             }
         }
         
@@ -1,8 +1,10 @@
 pub mod app;
 pub mod ui;
+pub mod log_event;
 mod util;
 
-pub use app::{AppUi, ServerInfo, EndpointInfo};
+pub use app::{AppUi, ServerInfo, EndpointInfo, EndpointStats, FaultInfo, LimiterStats, ListenerInfo, UiCommand};
+pub use log_event::{BodyPreview, HttpExchange, LogEvent, LogFilter, LogLevel};
 
 use std::io::stdout;
 use std::sync::mpsc;
@@ -13,9 +15,10 @@ use ratatui::Terminal;
 use ratatui::backend::CrosstermBackend;
 
 pub fn run_ui(
-    log_rx: mpsc::Receiver<String>, 
+    log_rx: mpsc::Receiver<LogEvent>, 
     shutdown_tx: watch::Sender<()>,
-    server_info: Option<ServerInfo>
+    mut server_info_rx: watch::Receiver<Option<ServerInfo>>,
+    command_tx: mpsc::Sender<UiCommand>,
 ) {
     // Set up the terminal with Crossterm backend
     enable_raw_mode().unwrap();
@@ -26,20 +29,21 @@ pub fn run_ui(
     // Initialize app state
     let mut app = AppUi::new();
     
-    // Set server info if provided
-    if let Some(info) = server_info {
-        app.server_info = Some(info);
-    }
-
     // Main UI loop
     loop {
+        // Pick up server info changes, e.g. after a configuration reload
+        if app.server_info.is_none() || server_info_rx.has_changed().unwrap_or(false) {
+            app.server_info = server_info_rx.borrow_and_update().clone();
+        }
+
+
         // Draw the UI
         terminal
             .draw(|f| ui::draw_ui(f, &mut app))
             .unwrap();
 
         // Handle events, break the loop if handle_events returns false
-        if !ui::handle_events(&mut app, &log_rx, &shutdown_tx) {
+        if !ui::handle_events(&mut app, &log_rx, &shutdown_tx, &command_tx) {
             break;
         }
     }
@@ -0,0 +1,395 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde_json::{Map, Value};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+/// Severity of a log event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+        }
+    }
+
+    /// Parse a level name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "debug" => Some(LogLevel::Debug),
+            "info" => Some(LogLevel::Info),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "error" => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Maximum number of bytes of a body kept for display
+pub const BODY_PREVIEW_LIMIT: usize = 4096;
+
+/// Start of a request or response body, kept for display
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BodyPreview {
+    /// Size of the whole body in bytes
+    pub size: usize,
+    /// The body as text, cut at `BODY_PREVIEW_LIMIT` bytes. Empty for binary bodies.
+    pub text: String,
+    pub truncated: bool,
+    pub binary: bool,
+    /// The body was passed through without being read, e.g. by a proxy
+    pub streamed: bool,
+}
+
+impl BodyPreview {
+    pub fn from_bytes(body: &[u8]) -> Self {
+        let end = body.len().min(BODY_PREVIEW_LIMIT);
+        let (text, binary) = match std::str::from_utf8(&body[..end]) {
+            Ok(text) => (text.to_string(), false),
+            // The limit may split a multi-byte character, keep the valid part
+            Err(e) if e.error_len().is_none() => (String::from_utf8_lossy(&body[..e.valid_up_to()]).into_owned(), false),
+            Err(_) => (String::new(), true),
+        };
+
+        Self {
+            size: body.len(),
+            text,
+            truncated: end < body.len(),
+            binary,
+            streamed: false,
+        }
+    }
+
+    /// Preview of a body that was streamed through unread
+    pub fn streamed() -> Self {
+        Self {
+            streamed: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Headers and bodies of a request and its response
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct HttpExchange {
+    pub query: Option<String>,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: BodyPreview,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: BodyPreview,
+}
+
+/// A structured log event sent from the server to the UI
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEvent {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub level: LogLevel,
+    /// Part of the application that produced the event, e.g. `server`, `request` or `config`
+    pub source: String,
+    pub message: String,
+    pub request_id: Option<String>,
+    pub method: Option<String>,
+    pub path: Option<String>,
+    pub status: Option<u16>,
+    pub latency: Option<Duration>,
+    pub handler: Option<String>,
+    /// Size of the response body in bytes
+    pub response_size: Option<u64>,
+    /// Headers and bodies of the request, shown in the Requests tab
+    pub exchange: Option<Arc<HttpExchange>>,
+    /// Additional key/value pairs, in insertion order
+    pub fields: Vec<(String, String)>,
+}
+
+impl LogEvent {
+    /// Create an event from the `server` source, timestamped now
+    pub fn new(level: LogLevel, message: impl Into<String>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::from_secs(0))
+            .as_secs();
+
+        Self {
+            timestamp,
+            level,
+            source: "server".to_string(),
+            message: message.into(),
+            request_id: None,
+            method: None,
+            path: None,
+            status: None,
+            latency: None,
+            handler: None,
+            response_size: None,
+            exchange: None,
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn debug(message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Debug, message)
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Info, message)
+    }
+
+    pub fn warn(message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Warn, message)
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(LogLevel::Error, message)
+    }
+
+    /// Create an event for a completed request, with the level derived from the status code
+    pub fn request(method: impl Into<String>, path: impl Into<String>, status: u16, latency: Duration) -> Self {
+        let level = match status {
+            500.. => LogLevel::Error,
+            400..=499 => LogLevel::Warn,
+            _ => LogLevel::Info,
+        };
+
+        let mut event = Self::new(level, "").with_source("request");
+        event.method = Some(method.into());
+        event.path = Some(path.into());
+        event.status = Some(status);
+        event.latency = Some(latency);
+        event
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = source.into();
+        self
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn with_handler(mut self, handler: impl Into<String>) -> Self {
+        self.handler = Some(handler.into());
+        self
+    }
+
+    pub fn with_exchange(mut self, exchange: HttpExchange) -> Self {
+        self.response_size = Some(exchange.response_body.size as u64);
+        self.exchange = Some(Arc::new(exchange));
+        self
+    }
+
+    /// Whether the event describes a completed request
+    pub fn is_request(&self) -> bool {
+        self.method.is_some() && self.status.is_some()
+    }
+
+    /// Add a free-form key/value field
+    pub fn with_field(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.fields.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// Value of a free-form field
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// One-line description of the event, without timestamp and level
+    pub fn text(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let (Some(method), Some(path)) = (&self.method, &self.path) {
+            parts.push(format!("{} {}", method, path));
+        }
+        if let Some(status) = self.status {
+            parts.push(format!("-> {}", status));
+        }
+        if let Some(latency) = self.latency {
+            parts.push(format!("({:.1}ms)", latency.as_secs_f64() * 1000.0));
+        }
+        if let Some(handler) = &self.handler {
+            parts.push(format!("[{}]", handler));
+        }
+        if !self.message.is_empty() {
+            parts.push(self.message.clone());
+        }
+        if let Some(request_id) = &self.request_id {
+            parts.push(format!("id={}", request_id));
+        }
+        for (key, value) in &self.fields {
+            parts.push(format!("{}={}", key, value));
+        }
+
+        parts.join(" ")
+    }
+
+    /// Timestamp in RFC 3339 format, in UTC
+    pub fn timestamp_rfc3339(&self) -> String {
+        OffsetDateTime::from_unix_timestamp(self.timestamp as i64)
+            .ok()
+            .and_then(|time| time.format(&Rfc3339).ok())
+            .unwrap_or_default()
+    }
+
+    /// The event as a JSON object, leaving out fields that are not set
+    pub fn to_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("timestamp".to_string(), self.timestamp_rfc3339().into());
+        object.insert("level".to_string(), self.level.as_str().to_lowercase().into());
+        object.insert("source".to_string(), self.source.clone().into());
+        if !self.message.is_empty() {
+            object.insert("message".to_string(), self.message.clone().into());
+        }
+        if let Some(request_id) = &self.request_id {
+            object.insert("request_id".to_string(), request_id.clone().into());
+        }
+        if let Some(method) = &self.method {
+            object.insert("method".to_string(), method.clone().into());
+        }
+        if let Some(path) = &self.path {
+            object.insert("path".to_string(), path.clone().into());
+        }
+        if let Some(status) = self.status {
+            object.insert("status".to_string(), status.into());
+        }
+        if let Some(latency) = self.latency {
+            object.insert("latency_ms".to_string(), (latency.as_secs_f64() * 1000.0).into());
+        }
+        if let Some(handler) = &self.handler {
+            object.insert("handler".to_string(), handler.clone().into());
+        }
+        if let Some(size) = self.response_size {
+            object.insert("response_size".to_string(), size.into());
+        }
+        if !self.fields.is_empty() {
+            let fields = self.fields.iter()
+                .map(|(key, value)| (key.clone(), Value::from(value.clone())))
+                .collect();
+            object.insert("fields".to_string(), Value::Object(fields));
+        }
+        Value::Object(object)
+    }
+}
+
+impl From<String> for LogEvent {
+    fn from(message: String) -> Self {
+        Self::info(message)
+    }
+}
+
+impl From<&str> for LogEvent {
+    fn from(message: &str) -> Self {
+        Self::info(message)
+    }
+}
+
+impl fmt::Display for LogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<5} {}", self.level, self.text())
+    }
+}
+
+/// Filter for log events, parsed from the filter input.
+///
+/// Terms are separated by whitespace and all of them must match. A term is either
+/// free text or `key:value`, where key is one of `level` (minimum level), `source`,
+/// `method`, `path` (prefix), `status` (`404` or `4xx`), `handler`, `id` or a field name.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    terms: Vec<FilterTerm>,
+}
+
+#[derive(Debug, Clone)]
+enum FilterTerm {
+    Text(String),
+    Level(LogLevel),
+    Source(String),
+    Method(String),
+    Path(String),
+    Status(String),
+    Handler(String),
+    RequestId(String),
+    Field(String, String),
+}
+
+impl LogFilter {
+    pub fn parse(input: &str) -> Self {
+        let terms = input.split_whitespace().map(|term| {
+            let Some((key, value)) = term.split_once(':') else {
+                return FilterTerm::Text(term.to_lowercase());
+            };
+
+            match key.to_ascii_lowercase().as_str() {
+                "level" => LogLevel::parse(value)
+                    .map(FilterTerm::Level)
+                    .unwrap_or_else(|| FilterTerm::Text(term.to_lowercase())),
+                "source" => FilterTerm::Source(value.to_lowercase()),
+                "method" => FilterTerm::Method(value.to_uppercase()),
+                "path" => FilterTerm::Path(value.to_string()),
+                "status" => FilterTerm::Status(value.to_lowercase()),
+                "handler" => FilterTerm::Handler(value.to_string()),
+                "id" => FilterTerm::RequestId(value.to_string()),
+                _ => FilterTerm::Field(key.to_string(), value.to_string()),
+            }
+        }).collect();
+
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        self.terms.iter().all(|term| term.matches(event))
+    }
+}
+
+impl FilterTerm {
+    fn matches(&self, event: &LogEvent) -> bool {
+        match self {
+            FilterTerm::Text(text) => event.text().to_lowercase().contains(text),
+            FilterTerm::Level(level) => event.level >= *level,
+            FilterTerm::Source(source) => event.source.to_lowercase() == *source,
+            FilterTerm::Method(method) => event.method.as_deref() == Some(method.as_str()),
+            FilterTerm::Path(path) => event.path.as_deref().is_some_and(|p| p.starts_with(path.as_str())),
+            FilterTerm::Status(status) => event.status.is_some_and(|code| status_matches(status, code)),
+            FilterTerm::Handler(handler) => event.handler.as_deref() == Some(handler.as_str()),
+            FilterTerm::RequestId(id) => event.request_id.as_deref() == Some(id.as_str()),
+            // Terms like `http://host` are not fields, so fall back to a text search
+            FilterTerm::Field(key, value) => match event.field(key) {
+                Some(field) => field == value,
+                None => event.text().to_lowercase().contains(&format!("{}:{}", key, value).to_lowercase()),
+            },
+        }
+    }
+}
+
+// Match a status code against `404` or a class like `4xx`
+fn status_matches(pattern: &str, status: u16) -> bool {
+    match pattern.strip_suffix("xx") {
+        Some(class) => class.parse::<u16>().is_ok_and(|class| status / 100 == class),
+        None => pattern.parse::<u16>().is_ok_and(|code| code == status),
+    }
+}
@@ -1,7 +1,9 @@
+use std::time::Duration;
 use ratatui::prelude::*;
-use ratatui::widgets::{Block, Borders, List, ListState, Paragraph, Tabs, Wrap, ListItem, Clear};
+use ratatui::widgets::{Block, Borders, Cell, List, ListState, Paragraph, Row, Table, TableState, Tabs, Wrap, ListItem, Clear};
 use ratatui::layout::{Layout, Direction, Constraint};
-use crate::app::{AppUi, AppMode};
+use crate::app::{AppUi, AppMode, EndpointInfo, LimiterStats, RequestColumn, TAB_LOGS, TAB_REQUESTS, TAB_SERVER_INFO};
+use crate::log_event::{BodyPreview, LogEvent, LogLevel};
 use crate::util::centered_rect;
 
 pub fn draw_ui(f: &mut Frame, app: &mut AppUi) {
@@ -18,7 +20,7 @@ pub fn draw_ui(f: &mut Frame, app: &mut AppUi) {
         .split(size);
     
     // Title bar with tabs
-    let titles = vec!["Logs", "Server Info"];
+    let titles = vec!["Logs", "Requests", "Server Info"];
     let tabs = Tabs::new(titles)
         .block(Block::default().borders(Borders::ALL).title("Axum Server Monitor"))
         .highlight_style(Style::default().fg(Color::Yellow))
@@ -29,11 +31,7 @@ pub fn draw_ui(f: &mut Frame, app: &mut AppUi) {
     match app.mode {
         AppMode::Normal | AppMode::Filter => {
             // Display content based on selected tab
-            match app.selected_tab {
-                0 => draw_logs(f, app, chunks[1]),
-                1 => draw_server_info(f, chunks[1], app),
-                _ => {}
-            }
+            draw_tab(f, app, chunks[1]);
             
             // If in filter mode, show the filter input
             if let AppMode::Filter = app.mode {
@@ -47,11 +45,7 @@ pub fn draw_ui(f: &mut Frame, app: &mut AppUi) {
         },
         AppMode::Help => {
             // First render the content based on selected tab
-            match app.selected_tab {
-                0 => draw_logs(f, app, chunks[1]),
-                1 => draw_server_info(f, chunks[1], app),
-                _ => {}
-            }
+            draw_tab(f, app, chunks[1]);
             
             // Then render help screen as an overlay
             draw_help(f, size);
@@ -62,16 +56,30 @@ pub fn draw_ui(f: &mut Frame, app: &mut AppUi) {
     draw_status_bar(f, app, chunks[2]);
 }
 
+fn draw_tab(f: &mut Frame, app: &mut AppUi, area: Rect) {
+    match app.selected_tab {
+        TAB_LOGS => draw_logs(f, app, area),
+        TAB_REQUESTS => draw_requests(f, app, area),
+        TAB_SERVER_INFO => draw_server_info(f, area, app),
+        _ => {}
+    }
+}
+
 fn draw_logs(f: &mut Frame, app: &mut AppUi, area: Rect) {
     let log_items: Vec<ListItem> = app.filtered_logs.iter()
         .map(|&idx| {
             let log = &app.logs[idx];
-            let content = if app.show_timestamps {
-                format!("[{}] {}", app.format_timestamp(log.timestamp), log.message)
-            } else {
-                log.message.clone()
-            };
-            ListItem::new(content)
+            let mut spans = Vec::new();
+            if app.show_timestamps {
+                spans.push(Span::styled(
+                    format!("[{}] ", app.format_timestamp(log.timestamp)),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            spans.push(Span::styled(format!("{:<5} ", log.level), level_style(log.level)));
+            spans.push(Span::styled(format!("{:<7} ", log.source), Style::default().fg(Color::Cyan)));
+            spans.push(Span::styled(log.text(), event_style(log)));
+            ListItem::new(Line::from(spans))
         })
         .collect();
     
@@ -90,6 +98,195 @@ fn draw_logs(f: &mut Frame, app: &mut AppUi, area: Rect) {
     f.render_stateful_widget(logs_list, area, &mut list_state);
 }
 
+fn draw_requests(f: &mut Frame, app: &mut AppUi, area: Rect) {
+    let visible = app.visible_requests();
+    if !visible.is_empty() && app.request_selected >= visible.len() {
+        app.request_selected = visible.len() - 1;
+    }
+
+    // Split the area when the detail pane is open
+    let (table_area, detail_area) = if app.show_request_detail && !visible.is_empty() {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+            .split(area);
+        (chunks[0], Some(chunks[1]))
+    } else {
+        (area, None)
+    };
+
+    // Mark the sort column in the header
+    let header = Row::new(RequestColumn::ALL.iter().enumerate().map(|(i, column)| {
+        let arrow = match (app.request_sort == *column, app.request_sort_descending) {
+            (true, true) => " v",
+            (true, false) => " ^",
+            (false, _) => "",
+        };
+        Cell::from(format!("{}:{}{}", i + 1, column.title(), arrow))
+    }))
+    .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+
+    let rows: Vec<Row> = visible.iter().map(|&idx| {
+        let request = &app.requests[idx];
+        Row::new(vec![
+            Cell::from(app.format_timestamp(request.timestamp)),
+            Cell::from(request.method.clone().unwrap_or_default()),
+            Cell::from(request.path.clone().unwrap_or_default()),
+            Cell::from(request.status.map(|s| s.to_string()).unwrap_or_default()).style(status_style(request.status)),
+            Cell::from(request.latency.map(format_latency).unwrap_or_default()),
+            Cell::from(request.response_size.map(format_size).unwrap_or_default()),
+            Cell::from(request.handler.clone().unwrap_or_default()),
+        ])
+    }).collect();
+
+    let widths = [
+        Constraint::Length(10),
+        Constraint::Length(9),
+        Constraint::Min(20),
+        Constraint::Length(9),
+        Constraint::Length(11),
+        Constraint::Length(9),
+        Constraint::Length(14),
+    ];
+
+    let filter = match app.request_status_class {
+        Some(class) => format!(" | Status: {}xx", class),
+        None => String::new(),
+    };
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default()
+            .title(format!("Requests ({}/{}){}", visible.len(), app.requests.len(), filter))
+            .borders(Borders::ALL))
+        .highlight_style(Style::default().bg(Color::DarkGray))
+        .highlight_symbol(">> ");
+
+    let mut table_state = TableState::default();
+    if !visible.is_empty() {
+        table_state.select(Some(app.request_selected));
+    }
+    f.render_stateful_widget(table, table_area, &mut table_state);
+
+    if let (Some(detail_area), Some(request)) = (detail_area, app.selected_request()) {
+        draw_request_detail(f, request, detail_area);
+    }
+}
+
+fn draw_request_detail(f: &mut Frame, request: &LogEvent, area: Rect) {
+    let heading = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+
+    let mut target = request.path.clone().unwrap_or_default();
+    if let Some(query) = request.exchange.as_ref().and_then(|e| e.query.as_ref()) {
+        target = format!("{}?{}", target, query);
+    }
+    lines.push(Line::from(vec![
+        Span::styled(format!("{} {} ", request.method.as_deref().unwrap_or(""), target), heading),
+        Span::styled(request.status.map(|s| s.to_string()).unwrap_or_default(), status_style(request.status)),
+    ]));
+    lines.push(Line::from(format!("Request id: {}  Handler: {}  Latency: {}",
+        request.request_id.as_deref().unwrap_or("-"),
+        request.handler.as_deref().unwrap_or("-"),
+        request.latency.map(format_latency).unwrap_or_default())));
+    for (key, value) in &request.fields {
+        lines.push(Line::from(format!("{}: {}", key, value)));
+    }
+
+    if let Some(exchange) = &request.exchange {
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Request headers:", heading));
+        lines.extend(exchange.request_headers.iter().map(|(k, v)| Line::from(format!("  {}: {}", k, v))));
+        lines.push(Line::from(""));
+        lines.push(Line::styled(body_heading("Request body", &exchange.request_body), heading));
+        lines.extend(body_lines(&exchange.request_body));
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Response headers:", heading));
+        lines.extend(exchange.response_headers.iter().map(|(k, v)| Line::from(format!("  {}: {}", k, v))));
+        lines.push(Line::from(""));
+        lines.push(Line::styled(body_heading("Response body", &exchange.response_body), heading));
+        lines.extend(body_lines(&exchange.response_body));
+    }
+
+    let detail = Paragraph::new(lines)
+        .block(Block::default().title("Request Detail (Esc to close)").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    f.render_widget(detail, area);
+}
+
+fn body_heading(title: &str, body: &BodyPreview) -> String {
+    if body.streamed {
+        format!("{}:", title)
+    } else {
+        format!("{} ({}):", title, format_size(body.size as u64))
+    }
+}
+
+fn body_lines(body: &BodyPreview) -> Vec<Line<'static>> {
+    let muted = Style::default().fg(Color::DarkGray);
+    if body.streamed {
+        return vec![Line::styled("  <streamed, not captured>", muted)];
+    }
+    if body.binary {
+        return vec![Line::styled("  <binary data>", muted)];
+    }
+    if body.size == 0 {
+        return vec![Line::styled("  <empty>", muted)];
+    }
+
+    let mut lines: Vec<Line> = body.text.lines().map(|line| Line::from(format!("  {}", line))).collect();
+    if body.truncated {
+        lines.push(Line::styled(format!("  ... truncated, {} shown", format_size(body.text.len() as u64)), muted));
+    }
+    lines
+}
+
+fn format_latency(latency: Duration) -> String {
+    format!("{:.1}ms", latency.as_secs_f64() * 1000.0)
+}
+
+fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    format!("{}h {:02}m {:02}s", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+fn format_size(size: u64) -> String {
+    match size {
+        0..=1023 => format!("{} B", size),
+        1024..=1_048_575 => format!("{:.1} KB", size as f64 / 1024.0),
+        _ => format!("{:.1} MB", size as f64 / 1_048_576.0),
+    }
+}
+
+fn status_style(status: Option<u16>) -> Style {
+    match status {
+        Some(500..) => Style::default().fg(Color::Red),
+        Some(400..=499) => Style::default().fg(Color::Yellow),
+        Some(300..=399) => Style::default().fg(Color::Blue),
+        Some(200..=299) => Style::default().fg(Color::Green),
+        _ => Style::default(),
+    }
+}
+
+fn level_style(level: LogLevel) -> Style {
+    match level {
+        LogLevel::Debug => Style::default().fg(Color::DarkGray),
+        LogLevel::Info => Style::default().fg(Color::Green),
+        LogLevel::Warn => Style::default().fg(Color::Yellow),
+        LogLevel::Error => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+    }
+}
+
+// Requests are colored by status class, other events by level
+fn event_style(event: &LogEvent) -> Style {
+    match (event.status, event.level) {
+        (Some(500..), _) | (None, LogLevel::Error) => Style::default().fg(Color::Red),
+        (Some(400..=499), _) | (None, LogLevel::Warn) => Style::default().fg(Color::Yellow),
+        (Some(300..=399), _) => Style::default().fg(Color::Blue),
+        (_, LogLevel::Debug) => Style::default().fg(Color::DarkGray),
+        _ => Style::default(),
+    }
+}
+
 fn draw_help(f: &mut Frame, size: Rect) {
     let help_text = vec![
         "Keyboard Controls:",
@@ -98,7 +295,8 @@ fn draw_help(f: &mut Frame, size: Rect) {
         "h       - Toggle help screen",
         "c       - Clear logs",
         "t       - Toggle timestamps",
-        "f       - Filter logs",
+        "f       - Filter logs (text, level:warn, status:5xx)",
+        "r       - Reload configuration",
         "Tab     - Next tab",
         "Shift+Tab - Previous tab",
         "Ctrl+s  - Save logs to file",
@@ -108,12 +306,21 @@ fn draw_help(f: &mut Frame, size: Rect) {
         "PgDown  - Scroll down 10 entries",
         "Home    - Jump to first log",
         "End     - Jump to last log",
+        "",
+        "Requests tab:",
+        "1-7     - Sort by column (again to reverse)",
+        "x       - Filter by status class",
+        "Enter   - Show/hide request details",
+        "",
+        "Server Info tab:",
+        "Up/Down - Select an endpoint",
+        "d       - Toggle the endpoint's faults",
     ].join("\n");
     
     // Create a floating help panel
     let help_area = {
         let popup_width = 60;
-        let popup_height = 16;
+        let popup_height = 28;
         
         let x = (size.width.saturating_sub(popup_width)) / 2;
         let y = (size.height.saturating_sub(popup_height)) / 2;
@@ -141,10 +348,12 @@ fn draw_status_bar(f: &mut Frame, app: &AppUi, area: Rect) {
     let status = match app.mode {
         AppMode::Normal => {
             match app.selected_tab {
-                0 => format!("Logs Tab | Logs: {} | Filter: {} | Press 'h' for help", 
+                TAB_LOGS => format!("Logs Tab | Logs: {} | Filter: {} | Press 'h' for help", 
                           app.logs.len(), 
                           if app.filter.is_empty() { "None".to_string() } else { app.filter.clone() }),
-                1 => "Server Info Tab | Server: localhost:3000 | Press 'h' for help".to_string(),
+                TAB_REQUESTS => format!("Requests Tab | Requests: {} | 1-7: sort, x: status filter, Enter: details | Press 'h' for help",
+                          app.requests.len()),
+                TAB_SERVER_INFO => "Server Info Tab | Server: localhost:3000 | Up/Down: select endpoint, d: toggle faults | Press 'h' for help".to_string(),
                 _ => "Press 'h' for help".to_string(),
             }
         },
@@ -157,6 +366,42 @@ fn draw_status_bar(f: &mut Frame, app: &AppUi, area: Rect) {
     f.render_widget(status_bar, area);
 }
 
+// Limits and current state of a rate limit, e.g. `10 per 1s, per ip: 2 active, 3.5 tokens left, 1 in flight, 4 rejected`
+fn limiter_line(limiter: &LimiterStats) -> String {
+    let mut state = vec![format!("{} active", limiter.active_clients)];
+    if let Some(tokens) = limiter.min_tokens {
+        state.push(format!("{:.1} tokens left", tokens));
+    }
+    state.push(format!("{} in flight", limiter.in_flight));
+    state.push(format!("{} rejected", limiter.rejected));
+    format!("{}: {}", limiter.limits, state.join(", "))
+}
+
+// An endpoint of the Server Info tab with its statistics, limiter and faults
+fn endpoint_lines(lines: &mut Vec<String>, endpoint: &EndpointInfo, selected: bool) {
+    lines.push(format!("  {} {} {}{} : {}",
+        if selected { ">" } else { "-" },
+        endpoint.method,
+        endpoint.path,
+        endpoint.host.as_ref().map(|host| format!(" ({})", host)).unwrap_or_default(),
+        endpoint.description));
+
+    let stats = &endpoint.stats;
+    if stats.requests > 0 {
+        lines.push(format!("      {} requests, {} errors, avg {}, p95 {}",
+            stats.requests,
+            stats.errors,
+            stats.average_latency.map(format_latency).unwrap_or_else(|| "-".to_string()),
+            stats.p95_latency.map(|l| format!("<= {}", format_latency(l))).unwrap_or_else(|| "> 10s".to_string())));
+    }
+    if let Some(limiter) = &endpoint.limiter {
+        lines.push(format!("      limit {}", limiter_line(limiter)));
+    }
+    if let Some(faults) = &endpoint.faults {
+        lines.push(format!("      faults {}: {}", if faults.enabled { "ON" } else { "off" }, faults.faults));
+    }
+}
+
 fn draw_server_info(f: &mut Frame, area: Rect, app: &AppUi) {
     let mut info_lines = Vec::new();
     
@@ -167,14 +412,27 @@ fn draw_server_info(f: &mut Frame, area: Rect, app: &AppUi) {
         info_lines.push(format!("Host: {}", server_info.host));
         info_lines.push(format!("Port: {}", server_info.port));
         info_lines.push("Status: Running".to_string());
+        if let Some(limiter) = &server_info.limiter {
+            info_lines.push(format!("Rate limit: {}", limiter_line(limiter)));
+        }
         info_lines.push("".to_string());
         
-        info_lines.push("Available Endpoints:".to_string());
-        for endpoint in &server_info.endpoints {
-            info_lines.push(format!("  - {} {} : {}", 
-                endpoint.method, 
-                endpoint.path, 
-                endpoint.description));
+        if server_info.listeners.len() > 1 {
+            // Endpoints without a listener are listed under each one
+            for listener in &server_info.listeners {
+                info_lines.push(format!("Listener {} on {}:", listener.name, listener.address));
+                for (index, endpoint) in server_info.endpoints.iter().enumerate() {
+                    if endpoint.listener.as_ref().is_none_or(|name| *name == listener.name) {
+                        endpoint_lines(&mut info_lines, endpoint, index == app.endpoint_selected);
+                    }
+                }
+                info_lines.push("".to_string());
+            }
+        } else {
+            info_lines.push("Available Endpoints:".to_string());
+            for (index, endpoint) in server_info.endpoints.iter().enumerate() {
+                endpoint_lines(&mut info_lines, endpoint, index == app.endpoint_selected);
+            }
         }
     } else {
         info_lines.push("Host: 127.0.0.1".to_string());
@@ -190,7 +448,12 @@ fn draw_server_info(f: &mut Frame, area: Rect, app: &AppUi) {
     
     info_lines.push("".to_string());
     info_lines.push("Server Statistics:".to_string());
-    info_lines.push(format!("  - Uptime: Since application start"));
+    info_lines.push(format!("  - Uptime: {}", format_uptime(app.started.elapsed())));
+    if let Some(server_info) = &app.server_info {
+        let requests: u64 = server_info.endpoints.iter().map(|e| e.stats.requests).sum();
+        let errors: u64 = server_info.endpoints.iter().map(|e| e.stats.errors).sum();
+        info_lines.push(format!("  - Requests: {} ({} errors)", requests, errors));
+    }
     info_lines.push(format!("  - Total Logs: {}", app.logs.len()));
     
     let server_info = info_lines.join("\n");
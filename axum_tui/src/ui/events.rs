@@ -3,16 +3,18 @@ use tokio::sync::watch;
 use std::sync::mpsc;
 use std::time::Duration;
 
-use crate::app::{AppUi, AppMode};
+use crate::app::{AppUi, AppMode, RequestColumn, UiCommand, TAB_COUNT, TAB_REQUESTS, TAB_SERVER_INFO};
+use crate::log_event::LogEvent;
 
 pub fn handle_events(
     app: &mut AppUi, 
-    log_rx: &mpsc::Receiver<String>,
-    shutdown_tx: &watch::Sender<()>
+    log_rx: &mpsc::Receiver<LogEvent>,
+    shutdown_tx: &watch::Sender<()>,
+    command_tx: &mpsc::Sender<UiCommand>,
 ) -> bool {
     // Collect new log messages
-    while let Ok(msg) = log_rx.try_recv() {
-        app.add_log(msg);
+    while let Ok(event) = log_rx.try_recv() {
+        app.add_log(event);
     }
 
     // Handle keyboard events
@@ -20,6 +22,15 @@ pub fn handle_events(
         if let Event::Key(key) = event::read().unwrap() {
             // Only process key press events, not key release events
             if key.kind == KeyEventKind::Press {
+                // The Requests tab has its own navigation keys
+                if matches!(app.mode, AppMode::Normal) && app.selected_tab == TAB_REQUESTS && handle_requests_key(app, key.code) {
+                    return true;
+                }
+                // So has the Server Info tab, for selecting endpoints
+                if matches!(app.mode, AppMode::Normal) && app.selected_tab == TAB_SERVER_INFO && handle_server_info_key(app, key.code, command_tx) {
+                    return true;
+                }
+
                 match app.mode {
                     AppMode::Normal => match key.code {
                         KeyCode::Char('q') => {
@@ -35,15 +46,20 @@ pub fn handle_events(
                         KeyCode::Char('t') => {
                             app.show_timestamps = !app.show_timestamps;
                         },
+                        KeyCode::Char('r') => {
+                            command_tx.send(UiCommand::ReloadConfig).unwrap_or_else(|_| {
+                                app.add_log(LogEvent::warn("Configuration reload is not available"));
+                            });
+                        },
                         KeyCode::Char('f') => {
                             app.mode = AppMode::Filter;
                             app.filter_input = app.filter.clone();
                         },
                         KeyCode::Tab => {
-                            app.selected_tab = (app.selected_tab + 1) % 2; // Cycle through tabs
+                            app.selected_tab = (app.selected_tab + 1) % TAB_COUNT; // Cycle through tabs
                         },
                         KeyCode::BackTab => {
-                            app.selected_tab = if app.selected_tab > 0 { app.selected_tab - 1 } else { 1 };
+                            app.selected_tab = if app.selected_tab > 0 { app.selected_tab - 1 } else { TAB_COUNT - 1 };
                         },
                         KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
                             match app.save_logs_to_file() {
@@ -51,19 +67,15 @@ pub fn handle_events(
                                     app.add_log("Logs saved to server_logs.txt".to_string());
                                 },
                                 Err(e) => {
-                                    app.add_log(format!("Error saving logs: {}", e));
+                                    app.add_log(LogEvent::error(format!("Error saving logs: {}", e)));
                                 }
                             }
                         },
-                        KeyCode::Up => {
-                            if app.scroll > 0 {
-                                app.scroll -= 1;
-                            }
+                        KeyCode::Up if app.scroll > 0 => {
+                            app.scroll -= 1;
                         },
-                        KeyCode::Down => {
-                            if !app.filtered_logs.is_empty() && app.scroll < app.filtered_logs.len() - 1 {
-                                app.scroll += 1;
-                            }
+                        KeyCode::Down if !app.filtered_logs.is_empty() && app.scroll < app.filtered_logs.len() - 1 => {
+                            app.scroll += 1;
                         },
                         KeyCode::PageUp => {
                             if app.scroll > 10 {
@@ -72,22 +84,18 @@ pub fn handle_events(
                                 app.scroll = 0;
                             }
                         },
-                        KeyCode::PageDown => {
-                            if !app.filtered_logs.is_empty() {
-                                if app.scroll + 10 < app.filtered_logs.len() - 1 {
-                                    app.scroll += 10;
-                                } else {
-                                    app.scroll = app.filtered_logs.len() - 1;
-                                }
+                        KeyCode::PageDown if !app.filtered_logs.is_empty() => {
+                            if app.scroll + 10 < app.filtered_logs.len() - 1 {
+                                app.scroll += 10;
+                            } else {
+                                app.scroll = app.filtered_logs.len() - 1;
                             }
                         },
                         KeyCode::Home => {
                             app.scroll = 0;
                         },
-                        KeyCode::End => {
-                            if !app.filtered_logs.is_empty() {
-                                app.scroll = app.filtered_logs.len() - 1;
-                            }
+                        KeyCode::End if !app.filtered_logs.is_empty() => {
+                            app.scroll = app.filtered_logs.len() - 1;
                         },
                         _ => {}
                     },
@@ -118,4 +126,91 @@ pub fn handle_events(
     }
     
     true // Continue the loop
-} 
\ No newline at end of file
+}
+
+// Handle a key in the Server Info tab, returning false for keys it doesn't use
+fn handle_server_info_key(app: &mut AppUi, code: KeyCode, command_tx: &mpsc::Sender<UiCommand>) -> bool {
+    let Some(count) = app.server_info.as_ref().map(|info| info.endpoints.len()) else {
+        return false;
+    };
+    let last = count.saturating_sub(1);
+
+    match code {
+        KeyCode::Up => {
+            app.endpoint_selected = app.endpoint_selected.saturating_sub(1);
+        },
+        KeyCode::Down => {
+            app.endpoint_selected = (app.endpoint_selected + 1).min(last);
+        },
+        KeyCode::Home => {
+            app.endpoint_selected = 0;
+        },
+        KeyCode::End => {
+            app.endpoint_selected = last;
+        },
+        KeyCode::Char('d') => {
+            let selected = app.server_info.as_ref().and_then(|info| info.endpoints.get(app.endpoint_selected).cloned());
+            let Some(endpoint) = selected else {
+                return true;
+            };
+            if endpoint.faults.is_none() {
+                let message = format!("{} has no faults configured", endpoint.key);
+                app.add_log(LogEvent::warn(message));
+                return true;
+            }
+            let command = UiCommand::ToggleFaults { endpoint: endpoint.key };
+            command_tx.send(command).unwrap_or_else(|_| {
+                app.add_log(LogEvent::warn("Fault injection is not available"));
+            });
+        },
+        _ => return false,
+    }
+
+    true
+}
+
+// Handle a key in the Requests tab, returning false for keys it doesn't use
+fn handle_requests_key(app: &mut AppUi, code: KeyCode) -> bool {
+    let count = app.visible_requests().len();
+    let last = count.saturating_sub(1);
+
+    match code {
+        KeyCode::Up => {
+            app.request_selected = app.request_selected.saturating_sub(1);
+        },
+        KeyCode::Down => {
+            app.request_selected = (app.request_selected + 1).min(last);
+        },
+        KeyCode::PageUp => {
+            app.request_selected = app.request_selected.saturating_sub(10);
+        },
+        KeyCode::PageDown => {
+            app.request_selected = (app.request_selected + 10).min(last);
+        },
+        KeyCode::Home => {
+            app.request_selected = 0;
+        },
+        KeyCode::End => {
+            app.request_selected = last;
+        },
+        KeyCode::Enter if count > 0 => {
+            app.show_request_detail = !app.show_request_detail;
+        },
+        KeyCode::Esc => {
+            app.show_request_detail = false;
+        },
+        KeyCode::Char('x') => {
+            app.cycle_request_status_filter();
+        },
+        KeyCode::Char('c') => {
+            app.clear_requests();
+        },
+        KeyCode::Char(c @ '1'..='7') => {
+            let index = c as usize - '1' as usize;
+            app.sort_requests_by(RequestColumn::ALL[index]);
+        },
+        _ => return false,
+    }
+
+    true
+}